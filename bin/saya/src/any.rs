@@ -1,27 +1,241 @@
 use anyhow::Result;
 use saya_core::{
-    block_ingestor::BlockInfo,
+    block_ingestor::{
+        BlockInfo, BlockIngestor, BlockIngestorBuilder, FinalityPolicy, IngestionHandle,
+        PollingBlockIngestor, PollingBlockIngestorBuilder, SubscriptionBlockIngestor,
+        SubscriptionBlockIngestorBuilder,
+    },
+    pie_generator::{
+        CairoPie, FilePieGenerator, InProcessPieGenerator, PieGenerator, SubprocessPieGenerator,
+    },
     prover::{
-        AtlanticLayoutBridgeProver, AtlanticLayoutBridgeProverBuilder, MockLayoutBridgeProver,
-        MockLayoutBridgeProverBuilder, Prover, ProverBuilder, SnosProof,
+        AtlanticLayoutBridgeProver, AtlanticLayoutBridgeProverBuilder, AtlanticOneShotProver,
+        AtlanticProof, AtlanticSnosProver, AtlanticSnosProverBuilder, HttpProverLayoutBridgeProver,
+        HttpProverLayoutBridgeProverBuilder, HttpProverOneShotProver, HttpProverSnosProver,
+        HttpProverSnosProverBuilder, MockLayoutBridgeProver, MockLayoutBridgeProverBuilder, Prover,
+        ProverBuilder, SnosProof,
     },
     service::{Daemon, ShutdownHandle},
     storage::PersistantStorage,
 };
 use tokio::sync::mpsc::{Receiver, Sender};
 
+#[derive(Debug)]
+pub enum AnyBlockIngestor<G, DB> {
+    Polling(PollingBlockIngestor<G, DB>),
+    Subscription(SubscriptionBlockIngestor<G, DB>),
+}
+
+#[derive(Debug)]
+pub enum AnyBlockIngestorBuilder<G, DB> {
+    Polling(PollingBlockIngestorBuilder<G, DB>),
+    Subscription(SubscriptionBlockIngestorBuilder<G, DB>),
+}
+
+#[derive(Debug, Clone)]
+pub enum AnyPieGenerator {
+    InProcess(InProcessPieGenerator),
+    Subprocess(SubprocessPieGenerator),
+    File(FilePieGenerator),
+}
+
+#[derive(Debug)]
+pub enum AnySnosProver<P, DB> {
+    Atlantic(AtlanticSnosProver<P, DB>),
+    HttpProver(HttpProverSnosProver<P, DB>),
+}
+
+#[derive(Debug)]
+pub enum AnySnosProverBuilder<P, DB> {
+    Atlantic(AtlanticSnosProverBuilder<P, DB>),
+    HttpProver(HttpProverSnosProverBuilder<P, DB>),
+}
+
 #[derive(Debug)]
 pub enum AnyLayoutBridgeProver<DB> {
     Atlantic(AtlanticLayoutBridgeProver<DB>),
+    HttpProver(HttpProverLayoutBridgeProver<DB>),
     Mock(MockLayoutBridgeProver),
 }
 
 #[derive(Debug)]
 pub enum AnyLayoutBridgeProverBuilder<DB> {
     Atlantic(AtlanticLayoutBridgeProverBuilder<DB>),
+    HttpProver(HttpProverLayoutBridgeProverBuilder<DB>),
     Mock(MockLayoutBridgeProverBuilder),
 }
 
+#[derive(Debug)]
+pub enum AnyOneShotProver<DB> {
+    Atlantic(AtlanticOneShotProver<DB>),
+    HttpProver(HttpProverOneShotProver<DB>),
+}
+
+impl<G, DB> AnyBlockIngestorBuilder<G, DB> {
+    pub fn ingestion_handle(self, ingestion_handle: IngestionHandle) -> Self {
+        match self {
+            Self::Polling(inner) => Self::Polling(inner.ingestion_handle(ingestion_handle)),
+            Self::Subscription(inner) => {
+                Self::Subscription(inner.ingestion_handle(ingestion_handle))
+            }
+        }
+    }
+
+    pub fn finality_policy(self, finality_policy: FinalityPolicy) -> Self {
+        match self {
+            Self::Polling(inner) => Self::Polling(inner.finality_policy(finality_policy)),
+            Self::Subscription(inner) => Self::Subscription(inner.finality_policy(finality_policy)),
+        }
+    }
+
+    pub fn from_block(self, from_block: u64) -> Self {
+        match self {
+            Self::Polling(inner) => Self::Polling(inner.from_block(from_block)),
+            Self::Subscription(inner) => Self::Subscription(inner.from_block(from_block)),
+        }
+    }
+
+    pub fn to_block(self, to_block: u64) -> Self {
+        match self {
+            Self::Polling(inner) => Self::Polling(inner.to_block(to_block)),
+            Self::Subscription(inner) => Self::Subscription(inner.to_block(to_block)),
+        }
+    }
+}
+
+impl PieGenerator for AnyPieGenerator {
+    async fn generate_pie(&self, block_number: u64) -> Result<CairoPie> {
+        match self {
+            Self::InProcess(inner) => inner.generate_pie(block_number).await,
+            Self::Subprocess(inner) => inner.generate_pie(block_number).await,
+            Self::File(inner) => inner.generate_pie(block_number).await,
+        }
+    }
+}
+
+impl<G, DB> BlockIngestor for AnyBlockIngestor<G, DB>
+where
+    G: PieGenerator + Send + Sync + Clone + 'static,
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    fn end_block(&self) -> Option<u64> {
+        match self {
+            Self::Polling(inner) => inner.end_block(),
+            Self::Subscription(inner) => inner.end_block(),
+        }
+    }
+}
+
+impl<G, DB> Daemon for AnyBlockIngestor<G, DB>
+where
+    G: PieGenerator + Send + Sync + Clone + 'static,
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    fn shutdown_handle(&self) -> ShutdownHandle {
+        match self {
+            Self::Polling(inner) => inner.shutdown_handle(),
+            Self::Subscription(inner) => inner.shutdown_handle(),
+        }
+    }
+
+    fn start(self) {
+        match self {
+            Self::Polling(inner) => inner.start(),
+            Self::Subscription(inner) => inner.start(),
+        }
+    }
+}
+
+impl<G, DB> BlockIngestorBuilder for AnyBlockIngestorBuilder<G, DB>
+where
+    G: PieGenerator + Send + Sync + Clone + 'static,
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    type Ingestor = AnyBlockIngestor<G, DB>;
+
+    fn build(self) -> Result<Self::Ingestor> {
+        Ok(match self {
+            Self::Polling(inner) => AnyBlockIngestor::Polling(inner.build()?),
+            Self::Subscription(inner) => AnyBlockIngestor::Subscription(inner.build()?),
+        })
+    }
+
+    fn start_block(self, start_block: u64) -> Self {
+        match self {
+            Self::Polling(inner) => Self::Polling(inner.start_block(start_block)),
+            Self::Subscription(inner) => Self::Subscription(inner.start_block(start_block)),
+        }
+    }
+
+    fn channel(self, channel: Sender<BlockInfo>) -> Self {
+        match self {
+            Self::Polling(inner) => Self::Polling(inner.channel(channel)),
+            Self::Subscription(inner) => Self::Subscription(inner.channel(channel)),
+        }
+    }
+}
+
+impl<P, DB> Prover for AnySnosProver<P, DB>
+where
+    P: AtlanticProof + Send + Sync + 'static,
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    type Statement = BlockInfo;
+    type BlockInfo = SnosProof<P>;
+}
+
+impl<P, DB> Daemon for AnySnosProver<P, DB>
+where
+    P: AtlanticProof + Send + Sync + 'static,
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    fn shutdown_handle(&self) -> ShutdownHandle {
+        match self {
+            Self::Atlantic(inner) => inner.shutdown_handle(),
+            Self::HttpProver(inner) => inner.shutdown_handle(),
+        }
+    }
+
+    fn start(self) {
+        match self {
+            Self::Atlantic(inner) => inner.start(),
+            Self::HttpProver(inner) => inner.start(),
+        }
+    }
+}
+
+impl<P, DB> ProverBuilder for AnySnosProverBuilder<P, DB>
+where
+    P: AtlanticProof + Send + Sync + 'static,
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    type Prover = AnySnosProver<P, DB>;
+
+    fn build(self) -> Result<Self::Prover> {
+        Ok(match self {
+            Self::Atlantic(inner) => AnySnosProver::Atlantic(inner.build()?),
+            Self::HttpProver(inner) => AnySnosProver::HttpProver(inner.build()?),
+        })
+    }
+
+    fn statement_channel(
+        self,
+        block_channel: Receiver<<Self::Prover as Prover>::Statement>,
+    ) -> Self {
+        match self {
+            Self::Atlantic(inner) => Self::Atlantic(inner.statement_channel(block_channel)),
+            Self::HttpProver(inner) => Self::HttpProver(inner.statement_channel(block_channel)),
+        }
+    }
+
+    fn proof_channel(self, proof_channel: Sender<<Self::Prover as Prover>::BlockInfo>) -> Self {
+        match self {
+            Self::Atlantic(inner) => Self::Atlantic(inner.proof_channel(proof_channel)),
+            Self::HttpProver(inner) => Self::HttpProver(inner.proof_channel(proof_channel)),
+        }
+    }
+}
+
 impl<DB> Prover for AnyLayoutBridgeProver<DB>
 where
     DB: PersistantStorage + Send + Sync + Clone + 'static,
@@ -37,6 +251,7 @@ where
     fn shutdown_handle(&self) -> ShutdownHandle {
         match self {
             Self::Atlantic(inner) => inner.shutdown_handle(),
+            Self::HttpProver(inner) => inner.shutdown_handle(),
             Self::Mock(inner) => inner.shutdown_handle(),
         }
     }
@@ -44,6 +259,7 @@ where
     fn start(self) {
         match self {
             Self::Atlantic(inner) => inner.start(),
+            Self::HttpProver(inner) => inner.start(),
             Self::Mock(inner) => inner.start(),
         }
     }
@@ -58,6 +274,7 @@ where
     fn build(self) -> Result<Self::Prover> {
         Ok(match self {
             Self::Atlantic(inner) => AnyLayoutBridgeProver::Atlantic(inner.build()?),
+            Self::HttpProver(inner) => AnyLayoutBridgeProver::HttpProver(inner.build()?),
             Self::Mock(inner) => AnyLayoutBridgeProver::Mock(inner.build()?),
         })
     }
@@ -68,6 +285,7 @@ where
     ) -> Self {
         match self {
             Self::Atlantic(inner) => Self::Atlantic(inner.statement_channel(block_channel)),
+            Self::HttpProver(inner) => Self::HttpProver(inner.statement_channel(block_channel)),
             Self::Mock(inner) => Self::Mock(inner.statement_channel(block_channel)),
         }
     }
@@ -75,7 +293,44 @@ where
     fn proof_channel(self, proof_channel: Sender<<Self::Prover as Prover>::BlockInfo>) -> Self {
         match self {
             Self::Atlantic(inner) => Self::Atlantic(inner.proof_channel(proof_channel)),
+            Self::HttpProver(inner) => Self::HttpProver(inner.proof_channel(proof_channel)),
             Self::Mock(inner) => Self::Mock(inner.proof_channel(proof_channel)),
         }
     }
 }
+
+impl<DB> AnyOneShotProver<DB>
+where
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    pub async fn prove_snos(&self, block_number: u32, compressed_pie: Vec<u8>) -> Result<String> {
+        match self {
+            Self::Atlantic(prover) => prover.prove_snos(block_number, compressed_pie).await,
+            Self::HttpProver(prover) => prover.prove_snos(block_number, compressed_pie).await,
+        }
+    }
+
+    /// Returns the compressed layout bridge PIE, if the prover produces one, and the raw proof
+    /// JSON.
+    pub async fn prove_layout_bridge(
+        &self,
+        block_number: u32,
+        layout_bridge: Vec<u8>,
+        snos_proof: &str,
+    ) -> Result<(Option<Vec<u8>>, String)> {
+        match self {
+            Self::Atlantic(prover) => {
+                let (pie, proof) = prover
+                    .prove_layout_bridge(block_number, layout_bridge, snos_proof)
+                    .await?;
+                Ok((Some(pie), proof))
+            }
+            Self::HttpProver(prover) => {
+                let proof = prover
+                    .prove_layout_bridge(block_number, layout_bridge, snos_proof)
+                    .await?;
+                Ok((None, proof))
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use clap::{Parser, Subcommand, ValueEnum};
+use saya_core::storage::{BlockStatus, PersistantStorage, SqliteDb};
+
+use crate::common::saya_db_path;
+
+#[derive(Debug, Parser)]
+pub struct Block {
+    #[clap(subcommand)]
+    command: Subcommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum Subcommands {
+    /// Queue a block for processing again, marking its latest failure as unhandled.
+    Retry(BlockArgs),
+    /// Reset a block to an earlier stage and queue it for processing again.
+    Reset(Reset),
+    /// Delete a block along with all its artifacts and queue it for processing from scratch.
+    Forget(BlockArgs),
+}
+
+#[derive(Debug, Parser)]
+struct BlockArgs {
+    /// Number of the block
+    block_number: u32,
+    /// Path to the database directory
+    #[clap(long, env)]
+    db_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Parser)]
+struct Reset {
+    #[clap(flatten)]
+    block: BlockArgs,
+    /// Stage to process the block again from
+    #[clap(long, value_enum)]
+    to: Stage,
+}
+
+/// Pipeline stages a block can be reset to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Stage {
+    /// Generate the SNOS PIE again. Clears all artifacts.
+    Pie,
+    /// Generate the SNOS proof again. Clears all proofs and query IDs.
+    SnosProof,
+    /// Generate the layout bridge proof again. Clears the bridge proof and its query IDs.
+    BridgeProof,
+    /// Settle the block again using its existing proofs.
+    Settlement,
+}
+
+impl Block {
+    pub async fn run(self) -> Result<()> {
+        match self.command {
+            Subcommands::Retry(args) => {
+                let db = args.open_db().await?;
+                db.requeue_block(args.block_number, "retried via CLI".to_string())
+                    .await?;
+                log::info!(block_number = args.block_number; "Block queued for retry");
+            }
+            Subcommands::Reset(reset) => {
+                let db = reset.block.open_db().await?;
+                let status = reset.to.status();
+                db.reset_block(reset.block.block_number, status.clone())
+                    .await?;
+                db.requeue_block(reset.block.block_number, "reset via CLI".to_string())
+                    .await?;
+                log::info!(
+                    block_number = reset.block.block_number, status:% = status;
+                    "Block reset and queued for processing"
+                );
+            }
+            Subcommands::Forget(args) => {
+                let db = args.open_db().await?;
+                db.forget_block(args.block_number).await?;
+                db.requeue_block(args.block_number, "forgotten via CLI".to_string())
+                    .await?;
+                log::info!(
+                    block_number = args.block_number;
+                    "Block forgotten and queued for processing"
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl BlockArgs {
+    async fn open_db(&self) -> Result<SqliteDb> {
+        let path = saya_db_path(self.db_dir.as_deref());
+        // `SqliteDb::new` would otherwise silently create an empty database.
+        if !Path::new(&path).try_exists()? {
+            anyhow::bail!("database file not found: {}", path);
+        }
+        SqliteDb::new(&path).await
+    }
+}
+
+impl Stage {
+    /// Gets the status the block is set to, i.e. the status right before the stage.
+    fn status(self) -> BlockStatus {
+        match self {
+            Self::Pie => BlockStatus::Mined,
+            Self::SnosProof => BlockStatus::SnosPieGenerated,
+            Self::BridgeProof => BlockStatus::SnosProofGenerated,
+            Self::Settlement => BlockStatus::BridgeProofGenerated,
+        }
+    }
+}
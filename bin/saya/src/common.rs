@@ -1,5 +1,46 @@
+use std::{
+    borrow::Cow,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Result;
+use clap::{Parser, ValueEnum};
+use metrics_exporter_prometheus::PrometheusBuilder;
+use saya_core::{
+    admin::AdminServerBuilder,
+    block_ingestor::{
+        FinalityPolicy, IngestionHandle, PollingBlockIngestorBuilder,
+        SubscriptionBlockIngestorBuilder,
+    },
+    metrics::{Label, StorageMetricsReporter},
+    pie_generator::{
+        FilePieGenerator, InProcessPieGenerator, LayoutName, PieGenerationLimits,
+        SubprocessPieGenerator, DEFAULT_RPC_VERSION,
+    },
+    prover::{
+        AtlanticLayoutBridgeProverBuilder, AtlanticNetwork, AtlanticOneShotProver, AtlanticProof,
+        AtlanticSnosProverBuilder, HttpProverLayoutBridgeProverBuilder, HttpProverOneShotProver,
+        HttpProverSnosProverBuilder, MockLayoutBridgeProverBuilder,
+    },
+    service::{Daemon, ExitStatus, RestartPolicy, ShutdownHandle},
+    settlement::{FeeConfig, PiltoverSettlementBackendBuilder, SettlementBudget},
+    storage::PersistantStorage,
+};
+use starknet_types_core::felt::Felt;
+use url::Url;
+
+use crate::any::{
+    AnyBlockIngestorBuilder, AnyLayoutBridgeProverBuilder, AnyOneShotProver, AnyPieGenerator,
+    AnySnosProverBuilder,
+};
+
 pub const SAYA_DB_PATH: &str = "saya.db";
 
+/// Interval between two reports of metrics derived from the database.
+const STORAGE_METRICS_INTERVAL: Duration = Duration::from_secs(15);
+
 // All time values are in seconds
 const SNOS_PROOF_GENERATION_TIME: u32 = 15 * 60;
 const LAYOUT_BRIDGE_PROOF_GENERATION_TIME: u32 = 30 * 60;
@@ -41,9 +82,692 @@ pub fn calculate_workers_per_stage(num_blocks_in_pipeline: usize) -> [usize; NUM
 
     workers_count
 }
+/// Backend used for generating SNOS and layout bridge proofs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProverBackend {
+    /// The hosted Atlantic prover service from Herodotus.
+    Atlantic,
+    /// A self-hosted http-prover instance.
+    HttpProver,
+}
+
+/// Starknet network on which Atlantic verifies layout bridge proofs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AtlanticL2Network {
+    Testnet,
+    Mainnet,
+}
+
+impl From<AtlanticL2Network> for AtlanticNetwork {
+    fn from(network: AtlanticL2Network) -> Self {
+        match network {
+            AtlanticL2Network::Testnet => Self::Testnet,
+            AtlanticL2Network::Mainnet => Self::Mainnet,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct ProverOptions {
+    /// Backend used for proving
+    #[clap(long, env, value_enum, default_value_t = ProverBackend::Atlantic)]
+    pub prover: ProverBackend,
+    /// Atlantic prover API key
+    #[clap(long, env)]
+    pub atlantic_key: Option<String>,
+    /// http-prover instance URL
+    #[clap(long, env)]
+    pub http_prover_url: Option<Url>,
+    /// http-prover access key, as a hex-encoded private key
+    #[clap(long, env)]
+    pub http_prover_key: Option<String>,
+}
+
+impl ProverOptions {
+    pub fn snos_prover_builder<P, DB>(
+        &self,
+        mock_snos_from_pie: bool,
+        db: DB,
+        worker_count: usize,
+    ) -> Result<AnySnosProverBuilder<P, DB>>
+    where
+        P: AtlanticProof + Send + Sync + 'static,
+        DB: PersistantStorage + Send + Sync + Clone + 'static,
+    {
+        Ok(match self.prover {
+            ProverBackend::Atlantic => {
+                // The API key is never used when mocking proofs from the PIE.
+                let api_key = if mock_snos_from_pie {
+                    self.atlantic_key.clone().unwrap_or_default()
+                } else {
+                    self.atlantic_key()?
+                };
+
+                AnySnosProverBuilder::Atlantic(AtlanticSnosProverBuilder::new(
+                    api_key,
+                    mock_snos_from_pie,
+                    db,
+                    worker_count,
+                ))
+            }
+            ProverBackend::HttpProver => {
+                if mock_snos_from_pie {
+                    anyhow::bail!(
+                        "invalid config: `--mock-snos-from-pie` is only supported with the Atlantic prover"
+                    );
+                }
+
+                let (url, access_key) = self.http_prover()?;
+                AnySnosProverBuilder::HttpProver(HttpProverSnosProverBuilder::new(
+                    url,
+                    access_key,
+                    db,
+                    worker_count,
+                ))
+            }
+        })
+    }
+
+    pub fn layout_bridge_prover_builder<L, DB>(
+        &self,
+        layout_bridge: L,
+        db: DB,
+        workers_count: usize,
+        l2_verification: Option<AtlanticL2Network>,
+    ) -> Result<AnyLayoutBridgeProverBuilder<DB>>
+    where
+        L: Into<Cow<'static, [u8]>>,
+        DB: PersistantStorage + Send + Sync + Clone + 'static,
+    {
+        Ok(match self.prover {
+            ProverBackend::Atlantic => {
+                let builder = AtlanticLayoutBridgeProverBuilder::new(
+                    self.atlantic_key()?,
+                    layout_bridge,
+                    db,
+                    workers_count,
+                );
+                AnyLayoutBridgeProverBuilder::Atlantic(match l2_verification {
+                    Some(network) => builder.l2_verification(network.into()),
+                    None => builder,
+                })
+            }
+            ProverBackend::HttpProver => {
+                if l2_verification.is_some() {
+                    anyhow::bail!(
+                        "invalid config: `--atlantic-l2-verification` is only supported with the Atlantic prover"
+                    );
+                }
+
+                let (url, access_key) = self.http_prover()?;
+                AnyLayoutBridgeProverBuilder::HttpProver(HttpProverLayoutBridgeProverBuilder::new(
+                    url,
+                    access_key,
+                    layout_bridge,
+                    db,
+                    workers_count,
+                ))
+            }
+        })
+    }
+
+    pub fn one_shot_prover<DB>(&self, db: DB) -> Result<AnyOneShotProver<DB>>
+    where
+        DB: PersistantStorage + Send + Sync + Clone + 'static,
+    {
+        Ok(match self.prover {
+            ProverBackend::Atlantic => {
+                AnyOneShotProver::Atlantic(AtlanticOneShotProver::new(self.atlantic_key()?, db))
+            }
+            ProverBackend::HttpProver => {
+                let (url, access_key) = self.http_prover()?;
+                AnyOneShotProver::HttpProver(HttpProverOneShotProver::new(url, access_key, db))
+            }
+        })
+    }
+
+    fn atlantic_key(&self) -> Result<String> {
+        self.atlantic_key.clone().ok_or_else(|| {
+            anyhow::anyhow!("invalid config: `--atlantic-key` must be provided when using Atlantic")
+        })
+    }
+
+    fn http_prover(&self) -> Result<(Url, String)> {
+        match (&self.http_prover_url, &self.http_prover_key) {
+            (Some(url), Some(key)) => Ok((url.clone(), key.clone())),
+            _ => anyhow::bail!(
+                "invalid config: `--http-prover-url` and `--http-prover-key` must be provided when using http-prover"
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct LayoutBridgeOptions {
+    /// Path to the compiled Cairo verifier program
+    #[clap(long, env)]
+    pub layout_bridge_program: Option<PathBuf>,
+    /// Settlement network integrity contract address
+    #[clap(long, env)]
+    pub settlement_integrity_address: Option<Felt>,
+    /// Generate mock layout bridge proof and skip on-chain fact registration if provided
+    #[clap(long, env)]
+    pub mock_layout_bridge_program_hash: Option<Felt>,
+    /// Verify layout bridge proofs locally before on-chain fact registration. Blocks with invalid
+    /// proofs are proven again.
+    #[clap(long)]
+    pub verify_proofs_locally: bool,
+    /// Have Atlantic verify layout bridge proofs on the given Starknet network and wait for their
+    /// facts to be registered on integrity, instead of sending the verification transactions from
+    /// the settlement account
+    #[clap(long, env, value_enum)]
+    pub atlantic_l2_verification: Option<AtlanticL2Network>,
+    /// Time in seconds to wait for Atlantic to register the fact of a proof before sending the
+    /// verification transactions from the settlement account
+    #[clap(long, env, default_value_t = 3600)]
+    pub atlantic_l2_verification_timeout: u64,
+}
+
+impl LayoutBridgeOptions {
+    /// Reads the `layout_bridge` program, which is not needed when mocking layout bridge proofs.
+    pub fn program(&self) -> Result<Option<Vec<u8>>> {
+        match (
+            self.mock_layout_bridge_program_hash,
+            &self.layout_bridge_program,
+        ) {
+            // We don't need the `layout_bridge` program in this case but it's okay if it's given.
+            (Some(_), _) => Ok(None),
+            (None, Some(layout_bridge_program)) => Ok(Some(std::fs::read(layout_bridge_program)?)),
+            (None, None) => anyhow::bail!(
+                "invalid config: `--layout-bridge-program` must be provided unless `--mock-layout-bridge-program-hash` is used"
+            ),
+        }
+    }
+
+    /// Creates the layout bridge prover from the `program` returned by [`Self::program`].
+    pub fn prover_builder<DB>(
+        &self,
+        prover: &ProverOptions,
+        program: Option<&[u8]>,
+        db: DB,
+        workers_count: usize,
+    ) -> Result<AnyLayoutBridgeProverBuilder<DB>>
+    where
+        DB: PersistantStorage + Send + Sync + Clone + 'static,
+    {
+        match (self.mock_layout_bridge_program_hash, program) {
+            (Some(mock_layout_bridge_program_hash), _) => Ok(AnyLayoutBridgeProverBuilder::Mock(
+                MockLayoutBridgeProverBuilder::new(mock_layout_bridge_program_hash),
+            )),
+            (None, Some(program)) => prover.layout_bridge_prover_builder(
+                program.to_vec(),
+                db,
+                workers_count,
+                self.atlantic_l2_verification,
+            ),
+            (None, None) => anyhow::bail!("`layout_bridge` program not read"),
+        }
+    }
+
+    /// Configures how the settlement backend gets the facts of layout bridge proofs registered.
+    pub fn fact_registration<DB>(
+        &self,
+        settlement_builder: PiltoverSettlementBackendBuilder<DB>,
+    ) -> Result<PiltoverSettlementBackendBuilder<DB>> {
+        Ok(
+            match (
+                self.mock_layout_bridge_program_hash,
+                self.settlement_integrity_address,
+            ) {
+                // We don't need `integrity` address but it's okay if it's given.
+                (Some(_), _) => settlement_builder.skip_fact_registration(true),
+                (None, Some(integrity_address)) => settlement_builder
+                    .integrity_address(integrity_address)
+                    .verify_proofs_locally(self.verify_proofs_locally)
+                    .fact_registered_by_prover(self.atlantic_l2_verification.is_some())
+                    .fact_timeout(Duration::from_secs(self.atlantic_l2_verification_timeout)),
+                (None, None) => anyhow::bail!(
+                    "invalid config: `integrity` address must be \
+                    provided unless `--mock-layout-bridge-program-hash` is used"
+                ),
+            },
+        )
+    }
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct SettlementOptions {
+    /// Maximum number of consecutive blocks settled together in a single transaction
+    #[clap(long, env, default_value_t = 1)]
+    pub settlement_batch_size: usize,
+    /// Time in seconds to wait for more blocks to fill a settlement batch
+    #[clap(long, env, default_value_t = 0)]
+    pub settlement_batch_window: u64,
+    /// Settlement fee options
+    #[clap(flatten)]
+    pub fees: FeeOptions,
+}
+
+impl SettlementOptions {
+    /// Applies the batching and fee options to the settlement backend.
+    pub fn apply<DB>(
+        &self,
+        settlement_builder: PiltoverSettlementBackendBuilder<DB>,
+    ) -> PiltoverSettlementBackendBuilder<DB> {
+        settlement_builder
+            .batch_size(self.settlement_batch_size)
+            .batch_window(Duration::from_secs(self.settlement_batch_window))
+            .fee_config(self.fees.config())
+            .budget(self.fees.budget())
+    }
+}
+
+/// Creates a block ingestor subscribing to new blocks over WebSocket if `ws_url` is set, or polling
+/// `rpc_url` otherwise.
+pub fn block_ingestor_builder<G, DB>(
+    rpc_url: Url,
+    ws_url: Option<Url>,
+    pie_generator: G,
+    db: DB,
+    workers_count: usize,
+) -> AnyBlockIngestorBuilder<G, DB> {
+    match ws_url {
+        Some(ws_url) => {
+            AnyBlockIngestorBuilder::Subscription(SubscriptionBlockIngestorBuilder::new(
+                rpc_url,
+                ws_url,
+                pie_generator,
+                db,
+                workers_count,
+            ))
+        }
+        None => AnyBlockIngestorBuilder::Polling(PollingBlockIngestorBuilder::new(
+            rpc_url,
+            pie_generator,
+            db,
+            workers_count,
+        )),
+    }
+}
+
+/// Gets the path of the Saya database file inside `db_dir`, or in the working directory if not set.
+pub fn saya_db_path(db_dir: Option<&Path>) -> String {
+    db_dir
+        .map(|db_dir| format!("{}/{}", db_dir.display(), SAYA_DB_PATH))
+        .unwrap_or_else(|| SAYA_DB_PATH.to_string())
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct MetricsOptions {
+    /// Address for serving Prometheus metrics over HTTP. Metrics are disabled if not set
+    #[clap(long = "metrics.addr", env = "METRICS_ADDR")]
+    pub addr: Option<SocketAddr>,
+}
+
+impl MetricsOptions {
+    /// Installs the Prometheus exporter if enabled. Returns whether metrics are enabled.
+    pub fn install(&self) -> Result<bool> {
+        let Some(addr) = self.addr else {
+            return Ok(false);
+        };
+
+        PrometheusBuilder::new()
+            .with_http_listener(addr)
+            .install()?;
+        saya_core::metrics::describe();
+
+        log::info!(addr:% = addr; "Serving metrics");
+        Ok(true)
+    }
+
+    /// Installs the Prometheus exporter if enabled, and starts reporting metrics derived from `db`.
+    /// Returns the shutdown handle of the reporter if started.
+    pub fn install_with_storage<DB>(&self, db: DB) -> Result<Option<ShutdownHandle>>
+    where
+        DB: PersistantStorage + Send + Sync + 'static,
+    {
+        Ok(if self.install()? {
+            Some(start_storage_metrics_reporter(db, Vec::new()))
+        } else {
+            None
+        })
+    }
+}
+
+/// Starts reporting metrics derived from `db`, with `labels` added to all of them. Returns the
+/// shutdown handle of the reporter.
+pub fn start_storage_metrics_reporter<DB>(db: DB, labels: Vec<Label>) -> ShutdownHandle
+where
+    DB: PersistantStorage + Send + Sync + 'static,
+{
+    let reporter = StorageMetricsReporter::new(db, STORAGE_METRICS_INTERVAL).labels(labels);
+    let shutdown_handle = reporter.shutdown_handle();
+    reporter.start();
+    shutdown_handle
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct AdminOptions {
+    /// Address for serving the admin HTTP API. The API is disabled if not set
+    #[clap(long = "admin.addr", env = "ADMIN_ADDR")]
+    pub addr: Option<SocketAddr>,
+}
+
+impl AdminOptions {
+    /// Starts the admin API server if enabled.
+    pub fn start<DB>(&self, db: DB, ingestion_handle: IngestionHandle) -> Result<()>
+    where
+        DB: PersistantStorage + Clone + Send + Sync + 'static,
+    {
+        if let Some(addr) = self.addr {
+            AdminServerBuilder::new(addr, db)
+                .ingestion_handle(ingestion_handle)
+                .build()?
+                .start();
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct PieOptions {
+    /// Layout the Starknet OS program is run with
+    #[clap(
+        long = "pie.layout",
+        env = "PIE_LAYOUT",
+        default_value = "all_cairo",
+        value_parser = parse_layout
+    )]
+    pub layout: LayoutName,
+    /// Version of the rollup Starknet JSON-RPC API used for generating PIEs
+    #[clap(long = "pie.rpc-version", env = "PIE_RPC_VERSION", default_value = DEFAULT_RPC_VERSION)]
+    pub rpc_version: String,
+    /// Generate each PIE in a separate `saya pie-worker` process instead of inside the Saya
+    /// process, so that crashes and memory usage are isolated
+    #[clap(long = "pie.isolate", conflicts_with_all = ["command", "dir"])]
+    pub isolate: bool,
+    /// Generate PIEs by running this program for each block instead of inside the Saya process.
+    /// The program is called with the block number and the path to write the zipped PIE to
+    #[clap(long = "pie.command", env = "PIE_COMMAND")]
+    pub command: Option<PathBuf>,
+    /// Argument passed to `--pie.command` before the block number. Can be repeated
+    #[clap(
+        long = "pie.command-arg",
+        requires = "command",
+        allow_hyphen_values = true
+    )]
+    pub command_args: Vec<String>,
+    /// Load PIEs from `block_<number>_pie.zip` files in this directory instead of generating them
+    #[clap(long = "pie.dir", env = "PIE_DIR", conflicts_with = "command")]
+    pub dir: Option<PathBuf>,
+    /// Maximum resident memory in MiB of a PIE generation process, which is killed when exceeding
+    /// it. Only enforced on Linux, with `--pie.isolate` or `--pie.command`
+    #[clap(long = "pie.max-rss", env = "PIE_MAX_RSS")]
+    pub max_rss: Option<u64>,
+    /// Time in seconds after which a PIE generation process is killed, with `--pie.isolate` or
+    /// `--pie.command`
+    #[clap(long = "pie.timeout", env = "PIE_TIMEOUT")]
+    pub timeout: Option<u64>,
+}
+
+impl PieOptions {
+    /// Builds the PIE generator for the rollup at `rpc_url`. The Starknet OS program is only read
+    /// when PIEs are generated inside the Saya process.
+    pub fn generator(&self, snos_program: &Path, rpc_url: &Url) -> Result<AnyPieGenerator> {
+        let limits = PieGenerationLimits {
+            max_rss: self.max_rss.map(|max_rss| max_rss * 1024 * 1024),
+            timeout: self.timeout.map(Duration::from_secs),
+        };
+
+        if self.isolate {
+            let args = vec![
+                "pie-worker".to_string(),
+                "--rollup-rpc".to_string(),
+                rpc_url.to_string(),
+                "--snos-program".to_string(),
+                snos_program.display().to_string(),
+                "--layout".to_string(),
+                self.layout.to_str().to_string(),
+                "--rpc-version".to_string(),
+                self.rpc_version.clone(),
+            ];
+            return Ok(AnyPieGenerator::Subprocess(
+                SubprocessPieGenerator::new(std::env::current_exe()?)
+                    .args(args)
+                    .limits(limits),
+            ));
+        }
+
+        if let Some(command) = &self.command {
+            return Ok(AnyPieGenerator::Subprocess(
+                SubprocessPieGenerator::new(command.clone())
+                    .args(self.command_args.clone())
+                    .limits(limits),
+            ));
+        }
+
+        if limits.max_rss.is_some() || limits.timeout.is_some() {
+            anyhow::bail!(
+                "invalid config: `--pie.max-rss` and `--pie.timeout` require `--pie.isolate` or `--pie.command`"
+            );
+        }
+
+        Ok(match &self.dir {
+            Some(dir) => AnyPieGenerator::File(FilePieGenerator::new(dir.clone())),
+            None => AnyPieGenerator::InProcess(
+                InProcessPieGenerator::new(
+                    std::fs::read(snos_program)?,
+                    rpc_url,
+                    &self.rpc_version,
+                )?
+                .layout(self.layout),
+            ),
+        })
+    }
+}
+
+pub fn parse_layout(name: &str) -> Result<LayoutName, String> {
+    [
+        LayoutName::plain,
+        LayoutName::small,
+        LayoutName::dex,
+        LayoutName::recursive,
+        LayoutName::starknet,
+        LayoutName::starknet_with_keccak,
+        LayoutName::recursive_large_output,
+        LayoutName::recursive_with_poseidon,
+        LayoutName::all_solidity,
+        LayoutName::all_cairo,
+        LayoutName::dynamic,
+    ]
+    .into_iter()
+    .find(|layout| layout.to_str() == name)
+    .ok_or_else(|| format!("unknown layout `{}`", name))
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct FinalityOptions {
+    /// Number of blocks built on top of a rollup block before it's ingested
+    #[clap(
+        long = "finality.confirmations",
+        env = "FINALITY_CONFIRMATIONS",
+        default_value_t = 0
+    )]
+    pub confirmations: u64,
+    /// Only ingest rollup blocks once they're accepted on L1
+    #[clap(long = "finality.accepted-on-l1", conflicts_with = "confirmations")]
+    pub accepted_on_l1: bool,
+}
+
+impl FinalityOptions {
+    pub fn policy(&self) -> FinalityPolicy {
+        if self.accepted_on_l1 {
+            FinalityPolicy::AcceptedOnL1
+        } else {
+            FinalityPolicy::Confirmations(self.confirmations)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct BlockRangeOptions {
+    /// First rollup block to process. In persistent and sharding modes, blocks are settled in
+    /// order, so it must be the block following the last settled one
+    #[clap(long, env)]
+    pub from_block: Option<u64>,
+    /// Last rollup block to process. Saya exits once all blocks up to this one are processed
+    #[clap(long, env)]
+    pub to_block: Option<u64>,
+}
+
+impl BlockRangeOptions {
+    pub fn validate(&self) -> Result<()> {
+        if let (Some(from_block), Some(to_block)) = (self.from_block, self.to_block) {
+            if from_block > to_block {
+                anyhow::bail!(
+                    "invalid config: `--from-block` ({}) is after `--to-block` ({})",
+                    from_block,
+                    to_block
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct RestartOptions {
+    /// Maximum number of times the pipeline is restarted after a service exits unexpectedly. Set
+    /// to `0` to exit instead.
+    #[clap(
+        long = "restart.max-restarts",
+        env = "RESTART_MAX_RESTARTS",
+        default_value_t = 5
+    )]
+    pub max_restarts: usize,
+    /// Delay in seconds before restarting the pipeline
+    #[clap(
+        long = "restart.backoff",
+        env = "RESTART_BACKOFF",
+        default_value_t = 30
+    )]
+    pub backoff: u64,
+}
+
+impl RestartOptions {
+    pub fn policy(&self) -> RestartPolicy {
+        if self.max_restarts == 0 {
+            RestartPolicy::Escalate
+        } else {
+            RestartPolicy::Restart {
+                max_restarts: self.max_restarts,
+                backoff: Duration::from_secs(self.backoff),
+            }
+        }
+    }
+}
+
+/// Waits for a termination signal or for the service to exit by itself, then shuts the service
+/// down gracefully along with the auxiliary services running next to it, such as metrics reporters.
+pub async fn run_until_shutdown(
+    shutdown_handle: ShutdownHandle,
+    auxiliary_handles: Vec<ShutdownHandle>,
+    graceful_shutdown_timeout: Duration,
+) -> Result<()> {
+    let mut sigterm_handle =
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+    let ctrl_c_handle = tokio::signal::ctrl_c();
+
+    tokio::select! {
+        _ = sigterm_handle.recv() => {},
+        _ = ctrl_c_handle => {},
+        _ = shutdown_handle.exited() => {},
+    }
+
+    // Graceful shutdown
+    shutdown_handle.shutdown();
+    for handle in auxiliary_handles.iter() {
+        handle.shutdown();
+    }
+
+    let exited = async {
+        for handle in auxiliary_handles.iter() {
+            handle.exited().await;
+        }
+        shutdown_handle.exited().await
+    };
+
+    tokio::select! {
+        _ = tokio::time::sleep(graceful_shutdown_timeout) => {
+            Err(anyhow::anyhow!("timeout waiting for graceful shutdown"))
+        },
+        exit_status = exited => match exit_status {
+            ExitStatus::Finished => Ok(()),
+            exit_status => Err(anyhow::anyhow!("service exited unexpectedly: {:?}", exit_status)),
+        },
+    }
+}
+
 #[test]
 fn test_split_workers() {
     let num_blocks_in_pipeline = 110;
     let workers = calculate_workers_per_stage(num_blocks_in_pipeline);
     assert_eq!(workers, [36, 72, 3]);
 }
+
+#[derive(Debug, Clone, Parser)]
+pub struct FeeOptions {
+    /// Multiplier applied to the estimated gas amount and gas price of settlement transactions
+    #[clap(long = "fee.multiplier", env = "FEE_MULTIPLIER", default_value_t = 1.5)]
+    pub multiplier: f64,
+    /// Maximum fee in STRK spent on a block, for its proof verification and for its share of a
+    /// settlement transaction. Transactions exceeding it are not sent
+    #[clap(long = "fee.max-per-block", env = "FEE_MAX_PER_BLOCK")]
+    pub max_per_block: Option<f64>,
+    /// Time in seconds after which an unconfirmed transaction is sent again with a higher gas
+    /// price and tip. Transactions are never resubmitted if not set
+    #[clap(long = "fee.resubmit-timeout", env = "FEE_RESUBMIT_TIMEOUT")]
+    pub resubmit_timeout: Option<u64>,
+    /// Multiplier applied to the gas price and tip of a transaction each time it's resubmitted
+    #[clap(
+        long = "fee.bump-multiplier",
+        env = "FEE_BUMP_MULTIPLIER",
+        default_value_t = 1.2
+    )]
+    pub bump_multiplier: f64,
+    /// Tip per unit of gas offered to the sequencer, raised by the bump multiplier each time a
+    /// transaction is resubmitted
+    #[clap(long = "fee.tip", env = "FEE_TIP", default_value_t = 0)]
+    pub tip: u64,
+    /// Maximum fee in STRK spent per UTC day. Settlement is paused once reached
+    #[clap(long = "fee.max-daily", env = "FEE_MAX_DAILY")]
+    pub max_daily: Option<f64>,
+    /// STRK balance to always keep on the settlement account. Settlement is paused when the balance
+    /// would drop below it
+    #[clap(long = "fee.min-balance", env = "FEE_MIN_BALANCE")]
+    pub min_balance: Option<f64>,
+}
+
+impl FeeOptions {
+    pub fn config(&self) -> FeeConfig {
+        FeeConfig {
+            multiplier: self.multiplier,
+            max_fee_per_block: self.max_per_block.map(strk_to_fri),
+            resubmit_timeout: self.resubmit_timeout.map(Duration::from_secs),
+            bump_multiplier: self.bump_multiplier,
+            tip: self.tip,
+        }
+    }
+
+    pub fn budget(&self) -> SettlementBudget {
+        SettlementBudget {
+            max_daily_fee: self.max_daily.map(strk_to_fri),
+            min_balance: self.min_balance.map(strk_to_fri),
+        }
+    }
+}
+
+/// Converts an amount of STRK to FRI, STRK having 18 decimals.
+fn strk_to_fri(amount: f64) -> Felt {
+    Felt::from((amount * 1e18) as u128)
+}
@@ -0,0 +1,170 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
+
+use anyhow::Result;
+use clap::Parser;
+use saya_core::storage::{PersistantStorage, SettlementCost, SettlementCostKind, SqliteDb};
+use serde::Serialize;
+use starknet_types_core::felt::Felt;
+
+use crate::common::saya_db_path;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Reports the fees spent on settlement per block and per day, as recorded in the database.
+#[derive(Debug, Parser)]
+pub struct Costs {
+    /// Path to the database directory
+    #[clap(long, env)]
+    db_dir: Option<PathBuf>,
+    /// Print the report as JSON
+    #[clap(long)]
+    json: bool,
+}
+
+/// Amounts are in STRK.
+#[derive(Debug, Serialize)]
+struct CostReport {
+    blocks: Vec<BlockCost>,
+    days: Vec<DayCost>,
+    total: String,
+}
+
+#[derive(Debug, Serialize)]
+struct BlockCost {
+    block_number: u32,
+    integrity: String,
+    settlement: String,
+    total: String,
+}
+
+#[derive(Debug, Serialize)]
+struct DayCost {
+    /// UTC date, as `YYYY-MM-DD`.
+    date: String,
+    transactions: usize,
+    total: String,
+}
+
+impl Costs {
+    pub async fn run(self) -> Result<()> {
+        let db = SqliteDb::open_read_only(&saya_db_path(self.db_dir.as_deref())).await?;
+        let report = CostReport::new(&db.list_settlement_costs(0).await?);
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            report.print();
+        }
+
+        Ok(())
+    }
+}
+
+impl CostReport {
+    fn new(costs: &[SettlementCost]) -> Self {
+        // Integrity and settlement fees per block.
+        let mut blocks: BTreeMap<u32, (Felt, Felt)> = BTreeMap::new();
+        // Fees and transactions per day since the Unix epoch.
+        let mut days: BTreeMap<u64, (Felt, BTreeSet<Felt>)> = BTreeMap::new();
+
+        for cost in costs {
+            let block = blocks.entry(cost.block_number).or_default();
+            match cost.kind {
+                SettlementCostKind::Integrity => block.0 += cost.fee,
+                SettlementCostKind::Settlement => block.1 += cost.fee,
+            }
+
+            let day = days.entry(cost.timestamp / SECONDS_PER_DAY).or_default();
+            day.0 += cost.fee;
+            day.1.insert(cost.transaction_hash);
+        }
+
+        Self {
+            blocks: blocks
+                .into_iter()
+                .map(|(block_number, (integrity, settlement))| BlockCost {
+                    block_number,
+                    integrity: format_strk(integrity),
+                    settlement: format_strk(settlement),
+                    total: format_strk(integrity + settlement),
+                })
+                .collect(),
+            days: days
+                .into_iter()
+                .map(|(day, (total, transactions))| DayCost {
+                    date: format_date(day),
+                    transactions: transactions.len(),
+                    total: format_strk(total),
+                })
+                .collect(),
+            total: format_strk(
+                costs
+                    .iter()
+                    .fold(Felt::ZERO, |total, cost| total + cost.fee),
+            ),
+        }
+    }
+
+    fn print(&self) {
+        println!("Spend per block (STRK):");
+        if self.blocks.is_empty() {
+            println!("  none");
+        } else {
+            println!(
+                "  {:<10} {:>24} {:>24} {:>24}",
+                "block", "integrity", "settlement", "total"
+            );
+        }
+        for block in &self.blocks {
+            println!(
+                "  {:<10} {:>24} {:>24} {:>24}",
+                block.block_number, block.integrity, block.settlement, block.total
+            );
+        }
+
+        println!("Spend per day (STRK):");
+        if self.days.is_empty() {
+            println!("  none");
+        }
+        for day in &self.days {
+            println!(
+                "  {} {:>24} ({} transactions)",
+                day.date, day.total, day.transactions
+            );
+        }
+
+        println!("Total: {} STRK", self.total);
+    }
+}
+
+/// Formats an amount in FRI as STRK, which has 18 decimals.
+fn format_strk(fri: Felt) -> String {
+    let digits = format!("{:0>19}", fri.to_string());
+    let (integer, decimals) = digits.split_at(digits.len() - 18);
+    let decimals = decimals.trim_end_matches('0');
+
+    if decimals.is_empty() {
+        integer.to_string()
+    } else {
+        format!("{}.{}", integer, decimals)
+    }
+}
+
+/// Formats a number of days since the Unix epoch as a `YYYY-MM-DD` date.
+fn format_date(days: u64) -> String {
+    // Howard Hinnant's `civil_from_days` algorithm.
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
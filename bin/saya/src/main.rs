@@ -15,10 +15,26 @@ use persistent::Persistent;
 mod sharding;
 use sharding::Sharding;
 
+mod block;
+use block::Block;
+
+mod prove_block;
+use prove_block::ProveBlock;
+
+mod verify_proof;
+use verify_proof::VerifyProof;
+
+mod pie_worker;
+use pie_worker::PieWorker;
+
 mod any;
 
 mod common;
 
+mod status;
+
+mod costs;
+
 #[derive(Debug, Parser)]
 #[clap(about, version)]
 struct Cli {
@@ -35,7 +51,18 @@ enum Subcommands {
     /// Run and manage Saya in persistent L3 mode where proofs are settled in a "base layer"
     /// network.
     Persistent(Persistent),
+    /// Run and manage Saya in sharding mode where multiple rollup shards are proven and settled
+    /// independently in a "base layer" network.
     Sharding(Sharding),
+    /// Inspect and repair individual blocks in a Saya database.
+    Block(Block),
+    /// Prove a single block with the selected prover, or mock its SNOS proof, without running the
+    /// pipeline, writing the PIE and proofs to disk.
+    ProveBlock(ProveBlock),
+    /// Verify a `recursive_with_poseidon` layout proof, such as a layout bridge proof, locally.
+    VerifyProof(VerifyProof),
+    /// Generate the SNOS PIE of a single block, as done by the pipeline in isolated processes.
+    PieWorker(PieWorker),
 }
 
 #[tokio::main]
@@ -54,5 +81,9 @@ async fn main() -> Result<()> {
         Subcommands::Sovereign(cmd) => cmd.run().await,
         Subcommands::Persistent(cmd) => cmd.run().await,
         Subcommands::Sharding(cmd) => cmd.run().await,
+        Subcommands::Block(cmd) => cmd.run().await,
+        Subcommands::ProveBlock(cmd) => cmd.run().await,
+        Subcommands::VerifyProof(cmd) => cmd.run().await,
+        Subcommands::PieWorker(cmd) => cmd.run().await,
     }
 }
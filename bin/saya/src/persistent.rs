@@ -1,16 +1,13 @@
-use std::{io::Read, path::PathBuf, time::Duration};
+use std::{path::PathBuf, time::Duration};
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use saya_core::{
-    block_ingestor::PollingBlockIngestorBuilder,
+    block_ingestor::IngestionHandle,
     data_availability::NoopDataAvailabilityBackendBuilder,
     orchestrator::PersistentOrchestratorBuilder,
-    prover::{
-        AtlanticLayoutBridgeProverBuilder, AtlanticSnosProverBuilder,
-        MockLayoutBridgeProverBuilder, RecursiveProverBuilder,
-    },
-    service::Daemon,
+    prover::RecursiveProverBuilder,
+    service::{Daemon, Supervisor},
     settlement::PiltoverSettlementBackendBuilder,
     storage::SqliteDb,
 };
@@ -18,8 +15,13 @@ use starknet_types_core::felt::Felt;
 use url::Url;
 
 use crate::{
-    any::AnyLayoutBridgeProverBuilder,
-    common::{calculate_workers_per_stage, NUMBER_OF_STAGES, SAYA_DB_PATH},
+    common::{
+        block_ingestor_builder, calculate_workers_per_stage, run_until_shutdown, saya_db_path,
+        AdminOptions, BlockRangeOptions, FinalityOptions, LayoutBridgeOptions, MetricsOptions,
+        PieOptions, ProverOptions, RestartOptions, SettlementOptions, NUMBER_OF_STAGES,
+    },
+    costs::Costs,
+    status::Status,
 };
 
 /// 10 seconds.
@@ -34,6 +36,10 @@ pub struct Persistent {
 enum Subcommands {
     /// Start Saya in persistent mode.
     Start(Start),
+    /// Summarize the state of a Saya database in persistent mode.
+    Status(Status),
+    /// Report the fees spent on settlement per block and per day.
+    Costs(Costs),
 }
 
 #[derive(Debug, Parser, Clone)]
@@ -41,6 +47,10 @@ struct Start {
     /// Rollup network Starknet JSON-RPC URL (v0.7.1)
     #[clap(long, env)]
     rollup_rpc: Url,
+    /// Rollup network Starknet JSON-RPC WebSocket URL (v0.8.0) for subscribing to new blocks.
+    /// Blocks are polled from `--rollup-rpc` if not set, or while the subscription is down
+    #[clap(long, env)]
+    rollup_ws: Option<Url>,
     /// Settlement network Starknet JSON-RPC URL (v0.7.1)
     #[clap(long, env)]
     settlement_rpc: Url,
@@ -50,18 +60,22 @@ struct Start {
     /// Whether to mock the SNOS proof by extracting the output from the PIE and using it from a proof.
     #[clap(long)]
     mock_snos_from_pie: bool,
-    /// Path to the compiled Cairo verifier program
-    #[clap(long, env)]
-    layout_bridge_program: Option<PathBuf>,
-    /// Atlantic prover API key
-    #[clap(long, env)]
-    atlantic_key: String,
-    /// Settlement network integrity contract address
-    #[clap(long, env)]
-    settlement_integrity_address: Option<Felt>,
-    /// Generate mock layout bridge proof and skip on-chain fact registration if provided
-    #[clap(long, env)]
-    mock_layout_bridge_program_hash: Option<Felt>,
+    /// Layout bridge proof options
+    #[clap(flatten)]
+    layout_bridge: LayoutBridgeOptions,
+    /// PIE generation options
+    #[clap(flatten)]
+    pie: PieOptions,
+    /// Prover options
+    #[clap(flatten)]
+    prover: ProverOptions,
+    /// Simulate the integrity and settlement transactions of proven blocks against the settlement
+    /// network and log their size and estimated fees, without sending them
+    #[clap(long)]
+    dry_run: bool,
+    /// Settlement batching and fee options
+    #[clap(flatten)]
+    settlement: SettlementOptions,
     /// Settlement network piltover contract address
     #[clap(long, env)]
     settlement_piltover_address: Felt,
@@ -77,26 +91,65 @@ struct Start {
     /// Number of blocks processed in parallel evenly distributed between the stages
     #[clap(long, env, default_value_t = 60)]
     blocks_processed_in_parallel: usize,
+    /// Block finality options
+    #[clap(flatten)]
+    finality: FinalityOptions,
+    /// Block range options. `--from-block` must be the block following the last settled one
+    #[clap(flatten)]
+    range: BlockRangeOptions,
+    /// Restart options
+    #[clap(flatten)]
+    restart: RestartOptions,
+    /// Metrics options
+    #[clap(flatten)]
+    metrics: MetricsOptions,
+    /// Admin API options
+    #[clap(flatten)]
+    admin: AdminOptions,
 }
 
 impl Persistent {
     pub async fn run(self) -> Result<()> {
         match self.command {
             Subcommands::Start(start) => start.run().await,
+            Subcommands::Status(status) => status.run().await,
+            Subcommands::Costs(costs) => costs.run().await,
         }
     }
 }
 
 impl Start {
     pub async fn run(self) -> Result<()> {
-        let mut snos_file = std::fs::File::open(self.snos_program)?;
-        let mut snos = Vec::with_capacity(snos_file.metadata()?.len() as usize);
-        snos_file.read_to_end(&mut snos)?;
+        let db = SqliteDb::new(&saya_db_path(self.db_dir.as_deref())).await?;
+        let metrics_reporter = self.metrics.install_with_storage(db.clone())?;
+
+        // Shared across restarts so that paused ingestion stays paused.
+        let ingestion_handle = IngestionHandle::new();
+        self.admin.start(db, ingestion_handle.clone())?;
+
+        let policy = self.restart.policy();
+        let supervisor = Supervisor::new("persistent", policy, move || {
+            let start = self.clone();
+            let ingestion_handle = ingestion_handle.clone();
+            async move { start.build_orchestrator(ingestion_handle).await }
+        })
+        .await?;
+        let supervisor_shutdown = supervisor.shutdown_handle();
+        supervisor.start();
+
+        run_until_shutdown(
+            supervisor_shutdown,
+            metrics_reporter.into_iter().collect(),
+            GRACEFUL_SHUTDOWN_TIMEOUT,
+        )
+        .await
+    }
 
-        let saya_path = self
-            .db_dir
-            .map(|db_dir| format!("{}/{}", db_dir.display(), SAYA_DB_PATH))
-            .unwrap_or_else(|| SAYA_DB_PATH.to_string());
+    async fn build_orchestrator(self, ingestion_handle: IngestionHandle) -> Result<impl Daemon> {
+        self.range.validate()?;
+        let policy = self.restart.policy();
+
+        let saya_path = saya_db_path(self.db_dir.as_deref());
 
         let workers_distribution: [usize; NUMBER_OF_STAGES] =
             calculate_workers_per_stage(self.blocks_processed_in_parallel);
@@ -110,46 +163,36 @@ impl Start {
         );
 
         let db = SqliteDb::new(&saya_path).await?;
-        let layout_bridge_prover_builder =
-            match (self.mock_layout_bridge_program_hash, self.layout_bridge_program) {
-                // We don't need the `layout_bridge` program in this case but it's okay if it's given.
-                (Some(mock_layout_bridge_program_hash), _) => {
-                    AnyLayoutBridgeProverBuilder::Mock(MockLayoutBridgeProverBuilder::new(
-                        mock_layout_bridge_program_hash,
-                    ))
-                }
-                (None, Some(layout_bridge_program)) => {
-                    let mut layout_bridge_file = std::fs::File::open(layout_bridge_program)?;
-                    let mut layout_bridge =
-                        Vec::with_capacity(layout_bridge_file.metadata()?.len() as usize);
-                    layout_bridge_file.read_to_end(&mut layout_bridge)?;
-                    AnyLayoutBridgeProverBuilder::Atlantic(AtlanticLayoutBridgeProverBuilder::new(
-                        self.atlantic_key.clone(),
-                        layout_bridge,
-                        db.clone(),
-                        layout_bridge_workers_count,
-                    ))
-                }
-                (None, None) => anyhow::bail!(
-                    "invalid config: `--layout-bridge-program` must be provided unless `--mock-layout-bridge-program-hash` is used"
-                ),
-            };
+        let layout_bridge_program = self.layout_bridge.program()?;
+        let layout_bridge_prover_builder = self.layout_bridge.prover_builder(
+            &self.prover,
+            layout_bridge_program.as_deref(),
+            db.clone(),
+            layout_bridge_workers_count,
+        )?;
 
         // TODO: make impls of these providers configurable
 
-        let block_ingestor_builder = PollingBlockIngestorBuilder::new(
+        let pie_generator = self.pie.generator(&self.snos_program, &self.rollup_rpc)?;
+        let mut block_ingestor_builder = block_ingestor_builder(
             self.rollup_rpc,
-            snos,
+            self.rollup_ws,
+            pie_generator,
             db.clone(),
             ingestor_worker_count,
-        );
+        )
+        .ingestion_handle(ingestion_handle)
+        .finality_policy(self.finality.policy());
+        if let Some(to_block) = self.range.to_block {
+            block_ingestor_builder = block_ingestor_builder.to_block(to_block);
+        }
+
         let prover_builder = RecursiveProverBuilder::new(
-            AtlanticSnosProverBuilder::new(
-                self.atlantic_key,
+            self.prover.snos_prover_builder(
                 self.mock_snos_from_pie,
                 db.clone(),
                 snos_worker_count,
-            ),
+            )?,
             layout_bridge_prover_builder,
         );
         let da_builder = NoopDataAvailabilityBackendBuilder::new();
@@ -159,53 +202,26 @@ impl Start {
             self.settlement_account_address,
             self.settlement_account_private_key,
             db.clone(),
-        );
+        )
+        .dry_run(self.dry_run);
+        let settlement_builder = self
+            .layout_bridge
+            .fact_registration(self.settlement.apply(settlement_builder))?;
 
-        let settlement_builder = match (
-            self.mock_layout_bridge_program_hash,
-            self.settlement_integrity_address,
-        ) {
-            // We don't need `integrity` address but it's okay if it's given.
-            (Some(_), _) => settlement_builder.skip_fact_registration(true),
-            (None, Some(integrity_address)) => {
-                settlement_builder.integrity_address(integrity_address)
-            }
-            (None, None) => anyhow::bail!(
-                "invalid config: `integrity` address must be \
-                provided unless `--mock-layout-bridge-program-hash` is used"
-            ),
-        };
-
-        let orchestrator = PersistentOrchestratorBuilder::new(
+        let mut orchestrator_builder = PersistentOrchestratorBuilder::new(
             block_ingestor_builder,
             prover_builder,
             da_builder,
             settlement_builder,
         )
-        .build()
-        .await?;
-        let orchestrator_shutdown = orchestrator.shutdown_handle();
-        orchestrator.start();
-
-        let mut sigterm_handle =
-            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
-        let ctrl_c_handle = tokio::signal::ctrl_c();
-
-        tokio::select! {
-            _ = sigterm_handle.recv() => {},
-            _ = ctrl_c_handle => {},
-            _ = orchestrator_shutdown.finished() => {},
+        .ingestor_policy(policy.clone())
+        .prover_policy(policy.clone())
+        .da_policy(policy.clone())
+        .settlement_policy(policy);
+        if let Some(from_block) = self.range.from_block {
+            orchestrator_builder = orchestrator_builder.start_block(from_block);
         }
 
-        // Graceful shutdown
-        orchestrator_shutdown.shutdown();
-        tokio::select! {
-            _ = tokio::time::sleep(GRACEFUL_SHUTDOWN_TIMEOUT) => {
-                Err(anyhow::anyhow!("timeout waiting for graceful shutdown"))
-            },
-            _ = orchestrator_shutdown.finished() => {
-                Ok(())
-            },
-        }
+        orchestrator_builder.build().await
     }
 }
@@ -0,0 +1,48 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Parser;
+use saya_core::{
+    pie_generator::{InProcessPieGenerator, LayoutName, PieGenerator, DEFAULT_RPC_VERSION},
+    prover::compress_pie,
+};
+use url::Url;
+
+use crate::common::parse_layout;
+
+/// Generates the SNOS PIE of a single block and writes it zipped to a file. Used by the pipeline
+/// with `--pie.isolate` to generate each PIE in a separate process.
+#[derive(Debug, Parser)]
+pub struct PieWorker {
+    /// Rollup network Starknet JSON-RPC URL (v0.7.1)
+    #[clap(long)]
+    rollup_rpc: Url,
+    /// Path to the compiled Starknet OS program
+    #[clap(long)]
+    snos_program: PathBuf,
+    /// Layout the Starknet OS program is run with
+    #[clap(long, default_value = "all_cairo", value_parser = parse_layout)]
+    layout: LayoutName,
+    /// Version of the rollup Starknet JSON-RPC API
+    #[clap(long, default_value = DEFAULT_RPC_VERSION)]
+    rpc_version: String,
+    /// Number of the block to generate the PIE of
+    block_number: u64,
+    /// Path to write the zipped PIE to
+    output: PathBuf,
+}
+
+impl PieWorker {
+    pub async fn run(self) -> Result<()> {
+        let snos = std::fs::read(&self.snos_program)?;
+
+        let pie = InProcessPieGenerator::new(snos, &self.rollup_rpc, &self.rpc_version)?
+            .layout(self.layout)
+            .generate_pie(self.block_number)
+            .await?;
+        std::fs::write(&self.output, compress_pie(pie).await?)?;
+
+        log::info!(block_number = self.block_number, path:% = self.output.display(); "PIE written");
+        Ok(())
+    }
+}
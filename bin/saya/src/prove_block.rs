@@ -0,0 +1,129 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Parser;
+use saya_core::{
+    pie_generator::PieGenerator,
+    prover::{compress_pie, mock_snos_proof, StarknetOsOutput},
+    storage::SqliteDb,
+};
+use url::Url;
+
+use crate::common::{PieOptions, ProverOptions};
+
+/// Proves a single block outside of the pipeline and writes the artifacts to disk.
+#[derive(Debug, Parser)]
+pub struct ProveBlock {
+    /// Number of the block to prove
+    block_number: u32,
+    /// Rollup network Starknet JSON-RPC URL (v0.7.1)
+    #[clap(long, env)]
+    rollup_rpc: Url,
+    /// Path to the compiled Starknet OS program
+    #[clap(long, env)]
+    snos_program: PathBuf,
+    /// Path to the compiled Cairo verifier program. The layout bridge proof is only generated if
+    /// provided
+    #[clap(long, env)]
+    layout_bridge_program: Option<PathBuf>,
+    #[clap(flatten)]
+    pie: PieOptions,
+    #[clap(flatten)]
+    prover: ProverOptions,
+    /// Mock the SNOS proof from the PIE instead of proving it. The layout bridge proof is still
+    /// generated by the prover on the mock proof if `--layout-bridge-program` is provided
+    #[clap(long)]
+    mock_snos_from_pie: bool,
+    /// Only generate the SNOS PIE without submitting it for proving
+    #[clap(long)]
+    pie_only: bool,
+    /// Path to the directory the artifacts are written to
+    #[clap(long, default_value = ".")]
+    output_dir: PathBuf,
+}
+
+impl ProveBlock {
+    pub async fn run(self) -> Result<()> {
+        tokio::select! {
+            result = self.prove() => result,
+            _ = tokio::signal::ctrl_c() => Err(anyhow::anyhow!("interrupted")),
+        }
+    }
+
+    async fn prove(&self) -> Result<()> {
+        let layout_bridge = self
+            .layout_bridge_program
+            .as_ref()
+            .map(std::fs::read)
+            .transpose()?;
+        std::fs::create_dir_all(&self.output_dir)?;
+
+        log::info!(block_number = self.block_number; "Generating SNOS PIE");
+        let pie = self
+            .pie
+            .generator(&self.snos_program, &self.rollup_rpc)?
+            .generate_pie(self.block_number.into())
+            .await?;
+
+        let os_output = StarknetOsOutput::from_pie(&pie)?;
+        log::info!(
+            initial_root:% = format!("{:#064x}", os_output.initial_root),
+            final_root:% = format!("{:#064x}", os_output.final_root),
+            block_hash:% = format!("{:#064x}", os_output.block_hash);
+            "SNOS output with {} messages to L1 and {} messages to L2",
+            os_output.messages_to_l1.len(),
+            os_output.messages_to_l2.len()
+        );
+
+        let mock_proof = self.mock_snos_from_pie.then(|| mock_snos_proof(&pie));
+        let compressed_pie = compress_pie(pie).await?;
+        self.write_artifact("pie.zip", &compressed_pie)?;
+
+        if self.pie_only {
+            return Ok(());
+        }
+
+        // Mocking the SNOS proof alone doesn't need a prover.
+        let prover = if mock_proof.is_none() || layout_bridge.is_some() {
+            // Artifacts are written to `output_dir` instead, so the database is only kept in
+            // memory.
+            Some(self.prover.one_shot_prover(SqliteDb::in_memory().await?)?)
+        } else {
+            None
+        };
+
+        let snos_proof = match (mock_proof, &prover) {
+            (Some(mock_proof), _) => {
+                log::info!(block_number = self.block_number; "Mock proof generated from PIE");
+                mock_proof
+            }
+            (None, Some(prover)) => prover.prove_snos(self.block_number, compressed_pie).await?,
+            (None, None) => unreachable!("a prover is always built when not mocking"),
+        };
+        self.write_artifact("snos_proof.json", snos_proof.as_bytes())?;
+
+        if let (Some(layout_bridge), Some(prover)) = (layout_bridge, &prover) {
+            let (bridge_pie, bridge_proof) = prover
+                .prove_layout_bridge(self.block_number, layout_bridge, &snos_proof)
+                .await?;
+            // http-prover runs the layout bridge program itself without returning its PIE.
+            if let Some(bridge_pie) = bridge_pie {
+                self.write_artifact("bridge_pie.zip", &bridge_pie)?;
+            }
+            self.write_artifact("bridge_proof.json", bridge_proof.as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes an artifact as `block_<number>_<name>` in the output directory.
+    fn write_artifact(&self, name: &str, content: &[u8]) -> Result<()> {
+        let path = self
+            .output_dir
+            .join(format!("block_{}_{}", self.block_number, name));
+        std::fs::write(&path, content)?;
+
+        log::info!(path:% = path.display(); "Artifact written");
+        Ok(())
+    }
+}
@@ -1,13 +1,446 @@
+use std::{collections::HashSet, path::PathBuf, time::Duration};
+
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use saya_core::{
+    data_availability::NoopDataAvailabilityBackendBuilder,
+    metrics::Label,
+    orchestrator::ShardingOrchestratorBuilder,
+    prover::RecursiveProverBuilder,
+    service::{Daemon, Supervisor},
+    settlement::PiltoverSettlementBackendBuilder,
+    storage::SqliteDb,
+};
+use starknet_types_core::felt::Felt;
+use url::Url;
+
+use crate::common::{
+    block_ingestor_builder, calculate_workers_per_stage, run_until_shutdown,
+    start_storage_metrics_reporter, BlockRangeOptions, FinalityOptions, LayoutBridgeOptions,
+    MetricsOptions, PieOptions, ProverOptions, RestartOptions, SettlementOptions, NUMBER_OF_STAGES,
+    SAYA_DB_PATH,
+};
+
+/// 10 seconds.
+const GRACEFUL_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[derive(Debug, Parser)]
-pub struct Sharding {}
+pub struct Sharding {
+    #[clap(subcommand)]
+    command: Subcommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum Subcommands {
+    /// Start Saya in sharding mode.
+    Start(Start),
+}
+
+#[derive(Debug, Parser, Clone)]
+struct Start {
+    /// Comma-separated Starknet JSON-RPC URLs (v0.7.1) of the rollup shards
+    #[clap(long, env, value_delimiter = ',', required = true)]
+    rollup_rpcs: Vec<Url>,
+    /// Comma-separated Starknet JSON-RPC WebSocket URLs (v0.8.0) for subscribing to new blocks, one
+    /// for each shard in `--rollup-rpcs` order. Blocks are polled from `--rollup-rpcs` if not set,
+    /// or while a subscription is down
+    #[clap(long, env, value_delimiter = ',')]
+    rollup_wss: Vec<Url>,
+    /// Comma-separated piltover contract addresses, one for each shard in `--rollup-rpcs` order
+    #[clap(long, env, value_delimiter = ',', required = true)]
+    settlement_piltover_addresses: Vec<Felt>,
+    /// Settlement network Starknet JSON-RPC URL (v0.7.1)
+    #[clap(long, env)]
+    settlement_rpc: Url,
+    /// Path to the compiled Starknet OS program
+    #[clap(long, env)]
+    snos_program: PathBuf,
+    /// Whether to mock the SNOS proof by extracting the output from the PIE and using it from a proof.
+    #[clap(long)]
+    mock_snos_from_pie: bool,
+    /// Layout bridge proof options
+    #[clap(flatten)]
+    layout_bridge: LayoutBridgeOptions,
+    /// PIE generation options. With `--pie.dir`, the PIEs of each shard are loaded from a
+    /// `shard_<index>` sub-directory
+    #[clap(flatten)]
+    pie: PieOptions,
+    /// Prover options
+    #[clap(flatten)]
+    prover: ProverOptions,
+    /// Settlement batching and fee options
+    #[clap(flatten)]
+    settlement: SettlementOptions,
+    /// Comma-separated settlement network account contract addresses, one for each shard in
+    /// `--rollup-rpcs` order. Shards can't share an account as each one manages its own nonces
+    #[clap(long, env, value_delimiter = ',', required = true)]
+    settlement_account_addresses: Vec<Felt>,
+    /// Comma-separated settlement network account private keys, one for each account in
+    /// `--settlement-account-addresses`
+    #[clap(long, env, value_delimiter = ',', required = true)]
+    settlement_account_private_keys: Vec<Felt>,
+    /// Path to the database directory. Each shard uses its own database in a sub-directory.
+    #[clap(long, env)]
+    db_dir: Option<PathBuf>,
+    /// Number of blocks processed in parallel for each shard evenly distributed between the stages
+    #[clap(long, env, default_value_t = 60)]
+    blocks_processed_in_parallel: usize,
+    /// Block finality options
+    #[clap(flatten)]
+    finality: FinalityOptions,
+    /// Block range options, applied to every shard. `--from-block` must be the block following
+    /// the last settled one on every shard
+    #[clap(flatten)]
+    range: BlockRangeOptions,
+    /// Restart options
+    #[clap(flatten)]
+    restart: RestartOptions,
+    /// Metrics options. Metrics specific to a shard are labelled with its index in `--rollup-rpcs`
+    #[clap(flatten)]
+    metrics: MetricsOptions,
+}
+
+/// The rollup network, settlement contract and account and database of a shard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ShardConfig {
+    rollup_rpc: Url,
+    rollup_ws: Option<Url>,
+    piltover_address: Felt,
+    account_address: Felt,
+    account_private_key: Felt,
+    db_path: String,
+}
 
 impl Sharding {
     pub async fn run(self) -> Result<()> {
-        Err(anyhow::anyhow!(
-            "sharding execution mode not yet implemented"
-        ))
+        match self.command {
+            Subcommands::Start(start) => start.run().await,
+        }
+    }
+}
+
+impl Start {
+    pub async fn run(self) -> Result<()> {
+        let mut metrics_reporters = Vec::new();
+        if self.metrics.install()? {
+            for (index, shard) in self.shards()?.into_iter().enumerate() {
+                let db = SqliteDb::new(&shard.db_path).await?;
+                metrics_reporters.push(start_storage_metrics_reporter(db, shard_labels(index)));
+            }
+        }
+
+        let policy = self.restart.policy();
+        let supervisor = Supervisor::new("sharding", policy, move || {
+            let start = self.clone();
+            async move { start.build_orchestrator().await }
+        })
+        .await?;
+        let supervisor_shutdown = supervisor.shutdown_handle();
+        supervisor.start();
+
+        run_until_shutdown(
+            supervisor_shutdown,
+            metrics_reporters,
+            GRACEFUL_SHUTDOWN_TIMEOUT,
+        )
+        .await
+    }
+
+    /// Assigns the rollup RPC URLs, piltover addresses, accounts and databases to shards in the
+    /// order they're given.
+    fn shards(&self) -> Result<Vec<ShardConfig>> {
+        let shard_count = self.rollup_rpcs.len();
+        if !self.rollup_wss.is_empty() && self.rollup_wss.len() != shard_count {
+            anyhow::bail!(
+                "invalid config: {} rollup RPC URLs provided for {} rollup WebSocket URLs",
+                shard_count,
+                self.rollup_wss.len()
+            );
+        }
+        if self.settlement_piltover_addresses.len() != shard_count {
+            anyhow::bail!(
+                "invalid config: {} rollup RPC URLs provided for {} piltover addresses",
+                shard_count,
+                self.settlement_piltover_addresses.len()
+            );
+        }
+        if self.settlement_account_addresses.len() != shard_count {
+            anyhow::bail!(
+                "invalid config: {} rollup RPC URLs provided for {} settlement accounts",
+                shard_count,
+                self.settlement_account_addresses.len()
+            );
+        }
+        if self.settlement_account_private_keys.len() != shard_count {
+            anyhow::bail!(
+                "invalid config: {} settlement accounts provided for {} private keys",
+                shard_count,
+                self.settlement_account_private_keys.len()
+            );
+        }
+
+        let mut account_addresses = HashSet::new();
+        for account_address in self.settlement_account_addresses.iter() {
+            if !account_addresses.insert(account_address) {
+                anyhow::bail!(
+                    "invalid config: settlement account {:#064x} is used by multiple shards",
+                    account_address
+                );
+            }
+        }
+
+        Ok((0..shard_count)
+            .map(|ind| ShardConfig {
+                rollup_rpc: self.rollup_rpcs[ind].clone(),
+                rollup_ws: self.rollup_wss.get(ind).cloned(),
+                piltover_address: self.settlement_piltover_addresses[ind],
+                account_address: self.settlement_account_addresses[ind],
+                account_private_key: self.settlement_account_private_keys[ind],
+                db_path: self
+                    .db_dir
+                    .as_ref()
+                    .map(|db_dir| format!("{}/shard_{}/{}", db_dir.display(), ind, SAYA_DB_PATH))
+                    .unwrap_or_else(|| format!("shard_{}/{}", ind, SAYA_DB_PATH)),
+            })
+            .collect())
+    }
+
+    /// Gets the PIE generation options of the shard at `index`. PIEs generated ahead of time are
+    /// loaded from a sub-directory of `--pie.dir` per shard, as block numbers overlap between
+    /// shards.
+    fn shard_pie_options(&self, index: usize) -> Result<PieOptions> {
+        // The program is only given the block number, and can't tell which shard it belongs to.
+        if self.pie.command.is_some() && self.rollup_rpcs.len() > 1 {
+            anyhow::bail!("invalid config: `--pie.command` is not supported with multiple shards");
+        }
+
+        let mut pie = self.pie.clone();
+        pie.dir = self
+            .pie
+            .dir
+            .as_ref()
+            .map(|dir| dir.join(format!("shard_{}", index)));
+        Ok(pie)
+    }
+
+    async fn build_orchestrator(self) -> Result<impl Daemon> {
+        self.range.validate()?;
+        let shards = self.shards()?;
+
+        let layout_bridge_program = self.layout_bridge.program()?;
+
+        let workers_distribution: [usize; NUMBER_OF_STAGES] =
+            calculate_workers_per_stage(self.blocks_processed_in_parallel);
+
+        let [snos_worker_count, layout_bridge_workers_count, ingestor_worker_count] =
+            workers_distribution;
+
+        log::info!(
+            shards = shards.len(),
+            snos_worker_count,layout_bridge_workers_count,ingestor_worker_count;
+            "workers distribution per shard"
+        );
+
+        let mut orchestrator_builder =
+            ShardingOrchestratorBuilder::new().restart_policy(self.restart.policy());
+        if let Some(from_block) = self.range.from_block {
+            orchestrator_builder = orchestrator_builder.start_block(from_block);
+        }
+
+        for (index, shard) in shards.into_iter().enumerate() {
+            let db = SqliteDb::new(&shard.db_path).await?;
+
+            let layout_bridge_prover_builder = self.layout_bridge.prover_builder(
+                &self.prover,
+                layout_bridge_program.as_deref(),
+                db.clone(),
+                layout_bridge_workers_count,
+            )?;
+
+            let pie_generator = self
+                .shard_pie_options(index)?
+                .generator(&self.snos_program, &shard.rollup_rpc)?;
+            let mut block_ingestor_builder = block_ingestor_builder(
+                shard.rollup_rpc.clone(),
+                shard.rollup_ws.clone(),
+                pie_generator,
+                db.clone(),
+                ingestor_worker_count,
+            )
+            .finality_policy(self.finality.policy());
+            if let Some(to_block) = self.range.to_block {
+                block_ingestor_builder = block_ingestor_builder.to_block(to_block);
+            }
+            let prover_builder = RecursiveProverBuilder::new(
+                self.prover.snos_prover_builder(
+                    self.mock_snos_from_pie,
+                    db.clone(),
+                    snos_worker_count,
+                )?,
+                layout_bridge_prover_builder,
+            );
+            let da_builder = NoopDataAvailabilityBackendBuilder::new();
+            let settlement_builder = PiltoverSettlementBackendBuilder::new(
+                self.settlement_rpc.clone(),
+                shard.piltover_address,
+                shard.account_address,
+                shard.account_private_key,
+                db.clone(),
+            )
+            .metrics_labels(shard_labels(index));
+            let settlement_builder = self
+                .layout_bridge
+                .fact_registration(self.settlement.apply(settlement_builder))?;
+
+            orchestrator_builder = orchestrator_builder.shard(
+                shard.rollup_rpc.to_string(),
+                block_ingestor_builder,
+                prover_builder,
+                da_builder,
+                settlement_builder,
+            );
+        }
+
+        orchestrator_builder.build().await
+    }
+}
+
+/// Labels added to the metrics of the shard at `index`.
+fn shard_labels(index: usize) -> Vec<Label> {
+    vec![Label::new("shard", index.to_string())]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start(extra_args: &[&str]) -> Result<Start, clap::Error> {
+        let args = [
+            "start",
+            "--rollup-rpcs",
+            "http://localhost:5050,http://localhost:5051",
+            "--settlement-piltover-addresses",
+            "0x10,0x11",
+            "--settlement-rpc",
+            "http://localhost:6060",
+            "--snos-program",
+            "snos.json",
+        ];
+        Start::try_parse_from(args.iter().chain(extra_args))
+    }
+
+    #[test]
+    fn test_shards_assigned_in_order() {
+        let start = start(&[
+            "--settlement-account-addresses",
+            "0x20,0x21",
+            "--settlement-account-private-keys",
+            "0x30,0x31",
+            "--db-dir",
+            "/data",
+        ])
+        .unwrap();
+
+        let shards = start.shards().unwrap();
+        assert_eq!(
+            shards,
+            vec![
+                ShardConfig {
+                    rollup_rpc: Url::parse("http://localhost:5050").unwrap(),
+                    rollup_ws: None,
+                    piltover_address: Felt::from(0x10_u64),
+                    account_address: Felt::from(0x20_u64),
+                    account_private_key: Felt::from(0x30_u64),
+                    db_path: "/data/shard_0/saya.db".to_string(),
+                },
+                ShardConfig {
+                    rollup_rpc: Url::parse("http://localhost:5051").unwrap(),
+                    rollup_ws: None,
+                    piltover_address: Felt::from(0x11_u64),
+                    account_address: Felt::from(0x21_u64),
+                    account_private_key: Felt::from(0x31_u64),
+                    db_path: "/data/shard_1/saya.db".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shards_without_db_dir() {
+        let start = start(&[
+            "--settlement-account-addresses",
+            "0x20,0x21",
+            "--settlement-account-private-keys",
+            "0x30,0x31",
+        ])
+        .unwrap();
+
+        let db_paths = start
+            .shards()
+            .unwrap()
+            .into_iter()
+            .map(|shard| shard.db_path)
+            .collect::<Vec<_>>();
+        assert_eq!(db_paths, vec!["shard_0/saya.db", "shard_1/saya.db"]);
+    }
+
+    #[test]
+    fn test_shards_missing_account() {
+        let start = start(&[
+            "--settlement-account-addresses",
+            "0x20",
+            "--settlement-account-private-keys",
+            "0x30",
+        ])
+        .unwrap();
+
+        assert!(start.shards().is_err());
+    }
+
+    #[test]
+    fn test_shards_shared_account() {
+        let start = start(&[
+            "--settlement-account-addresses",
+            "0x20,0x20",
+            "--settlement-account-private-keys",
+            "0x30,0x30",
+        ])
+        .unwrap();
+
+        let err = start.shards().unwrap_err();
+        assert!(err.to_string().contains("used by multiple shards"));
+    }
+
+    #[test]
+    fn test_shards_rollup_ws_count() {
+        let start = start(&[
+            "--settlement-account-addresses",
+            "0x20,0x21",
+            "--settlement-account-private-keys",
+            "0x30,0x31",
+            "--rollup-wss",
+            "ws://localhost:5050",
+        ])
+        .unwrap();
+
+        assert!(start.shards().is_err());
+    }
+
+    #[test]
+    fn test_shard_pie_dirs() {
+        let start = start(&[
+            "--settlement-account-addresses",
+            "0x20,0x21",
+            "--settlement-account-private-keys",
+            "0x30,0x31",
+            "--pie.dir",
+            "/pies",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            start.shard_pie_options(1).unwrap().dir,
+            Some(PathBuf::from("/pies/shard_1"))
+        );
     }
 }
@@ -1,18 +1,24 @@
-use std::{io::Read, path::PathBuf, time::Duration};
+use std::{path::PathBuf, time::Duration};
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use saya_core::{
-    block_ingestor::PollingBlockIngestorBuilder,
+    block_ingestor::IngestionHandle,
     data_availability::CelestiaDataAvailabilityBackendBuilder,
     orchestrator::{Genesis, SovereignOrchestratorBuilder},
-    prover::AtlanticSnosProverBuilder,
-    service::Daemon,
-    storage::{InMemoryStorageBackend, SqliteDb},
+    service::{Daemon, Supervisor},
+    storage::SqliteDb,
 };
 use url::Url;
 
-use crate::common::{calculate_workers_per_stage, SAYA_DB_PATH};
+use crate::{
+    common::{
+        block_ingestor_builder, calculate_workers_per_stage, run_until_shutdown, saya_db_path,
+        AdminOptions, BlockRangeOptions, FinalityOptions, MetricsOptions, PieOptions,
+        ProverOptions, RestartOptions,
+    },
+    status::Status,
+};
 
 /// 10 seconds.
 const GRACEFUL_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
@@ -27,22 +33,31 @@ pub struct Sovereign {
 enum Subcommands {
     /// Start Saya in sovereign mode.
     Start(Start),
+    /// Summarize the state of a Saya database in sovereign mode.
+    Status(Status),
 }
 
-#[derive(Debug, Parser)]
+#[derive(Debug, Parser, Clone)]
 struct Start {
     /// Starknet JSON-RPC URL (v0.7.1)
     #[clap(long, env)]
     starknet_rpc: Url,
+    /// Starknet JSON-RPC WebSocket URL (v0.8.0) for subscribing to new blocks. Blocks are
+    /// polled from `--starknet-rpc` if not set, or while the subscription is down
+    #[clap(long, env)]
+    starknet_ws: Option<Url>,
     /// Path to the compiled Starknet OS program
     #[clap(long, env)]
     snos_program: PathBuf,
     /// Whether to mock the SNOS proof by extracting the output from the PIE and using it from a proof.
     #[clap(long)]
     mock_snos_from_pie: bool,
-    /// Atlantic prover API key
-    #[clap(long, env)]
-    atlantic_key: String,
+    /// PIE generation options
+    #[clap(flatten)]
+    pie: PieOptions,
+    /// Prover options
+    #[clap(flatten)]
+    prover: ProverOptions,
     /// Celestia RPC endpoint URL
     #[clap(long, env)]
     celestia_rpc: Url,
@@ -66,6 +81,21 @@ struct Start {
     /// Path to the database directory
     #[clap(long, env)]
     db_dir: Option<PathBuf>,
+    /// Block finality options
+    #[clap(flatten)]
+    finality: FinalityOptions,
+    /// Block range options
+    #[clap(flatten)]
+    range: BlockRangeOptions,
+    /// Restart options
+    #[clap(flatten)]
+    restart: RestartOptions,
+    /// Metrics options
+    #[clap(flatten)]
+    metrics: MetricsOptions,
+    /// Admin API options
+    #[clap(flatten)]
+    admin: AdminOptions,
 }
 
 /// Validate that the value is not empty.
@@ -77,8 +107,9 @@ fn validate_non_empty(s: &str) -> Result<String, String> {
     }
 }
 
-#[derive(Debug, Parser)]
+#[derive(Debug, Parser, Clone)]
 struct GenesisOptions {
+    /// First block to process. Only used when no chain head has been persisted in the database yet.
     #[clap(
         long = "genesis.first-block-number",
         env = "GENESIS_FIRST_BLOCK_NUMBER"
@@ -90,20 +121,43 @@ impl Sovereign {
     pub async fn run(self) -> Result<()> {
         match self.command {
             Subcommands::Start(start) => start.run().await,
+            Subcommands::Status(status) => status.run().await,
         }
     }
 }
 
 impl Start {
     pub async fn run(self) -> Result<()> {
-        let mut snos_file = std::fs::File::open(self.snos_program)?;
-        let mut snos = Vec::with_capacity(snos_file.metadata()?.len() as usize);
-        snos_file.read_to_end(&mut snos)?;
-
-        let saya_path = self
-            .db_dir
-            .map(|db_dir| format!("{}/{}", db_dir.display(), SAYA_DB_PATH))
-            .unwrap_or_else(|| SAYA_DB_PATH.to_string());
+        let db = SqliteDb::new(&saya_db_path(self.db_dir.as_deref())).await?;
+        let metrics_reporter = self.metrics.install_with_storage(db.clone())?;
+
+        // Shared across restarts so that paused ingestion stays paused.
+        let ingestion_handle = IngestionHandle::new();
+        self.admin.start(db, ingestion_handle.clone())?;
+
+        let policy = self.restart.policy();
+        let supervisor = Supervisor::new("sovereign", policy, move || {
+            let start = self.clone();
+            let ingestion_handle = ingestion_handle.clone();
+            async move { start.build_orchestrator(ingestion_handle).await }
+        })
+        .await?;
+        let supervisor_shutdown = supervisor.shutdown_handle();
+        supervisor.start();
+
+        run_until_shutdown(
+            supervisor_shutdown,
+            metrics_reporter.into_iter().collect(),
+            GRACEFUL_SHUTDOWN_TIMEOUT,
+        )
+        .await
+    }
+
+    async fn build_orchestrator(self, ingestion_handle: IngestionHandle) -> Result<impl Daemon> {
+        self.range.validate()?;
+        let policy = self.restart.policy();
+
+        let saya_path = saya_db_path(self.db_dir.as_deref());
         let db = SqliteDb::new(&saya_path).await?;
 
         let workers_distribution: [usize; 3] =
@@ -111,59 +165,47 @@ impl Start {
         let [snos_worker_count, _layout_bridge_workers_count, ingestor_worker_count] =
             workers_distribution;
 
-        let block_ingestor_builder = PollingBlockIngestorBuilder::new(
+        let pie_generator = self.pie.generator(&self.snos_program, &self.starknet_rpc)?;
+        let mut block_ingestor_builder = block_ingestor_builder(
             self.starknet_rpc,
-            snos,
+            self.starknet_ws,
+            pie_generator,
             db.clone(),
             ingestor_worker_count,
-        );
+        )
+        .ingestion_handle(ingestion_handle)
+        .finality_policy(self.finality.policy());
+        if let Some(from_block) = self.range.from_block {
+            block_ingestor_builder = block_ingestor_builder.from_block(from_block);
+        }
+        if let Some(to_block) = self.range.to_block {
+            block_ingestor_builder = block_ingestor_builder.to_block(to_block);
+        }
 
-        let prover_builder = AtlanticSnosProverBuilder::new(
-            self.atlantic_key,
+        let prover_builder = self.prover.snos_prover_builder(
             self.mock_snos_from_pie,
             db.clone(),
             snos_worker_count,
-        );
+        )?;
         let da_builder = CelestiaDataAvailabilityBackendBuilder::new(
             self.celestia_rpc,
             self.celestia_token,
             self.celestia_namespace,
             self.celestia_key_name,
         )?;
-        let storage = InMemoryStorageBackend::new();
 
-        let orchestrator = SovereignOrchestratorBuilder::new(
+        SovereignOrchestratorBuilder::new(
             block_ingestor_builder,
             prover_builder,
             da_builder,
-            storage,
+            db,
             self.genesis.into(),
         )
+        .ingestor_policy(policy.clone())
+        .prover_policy(policy.clone())
+        .da_policy(policy)
         .build()
-        .await?;
-        let orchestrator_shutdown = orchestrator.shutdown_handle();
-        orchestrator.start();
-
-        let mut sigterm_handle =
-            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
-        let ctrl_c_handle = tokio::signal::ctrl_c();
-
-        tokio::select! {
-            _ = sigterm_handle.recv() => {},
-            _ = ctrl_c_handle => {},
-            _ = orchestrator_shutdown.finished() => {},
-        }
-
-        // Graceful shutdown
-        orchestrator_shutdown.shutdown();
-        tokio::select! {
-            _ = tokio::time::sleep(GRACEFUL_SHUTDOWN_TIMEOUT) => {
-                Err(anyhow::anyhow!("timeout waiting for graceful shutdown"))
-            },
-            _ = orchestrator_shutdown.finished() => {
-                Ok(())
-            },
-        }
+        .await
     }
 }
 
@@ -0,0 +1,171 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Parser;
+use saya_core::storage::{BlockRecord, BlockStatus, PersistantStorage, SqliteDb};
+use serde::Serialize;
+
+use crate::common::saya_db_path;
+
+/// Summarizes the state of the pipeline from its database without modifying it.
+#[derive(Debug, Parser)]
+pub struct Status {
+    /// Path to the database directory
+    #[clap(long, env)]
+    db_dir: Option<PathBuf>,
+    /// Print the summary as JSON
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct StatusReport {
+    blocks: Vec<StatusCount>,
+    oldest_unsettled_block: Option<u32>,
+    failed_blocks: Vec<FailedBlock>,
+    /// Blocks failed for good, which are not retried until they're reset.
+    terminal_failures: Vec<FailedBlock>,
+    in_flight_queries: Vec<BlockRecord>,
+}
+
+#[derive(Debug, Serialize)]
+struct StatusCount {
+    status: BlockStatus,
+    count: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct FailedBlock {
+    block_number: u32,
+    failure_reason: String,
+}
+
+impl Status {
+    pub async fn run(self) -> Result<()> {
+        let db = SqliteDb::open_read_only(&saya_db_path(self.db_dir.as_deref())).await?;
+        let report = StatusReport::load(&db).await?;
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            report.print();
+        }
+
+        Ok(())
+    }
+}
+
+impl StatusReport {
+    async fn load<DB>(db: &DB) -> Result<Self>
+    where
+        DB: PersistantStorage,
+    {
+        let blocks = db
+            .get_block_status_counts()
+            .await?
+            .into_iter()
+            .map(|(status, count)| StatusCount { status, count })
+            .collect();
+
+        let unsettled_blocks = db
+            .list_blocks()
+            .await?
+            .into_iter()
+            .filter(|block| block.status != BlockStatus::Settled)
+            .collect::<Vec<_>>();
+
+        let oldest_unsettled_block = unsettled_blocks.first().map(|block| block.block_number);
+
+        let failed_blocks = db
+            .get_failed_blocks()
+            .await?
+            .into_iter()
+            .map(|(block_number, failure_reason)| FailedBlock {
+                block_number,
+                failure_reason,
+            })
+            .collect();
+
+        let terminal_failures = unsettled_blocks
+            .iter()
+            .filter(|block| block.status == BlockStatus::Failed)
+            .map(|block| FailedBlock {
+                block_number: block.block_number,
+                failure_reason: block.failure_reason.clone().unwrap_or_default(),
+            })
+            .collect();
+
+        let in_flight_queries = unsettled_blocks
+            .into_iter()
+            .filter(|block| {
+                block.snos_proof_query_id.is_some()
+                    || block.trace_gen_query_id.is_some()
+                    || block.bridge_proof_query_id.is_some()
+            })
+            .collect();
+
+        Ok(Self {
+            blocks,
+            oldest_unsettled_block,
+            failed_blocks,
+            terminal_failures,
+            in_flight_queries,
+        })
+    }
+
+    fn print(&self) {
+        println!("Blocks by status:");
+        if self.blocks.is_empty() {
+            println!("  none");
+        }
+        for count in &self.blocks {
+            // `BlockStatus` does not support padding through its `Display` impl.
+            let status = count.status.to_string();
+            println!("  {:<24} {}", status, count.count);
+        }
+
+        match self.oldest_unsettled_block {
+            Some(block_number) => println!("Oldest unsettled block: {}", block_number),
+            None => println!("Oldest unsettled block: none"),
+        }
+
+        println!("Failed blocks pending requeue:");
+        if self.failed_blocks.is_empty() {
+            println!("  none");
+        }
+        for failed_block in &self.failed_blocks {
+            println!(
+                "  #{}: {}",
+                failed_block.block_number, failed_block.failure_reason
+            );
+        }
+
+        println!("Failed blocks awaiting reset:");
+        if self.terminal_failures.is_empty() {
+            println!("  none");
+        }
+        for failed_block in &self.terminal_failures {
+            println!(
+                "  #{}: {}",
+                failed_block.block_number, failed_block.failure_reason
+            );
+        }
+
+        println!("In-flight prover queries:");
+        if self.in_flight_queries.is_empty() {
+            println!("  none");
+        }
+        for block in &self.in_flight_queries {
+            println!("  #{} ({})", block.block_number, block.status);
+            for (name, query_id) in [
+                ("snos_proof", &block.snos_proof_query_id),
+                ("trace_gen", &block.trace_gen_query_id),
+                ("bridge_proof", &block.bridge_proof_query_id),
+            ] {
+                if let Some(query_id) = query_id {
+                    println!("    {:<14} {}", name, query_id);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Parser;
+use saya_core::prover::{verify_proof, AtlanticProof};
+
+/// Verifies a proof locally with the `swiftness` verifier.
+#[derive(Debug, Parser)]
+pub struct VerifyProof {
+    /// Path to the proof JSON file, as returned by the prover
+    file: PathBuf,
+}
+
+impl VerifyProof {
+    pub async fn run(self) -> Result<()> {
+        let raw_proof = std::fs::read_to_string(&self.file)?;
+
+        let verified = tokio::task::spawn_blocking(move || {
+            let proof = AtlanticProof::parse(raw_proof)?;
+            verify_proof(&proof)
+        })
+        .await??;
+
+        println!("Proof is valid");
+        println!("Program hash: {:#064x}", verified.program_hash);
+        println!("Output hash:  {:#064x}", verified.output_hash);
+
+        Ok(())
+    }
+}
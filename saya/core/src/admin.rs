@@ -0,0 +1,271 @@
+use std::net::{SocketAddr, TcpListener};
+
+use anyhow::Result;
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    block_ingestor::IngestionHandle,
+    service::{Daemon, FinishHandle, ShutdownHandle},
+    storage::{BlockRecord, BlockStatus, PersistantStorage, Step},
+};
+
+/// An embedded HTTP server exposing a JSON API for inspecting and controlling the pipeline.
+///
+/// Endpoints:
+///
+/// - `GET /blocks`: lists blocks with their status, prover query IDs and failure reason.
+/// - `GET /blocks/{block_number}/proofs/{snos|bridge}`: fetches a stored proof.
+/// - `POST /blocks/{block_number}/requeue`: queues a block for the ingestor to pick up again.
+///   Failed blocks must be reset instead.
+/// - `POST /blocks/{block_number}/reset`: resets a block to the stage given as `{"status": ...}`
+///   and requeues it.
+/// - `GET /ingestion`, `POST /ingestion/pause` and `POST /ingestion/resume`: controls ingestion
+///   of new blocks.
+#[derive(Debug)]
+pub struct AdminServer<DB> {
+    listener: TcpListener,
+    db: DB,
+    ingestion_handle: IngestionHandle,
+    finish_handle: FinishHandle,
+}
+
+#[derive(Debug)]
+pub struct AdminServerBuilder<DB> {
+    addr: SocketAddr,
+    db: DB,
+    ingestion_handle: Option<IngestionHandle>,
+}
+
+#[derive(Debug, Clone)]
+struct AdminState<DB> {
+    db: DB,
+    ingestion_handle: IngestionHandle,
+}
+
+#[derive(Debug, Serialize)]
+struct IngestionStatus {
+    paused: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResetRequest {
+    status: BlockStatus,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(ErrorResponse { error: self.1 })).into_response()
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(value: anyhow::Error) -> Self {
+        Self(StatusCode::INTERNAL_SERVER_ERROR, value.to_string())
+    }
+}
+
+impl<DB> AdminServerBuilder<DB> {
+    pub fn new(addr: SocketAddr, db: DB) -> Self {
+        Self {
+            addr,
+            db,
+            ingestion_handle: None,
+        }
+    }
+
+    /// Sets the handle of the ingestion to control. Pausing has no effect if not set.
+    pub fn ingestion_handle(mut self, ingestion_handle: IngestionHandle) -> Self {
+        self.ingestion_handle = Some(ingestion_handle);
+        self
+    }
+
+    pub fn build(self) -> Result<AdminServer<DB>> {
+        // Binding eagerly so that address errors surface when building instead of after starting.
+        let listener = TcpListener::bind(self.addr)?;
+        listener.set_nonblocking(true)?;
+
+        Ok(AdminServer {
+            listener,
+            db: self.db,
+            ingestion_handle: self.ingestion_handle.unwrap_or_default(),
+            finish_handle: FinishHandle::new(),
+        })
+    }
+}
+
+impl<DB> AdminServer<DB>
+where
+    DB: PersistantStorage + Clone + Send + Sync + 'static,
+{
+    async fn run(self) {
+        let state = AdminState {
+            db: self.db,
+            ingestion_handle: self.ingestion_handle,
+        };
+        let router = Router::new()
+            .route("/blocks", get(list_blocks::<DB>))
+            .route("/blocks/{block_number}/proofs/{step}", get(get_proof::<DB>))
+            .route("/blocks/{block_number}/requeue", post(requeue_block::<DB>))
+            .route("/blocks/{block_number}/reset", post(reset_block::<DB>))
+            .route("/ingestion", get(ingestion_status::<DB>))
+            .route("/ingestion/pause", post(pause_ingestion::<DB>))
+            .route("/ingestion/resume", post(resume_ingestion::<DB>))
+            .with_state(state);
+
+        match tokio::net::TcpListener::from_std(self.listener) {
+            Ok(listener) => {
+                if let Ok(addr) = listener.local_addr() {
+                    info!(addr:% = addr; "Admin API server started");
+                }
+
+                let finish_handle = self.finish_handle.clone();
+                if let Err(err) = axum::serve(listener, router)
+                    .with_graceful_shutdown(async move { finish_handle.shutdown_requested().await })
+                    .await
+                {
+                    error!(error:% = err; "Admin API server error");
+                }
+            }
+            Err(err) => error!(error:% = err; "Failed to start admin API server"),
+        }
+
+        debug!("Graceful shutdown finished");
+        self.finish_handle.finish();
+    }
+}
+
+impl<DB> Daemon for AdminServer<DB>
+where
+    DB: PersistantStorage + Clone + Send + Sync + 'static,
+{
+    fn shutdown_handle(&self) -> ShutdownHandle {
+        self.finish_handle.shutdown_handle()
+    }
+
+    fn start(self) {
+        tokio::spawn(self.run());
+    }
+}
+
+async fn list_blocks<DB>(
+    State(state): State<AdminState<DB>>,
+) -> Result<Json<Vec<BlockRecord>>, ApiError>
+where
+    DB: PersistantStorage,
+{
+    Ok(Json(state.db.list_blocks().await?))
+}
+
+async fn get_proof<DB>(
+    State(state): State<AdminState<DB>>,
+    Path((block_number, step)): Path<(u32, String)>,
+) -> Result<Response, ApiError>
+where
+    DB: PersistantStorage,
+{
+    let step = match step.as_str() {
+        "snos" => Step::Snos,
+        "bridge" => Step::Bridge,
+        _ => {
+            return Err(ApiError(
+                StatusCode::BAD_REQUEST,
+                format!("unknown proof step `{}`, expected `snos` or `bridge`", step),
+            ))
+        }
+    };
+
+    let proof = state
+        .db
+        .find_proof(block_number, step)
+        .await?
+        .ok_or_else(|| {
+            ApiError(
+                StatusCode::NOT_FOUND,
+                format!("proof not found for block {}", block_number),
+            )
+        })?;
+
+    // Proofs are stored as raw JSON.
+    Ok(([(header::CONTENT_TYPE, "application/json")], proof).into_response())
+}
+
+async fn requeue_block<DB>(
+    State(state): State<AdminState<DB>>,
+    Path(block_number): Path<u32>,
+) -> Result<StatusCode, ApiError>
+where
+    DB: PersistantStorage,
+{
+    // Failed blocks would fail the same way again with their current artifacts.
+    if let Ok(BlockStatus::Failed) = state.db.get_status(block_number).await {
+        return Err(ApiError(
+            StatusCode::CONFLICT,
+            format!("block {} failed, reset it instead", block_number),
+        ));
+    }
+
+    state
+        .db
+        .requeue_block(block_number, "requeued via admin API".to_string())
+        .await?;
+    info!(block_number; "Block requeued via admin API");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn reset_block<DB>(
+    State(state): State<AdminState<DB>>,
+    Path(block_number): Path<u32>,
+    Json(request): Json<ResetRequest>,
+) -> Result<StatusCode, ApiError>
+where
+    DB: PersistantStorage,
+{
+    state
+        .db
+        .reset_block(block_number, request.status.clone())
+        .await
+        .map_err(|err| ApiError(StatusCode::BAD_REQUEST, err.to_string()))?;
+    state
+        .db
+        .requeue_block(block_number, "reset via admin API".to_string())
+        .await?;
+    info!(block_number, status:% = request.status; "Block reset via admin API");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn ingestion_status<DB>(State(state): State<AdminState<DB>>) -> Json<IngestionStatus> {
+    Json(IngestionStatus {
+        paused: state.ingestion_handle.is_paused(),
+    })
+}
+
+async fn pause_ingestion<DB>(State(state): State<AdminState<DB>>) -> Json<IngestionStatus> {
+    state.ingestion_handle.pause();
+    info!("Block ingestion paused via admin API");
+
+    Json(IngestionStatus { paused: true })
+}
+
+async fn resume_ingestion<DB>(State(state): State<AdminState<DB>>) -> Json<IngestionStatus> {
+    state.ingestion_handle.resume();
+    info!("Block ingestion resumed via admin API");
+
+    Json(IngestionStatus { paused: false })
+}
@@ -1,12 +1,24 @@
-use anyhow::Result;
+use std::sync::Arc;
 
-use tokio::sync::mpsc::Sender;
+use anyhow::Result;
+use log::warn;
+use starknet::{
+    core::types::{BlockId, MaybePendingBlockWithTxHashes},
+    providers::Provider,
+};
+use starknet_types_core::felt::Felt;
+use tokio::sync::{mpsc::Sender, watch};
 
 mod polling;
+mod subscription;
 
 pub use polling::{PollingBlockIngestor, PollingBlockIngestorBuilder};
+pub use subscription::{SubscriptionBlockIngestor, SubscriptionBlockIngestorBuilder};
 
-use crate::{service::Daemon, storage::BlockStatus};
+use crate::{
+    service::Daemon,
+    storage::{BlockStatus, PersistantStorage},
+};
 
 pub trait BlockIngestorBuilder {
     type Ingestor: BlockIngestor;
@@ -18,10 +30,239 @@ pub trait BlockIngestorBuilder {
     fn channel(self, channel: Sender<BlockInfo>) -> Self;
 }
 
-pub trait BlockIngestor: Daemon {}
+pub trait BlockIngestor: Daemon {
+    /// The last block to ingest. Failed blocks are still retried after it is queued, so the
+    /// orchestrator shuts the ingestor down once the range is processed. The ingestor finishes by
+    /// itself if only blocks failed for good are left in the range. Blocks are ingested
+    /// indefinitely when `None`.
+    fn end_block(&self) -> Option<u64> {
+        None
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct BlockInfo {
     pub number: u64,
     pub status: BlockStatus,
 }
+
+/// Number of blocks past the finality depth during which ingested blocks are still checked for
+/// reorgs. Deeper blocks are considered final.
+const REORG_CHECK_DEPTH: u64 = 64;
+
+/// The condition for a rollup block to be ingested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FinalityPolicy {
+    /// Blocks are ingested once the given number of blocks have been built on top of them. With
+    /// `0`, blocks are ingested as soon as they're produced.
+    Confirmations(u64),
+    /// Blocks are ingested once the rollup reports them as accepted on L1.
+    AcceptedOnL1,
+}
+
+impl FinalityPolicy {
+    /// Checks whether `block_number` can be ingested, given the latest block of the rollup.
+    async fn is_final<P>(&self, provider: &P, block_number: u64, latest_block: u64) -> Result<bool>
+    where
+        P: Provider + Sync,
+    {
+        if latest_block < block_number {
+            return Ok(false);
+        }
+
+        match self {
+            Self::Confirmations(confirmations) => Ok(latest_block - block_number >= *confirmations),
+            Self::AcceptedOnL1 => match provider
+                .get_block_with_tx_hashes(BlockId::Number(block_number))
+                .await?
+            {
+                MaybePendingBlockWithTxHashes::Block(block) => {
+                    Ok(block.status == starknet::core::types::BlockStatus::AcceptedOnL1)
+                }
+                MaybePendingBlockWithTxHashes::PendingBlock(_) => Ok(false),
+            },
+        }
+    }
+
+    /// Gets the depth from the latest block of the rollup past which ingested blocks are no longer
+    /// checked for reorgs, or `None` if blocks can't be reorged once ingested.
+    fn reorg_check_depth(&self) -> Option<u64> {
+        match self {
+            Self::Confirmations(confirmations) => {
+                Some(confirmations.saturating_add(REORG_CHECK_DEPTH))
+            }
+            Self::AcceptedOnL1 => None,
+        }
+    }
+}
+
+impl Default for FinalityPolicy {
+    fn default() -> Self {
+        Self::Confirmations(0)
+    }
+}
+
+/// Fetches the hash of a block that is no longer pending.
+async fn get_block_hash<P>(provider: &P, block_number: u64) -> Result<Felt>
+where
+    P: Provider + Sync,
+{
+    match provider
+        .get_block_with_tx_hashes(BlockId::Number(block_number))
+        .await?
+    {
+        MaybePendingBlockWithTxHashes::Block(block) => Ok(block.block_hash),
+        MaybePendingBlockWithTxHashes::PendingBlock(_) => {
+            anyhow::bail!("block {} is pending", block_number)
+        }
+    }
+}
+
+/// Compares the recorded hashes of unsettled blocks in storage with the ones reported by the rollup.
+/// The first block with a different hash and all the blocks in storage after it are reset and
+/// queued for being ingested again.
+///
+/// Only blocks within the reorg check depth of `finality_policy` are checked, so that the number of
+/// requests doesn't grow with the number of blocks in storage.
+async fn invalidate_reorged_blocks<P, DB>(
+    provider: &P,
+    db: &DB,
+    finality_policy: &FinalityPolicy,
+) -> Result<()>
+where
+    P: Provider + Sync,
+    DB: PersistantStorage + Sync,
+{
+    let depth = match finality_policy.reorg_check_depth() {
+        Some(depth) => depth,
+        None => return Ok(()),
+    };
+    let from_block = provider.block_number().await?.saturating_sub(depth);
+    let from_block = u32::try_from(from_block).unwrap_or(u32::MAX);
+
+    let mut first_reorged_block = None;
+    for (block_number, recorded_hash) in db.list_block_hashes(from_block).await? {
+        let block_hash = get_block_hash(provider, block_number.into()).await?;
+        if block_hash != recorded_hash {
+            warn!(
+                block_number,
+                recorded_hash:% = format!("{:#064x}", recorded_hash),
+                block_hash:% = format!("{:#064x}", block_hash);
+                "Block hash changed, rollup reorg detected"
+            );
+            first_reorged_block = Some(block_number);
+            break;
+        }
+    }
+
+    let first_reorged_block = match first_reorged_block {
+        Some(block_number) => block_number,
+        None => return Ok(()),
+    };
+
+    // Later blocks are reset too, whether their hash changed or not, since they're built on top
+    // of the reorged block.
+    for block in db.list_blocks().await? {
+        if block.block_number >= first_reorged_block {
+            // Deletes all the block artifacts, including its hash, and queues it again.
+            db.add_failed_block(
+                block.block_number,
+                format!("reorg from block {}", first_reorged_block),
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks whether the blocks up to `end_block` left in storage are all failed for good. Settled
+/// blocks are removed from storage, so such a range can't be processed any further until the failed
+/// blocks are reset.
+async fn only_failed_blocks_left<DB>(db: &DB, end_block: u64) -> Result<bool>
+where
+    DB: PersistantStorage + Sync,
+{
+    let blocks = db.list_blocks().await?;
+    let mut blocks = blocks
+        .iter()
+        .filter(|block| u64::from(block.block_number) <= end_block)
+        .filter(|block| block.status != BlockStatus::Settled)
+        .peekable();
+
+    Ok(blocks.peek().is_some() && blocks.all(|block| block.status == BlockStatus::Failed))
+}
+
+/// A handle for pausing and resuming the ingestion of new blocks.
+///
+/// Blocks already being processed are not affected by pausing. Clones of the handle control the
+/// same ingestion state, which makes it possible to keep the state across pipeline restarts.
+#[derive(Debug, Clone)]
+pub struct IngestionHandle {
+    paused: Arc<watch::Sender<bool>>,
+}
+
+impl IngestionHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pause(&self) {
+        self.paused.send_replace(true);
+    }
+
+    pub fn resume(&self) {
+        self.paused.send_replace(false);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Waits asynchronously until ingestion is not paused.
+    pub async fn resumed(&self) {
+        let mut paused = self.paused.subscribe();
+        // The sender is owned by `self` and can never be dropped here.
+        let _ = paused.wait_for(|paused| !paused).await;
+    }
+}
+
+impl Default for IngestionHandle {
+    fn default() -> Self {
+        Self {
+            paused: Arc::new(watch::Sender::new(false)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::SqliteDb;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_only_failed_blocks_left() {
+        let db = SqliteDb::in_memory().await.unwrap();
+
+        // Nothing left to process.
+        assert!(!only_failed_blocks_left(&db, 3).await.unwrap());
+
+        db.initialize_block(2).await.unwrap();
+        db.initialize_block(3).await.unwrap();
+        db.fail_block(2, "invalid proof".to_string()).await.unwrap();
+        assert!(!only_failed_blocks_left(&db, 3).await.unwrap());
+
+        // Blocks retried after a transient failure are still processed.
+        db.add_failed_block(3, "timeout".to_string()).await.unwrap();
+        assert!(!only_failed_blocks_left(&db, 3).await.unwrap());
+
+        db.fail_block(3, "invalid proof".to_string()).await.unwrap();
+        assert!(only_failed_blocks_left(&db, 3).await.unwrap());
+
+        // Blocks after the end of the range are ignored.
+        db.initialize_block(4).await.unwrap();
+        assert!(only_failed_blocks_left(&db, 3).await.unwrap());
+        assert!(!only_failed_blocks_left(&db, 4).await.unwrap());
+    }
+}
@@ -1,64 +1,83 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use ::metrics::{counter, histogram};
 use anyhow::Result;
 use cairo_vm::vm::runners::cairo_pie::CairoPie;
-use log::{debug, error, info, trace};
+use log::{debug, error, info, trace, warn};
 use starknet::providers::{jsonrpc::HttpTransport, JsonRpcClient, Provider};
+use starknet_types_core::felt::Felt;
 use tokio::{
     sync::{
         mpsc::{self, Sender},
         Mutex,
     },
-    task,
+    task::JoinSet,
     time::sleep,
 };
 use url::Url;
 
 use crate::{
-    block_ingestor::{BlockInfo, BlockIngestor, BlockIngestorBuilder},
+    block_ingestor::{
+        get_block_hash, invalidate_reorged_blocks, only_failed_blocks_left, BlockInfo,
+        BlockIngestor, BlockIngestorBuilder, FinalityPolicy, IngestionHandle,
+    },
+    metrics::{PIES_GENERATED, PIE_GENERATION_DURATION},
+    pie_generator::{PieGenerationLimitExceeded, PieGenerator},
     prover::compress_pie,
-    service::{Daemon, FinishHandle, ShutdownHandle},
+    service::{join_workers, Daemon, ExitStatus, FinishHandle, ShutdownHandle},
     storage::{BlockStatus, PersistantStorage, Step},
 };
-use prove_block::prove_block;
 
 const BLOCK_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const REORG_CHECK_INTERVAL: Duration = Duration::from_secs(30);
 const TASK_BUFFER_SIZE: usize = 4;
 const MAX_RETRIES: usize = 3;
+const MAX_LIMIT_VIOLATIONS: u32 = 3;
+/// Prefix of the failure reason of blocks exceeding the PIE generation limits.
+const LIMIT_VIOLATION_PREFIX: &str = "limit exceeded: ";
 
 /// A block ingestor which collects new blocks by polling a Starknet RPC endpoint.
 #[derive(Debug)]
-pub struct PollingBlockIngestor<S, DB> {
+pub struct PollingBlockIngestor<G, DB> {
     rpc_url: Url,
-    snos: S,
+    provider: JsonRpcClient<HttpTransport>,
+    pie_generator: G,
     current_block: u64,
+    end_block: Option<u64>,
     channel: Sender<BlockInfo>,
     finish_handle: FinishHandle,
     db: DB,
     workers_count: usize,
+    ingestion_handle: IngestionHandle,
+    finality_policy: FinalityPolicy,
 }
 
 #[derive(Debug)]
-pub struct PollingBlockIngestorBuilder<S, DB> {
+pub struct PollingBlockIngestorBuilder<G, DB> {
     rpc_url: Url,
-    snos: S,
+    pie_generator: G,
     start_block: Option<u64>,
+    from_block: Option<u64>,
+    to_block: Option<u64>,
     channel: Option<Sender<BlockInfo>>,
     db: DB,
     workers_count: usize,
+    ingestion_handle: IngestionHandle,
+    finality_policy: FinalityPolicy,
 }
 
-impl<S, DB> PollingBlockIngestor<S, DB>
+impl<G, DB> PollingBlockIngestor<G, DB>
 where
-    S: AsRef<[u8]> + Send + Sync + Clone + 'static,
+    G: PieGenerator + Send + Sync + Clone + 'static,
     DB: PersistantStorage + Send + Sync + Clone + 'static,
 {
     /// Fetches the latest block number from the StarkNet RPC.
     async fn get_latest_block(&self) -> Option<u64> {
-        let provider = JsonRpcClient::new(HttpTransport::new(self.rpc_url.clone()));
-
         let block_number = crate::utils::retry_with_backoff(
-            || provider.block_number(),
+            || self.provider.block_number(),
             "get_latest_block",
             MAX_RETRIES as u32,
             Duration::from_secs(5),
@@ -74,18 +93,58 @@ where
         }
     }
 
+    /// Checks whether the current block can be ingested under the finality policy.
+    async fn is_current_block_final(&self, latest_block: u64) -> bool {
+        match self
+            .finality_policy
+            .is_final(&self.provider, self.current_block, latest_block)
+            .await
+        {
+            Ok(is_final) => is_final,
+            Err(err) => {
+                error!(
+                    block_number = self.current_block, error:? = err;
+                    "Failed to check block finality"
+                );
+                false
+            }
+        }
+    }
+
+    /// Checks whether the end block, if any, has been queued.
+    fn is_range_ingested(&self) -> bool {
+        self.end_block
+            .is_some_and(|end_block| self.current_block > end_block)
+    }
+
+    /// Fetches the hash of a block, retrying on failure.
+    async fn fetch_block_hash(
+        provider: &JsonRpcClient<HttpTransport>,
+        block_number: u64,
+    ) -> Result<Felt> {
+        crate::utils::retry_with_backoff(
+            || get_block_hash(provider, block_number),
+            "get_block_hash",
+            MAX_RETRIES as u32,
+            Duration::from_secs(5),
+        )
+        .await
+    }
+
     /// Worker function: proves a block and sends the result.
-    async fn worker(
+    pub(super) async fn worker(
         task_rx: Arc<Mutex<mpsc::Receiver<u64>>>,
         finish_handle: FinishHandle,
         rpc_url: Url,
         channel: mpsc::Sender<BlockInfo>,
-        snos: S,
+        pie_generator: G,
         db: DB,
     ) where
-        S: AsRef<[u8]> + Send + Sync + 'static,
+        G: PieGenerator + Send + Sync + 'static,
         DB: PersistantStorage + Send + Sync + 'static,
     {
+        let provider = JsonRpcClient::new(HttpTransport::new(rpc_url));
+
         loop {
             let block_number = if let Some(block_number) = task_rx.lock().await.recv().await {
                 block_number
@@ -129,20 +188,62 @@ where
                 }
             }
 
-            let (pie, _) = prove_block(
-                snos.as_ref(),
-                block_number,
-                rpc_url.as_str().trim_end_matches("/rpc/v0_7"),
-                cairo_vm::types::layout_name::LayoutName::all_cairo,
-                true,
+            // The hash is recorded before generating the PIE to detect reorgs happening meanwhile.
+            let block_hash = match Self::fetch_block_hash(&provider, block_number).await {
+                Ok(block_hash) => block_hash,
+                Err(err) => {
+                    error!(block_number, error:% = err; "Failed to fetch block hash");
+                    db.add_failed_block(block_number.try_into().unwrap(), err.to_string())
+                        .await
+                        .unwrap();
+                    continue;
+                }
+            };
+            if let Err(err) = crate::utils::retry_with_backoff(
+                || db.set_block_hash(block_number.try_into().unwrap(), block_hash),
+                "set_block_hash",
+                MAX_RETRIES as u32,
+                Duration::from_secs(5),
             )
             .await
-            .unwrap();
+            {
+                error!(block_number, error:% = err; "Failed to record block hash");
+                db.add_failed_block(block_number.try_into().unwrap(), err.to_string())
+                    .await
+                    .unwrap();
+                continue;
+            }
+
+            let pie_generation_start = Instant::now();
+            let pie = match pie_generator.generate_pie(block_number).await {
+                Ok(pie) => pie,
+                Err(err) => {
+                    error!(block_number, error:% = err; "Failed to generate PIE");
+                    Self::record_pie_failure(&db, block_number.try_into().unwrap(), err).await;
+                    continue;
+                }
+            };
 
             if finish_handle.is_shutdown_requested() {
                 break;
             }
 
+            match Self::fetch_block_hash(&provider, block_number).await {
+                Ok(new_block_hash) if new_block_hash == block_hash => {}
+                // The block is queued again once the reorg is detected with the recorded hash.
+                Ok(_) => {
+                    warn!(block_number; "Block reorged while generating its PIE, discarding");
+                    continue;
+                }
+                Err(err) => {
+                    error!(block_number, error:% = err; "Failed to fetch block hash");
+                    db.add_failed_block(block_number.try_into().unwrap(), err.to_string())
+                        .await
+                        .unwrap();
+                    continue;
+                }
+            }
+
             let new_block = BlockInfo {
                 number: block_number,
                 status: BlockStatus::SnosPieGenerated,
@@ -151,10 +252,27 @@ where
             let pie_bytes = compress_pie(pie.clone()).await.unwrap();
             let block_number = block_number.try_into().unwrap();
 
-            db.add_pie(block_number, pie_bytes.clone(), Step::Snos)
+            match db
+                .add_snos_pie_for_block_hash(block_number, pie_bytes, block_hash)
                 .await
-                .unwrap();
+            {
+                Ok(true) => {}
+                // The block is queued again by the reset.
+                Ok(false) => {
+                    warn!(block_number; "Block reset while generating its PIE, discarding");
+                    continue;
+                }
+                Err(err) => {
+                    error!(block_number, error:% = err; "Failed to store PIE");
+                    db.add_failed_block(block_number, err.to_string())
+                        .await
+                        .unwrap();
+                    continue;
+                }
+            }
 
+            histogram!(PIE_GENERATION_DURATION).record(pie_generation_start.elapsed());
+            counter!(PIES_GENERATED).increment(1);
             info!(block_number; "Pie generated for block");
 
             if channel.send(new_block).await.is_err() {
@@ -163,13 +281,84 @@ where
         }
     }
 
+    /// Records the failure of generating the PIE of a block for it to be retried. Blocks exceeding
+    /// the PIE generation limits are likely to keep exceeding them, and are failed for good once
+    /// they have done so `MAX_LIMIT_VIOLATIONS` times.
+    async fn record_pie_failure(db: &DB, block_number: u32, err: anyhow::Error) {
+        if !err.is::<PieGenerationLimitExceeded>() {
+            db.add_failed_block(block_number, err.to_string())
+                .await
+                .unwrap();
+            return;
+        }
+
+        let reason = format!("{}{}", LIMIT_VIOLATION_PREFIX, err);
+        let violations = db
+            .count_failures(block_number, LIMIT_VIOLATION_PREFIX)
+            .await
+            .unwrap()
+            + 1;
+        if violations < MAX_LIMIT_VIOLATIONS {
+            db.add_failed_block(block_number, reason).await.unwrap();
+        } else {
+            error!(
+                block_number;
+                "PIE generation limits exceeded {} times, failing block until it's reset",
+                violations
+            );
+            db.fail_block(block_number, reason).await.unwrap();
+        }
+    }
+
+    /// Sends the failed blocks to retry, then `block_number`, to the worker queue. Returns `false`
+    /// if the queue is closed.
+    pub(super) async fn queue_block(
+        db: &DB,
+        task_tx: &mpsc::Sender<u64>,
+        block_number: u64,
+    ) -> bool {
+        Self::queue_failed_blocks(db, task_tx).await && task_tx.send(block_number).await.is_ok()
+    }
+
+    /// Sends the failed blocks to retry to the worker queue. Returns `false` if the queue is
+    /// closed.
+    pub(super) async fn queue_failed_blocks(db: &DB, task_tx: &mpsc::Sender<u64>) -> bool {
+        if let Ok(mut failed_blocks) = db.get_failed_blocks().await {
+            let block_ids: Vec<u32> = failed_blocks.iter().map(|(id, _)| *id).collect();
+            for (block_id, _) in failed_blocks.drain(..) {
+                if task_tx.send(block_id as u64).await.is_err() {
+                    return false;
+                }
+            }
+            db.mark_failed_blocks_as_handled(&block_ids).await.unwrap();
+        }
+        true
+    }
+
+    /// Checks whether only blocks failed for good are left in the ingested range ending at
+    /// `end_block`, in which case the ingestor finishes and the orchestrator fails the pipeline.
+    pub(super) async fn is_range_abandoned(db: &DB, end_block: u64) -> bool {
+        match only_failed_blocks_left(db, end_block).await {
+            Ok(true) => {
+                error!(end_block; "Only failed blocks are left in the block range, giving up");
+                true
+            }
+            Ok(false) => false,
+            Err(err) => {
+                error!(error:% = err; "Failed to list blocks");
+                false
+            }
+        }
+    }
+
     /// Continuously fetches the latest available block and sends it to the worker queue.
     ///
     /// This loop ensures that blocks are processed sequentially while also handling previously
     /// failed blocks. It will continue running until a shutdown request is received.
     ///
     /// # Process:
-    /// - Checks if the latest available block is greater than or equal to the current block.
+    /// - Periodically checks the blocks in storage for reorgs, queueing reorged blocks again.
+    /// - Checks if the current block is final under the finality policy.
     /// - If there are failed blocks, retrieves them and sends them to the worker queue.
     /// - Marks handled failed blocks in the database.
     /// - Sends the current block to the worker queue and increments `current_block`.
@@ -177,13 +366,16 @@ where
     ///
     /// # Shutdown Handling:
     /// - The loop will terminate if `self.finish_handle.is_shutdown_requested()` returns `true`.
+    /// - Once the end block, if any, has been queued, only failed blocks keep being queued until
+    ///   shutdown, which the orchestrator requests once the whole range is processed. The loop
+    ///   exits once only blocks failed for good are left in the range.
     /// - If sending to the worker queue (`task_tx.send()`) fails, the loop exits early.
     ///
     /// # Blocking Behavior:
     /// - If no new block is available, it waits for `BLOCK_CHECK_INTERVAL` before retrying.
     async fn run(mut self) {
         let (task_tx, task_rx) = mpsc::channel(TASK_BUFFER_SIZE);
-        let mut workers = Vec::new();
+        let mut workers = JoinSet::new();
         let task_rx = Arc::new(Mutex::new(task_rx));
 
         for _ in 0..self.workers_count {
@@ -191,84 +383,151 @@ where
             let finish_handle = self.finish_handle.clone();
             let rpc_url = self.rpc_url.clone();
             let channel = self.channel.clone();
-            let snos = self.snos.clone();
+            let pie_generator = self.pie_generator.clone();
 
-            workers.push(task::spawn(Self::worker(
+            workers.spawn(Self::worker(
                 worker_task_rx,
                 finish_handle,
                 rpc_url,
                 channel,
-                snos,
+                pie_generator,
                 self.db.clone(),
-            )));
+            ));
         }
 
+        let workers = tokio::spawn(join_workers(workers));
+        let mut last_reorg_check = Instant::now();
+
         while !self.finish_handle.is_shutdown_requested() {
-            match self.get_latest_block().await {
-                Some(latest_block) if latest_block >= self.current_block => {
-                    if let Ok(mut failed_blocks) = self.db.get_failed_blocks().await {
-                        let block_ids: Vec<u32> = failed_blocks.iter().map(|(id, _)| *id).collect();
-                        for (block_id, _) in failed_blocks.drain(..) {
-                            if task_tx.send(block_id as u64).await.is_err() {
-                                return;
-                            }
-                        }
-                        self.db
-                            .mark_failed_blocks_as_handled(&block_ids)
-                            .await
-                            .unwrap();
-                    }
-                    if task_tx.send(self.current_block).await.is_err() {
-                        return;
-                    }
-                    self.current_block += 1;
+            // Workers only end by themselves on failure.
+            if workers.is_finished() {
+                break;
+            }
+
+            if self.ingestion_handle.is_paused() {
+                info!(current_block = self.current_block; "Block ingestion paused");
+                tokio::select! {
+                    _ = self.finish_handle.shutdown_requested() => break,
+                    _ = self.ingestion_handle.resumed() => {},
+                }
+                info!(current_block = self.current_block; "Block ingestion resumed");
+            }
+
+            if last_reorg_check.elapsed() >= REORG_CHECK_INTERVAL {
+                last_reorg_check = Instant::now();
+                if let Err(err) =
+                    invalidate_reorged_blocks(&self.provider, &self.db, &self.finality_policy).await
+                {
+                    error!(error:? = err; "Failed to check blocks for reorgs");
+                }
+            }
+
+            if self.is_range_ingested() {
+                if !Self::queue_failed_blocks(&self.db, &task_tx).await {
+                    break;
+                }
+                if Self::is_range_abandoned(&self.db, self.current_block - 1).await {
+                    break;
+                }
+                tokio::select! {
+                    _ = self.finish_handle.shutdown_requested() => break,
+                    _ = sleep(BLOCK_CHECK_INTERVAL) => continue,
+                }
+            }
+
+            let is_final = match self.get_latest_block().await {
+                Some(latest_block) => self.is_current_block_final(latest_block).await,
+                None => false,
+            };
+
+            if is_final {
+                if !Self::queue_block(&self.db, &task_tx, self.current_block).await {
+                    break;
                 }
-                _ => {
-                    sleep(BLOCK_CHECK_INTERVAL).await;
+                self.current_block += 1;
+                if self.is_range_ingested() {
+                    info!(end_block = self.current_block - 1; "Block range ingested");
                 }
+            } else {
+                sleep(BLOCK_CHECK_INTERVAL).await;
             }
         }
 
         drop(task_tx);
-        futures_util::future::join_all(workers).await; // Wait for all workers
+        let status = workers.await.unwrap_or(ExitStatus::Failed); // Wait for all workers
         debug!("Graceful shutdown finished");
-        self.finish_handle.finish();
+        self.finish_handle.exit(status);
     }
 }
 
-impl<S, DB> PollingBlockIngestorBuilder<S, DB> {
-    pub fn new(rpc_url: Url, snos: S, db: DB, workers_count: usize) -> Self {
+impl<G, DB> PollingBlockIngestorBuilder<G, DB> {
+    pub fn new(rpc_url: Url, pie_generator: G, db: DB, workers_count: usize) -> Self {
         Self {
             rpc_url,
-            snos,
+            pie_generator,
             start_block: None,
+            from_block: None,
+            to_block: None,
             channel: None,
             db,
             workers_count,
+            ingestion_handle: IngestionHandle::new(),
+            finality_policy: FinalityPolicy::default(),
         }
     }
+
+    /// Sets the handle for pausing and resuming ingestion.
+    pub fn ingestion_handle(mut self, ingestion_handle: IngestionHandle) -> Self {
+        self.ingestion_handle = ingestion_handle;
+        self
+    }
+
+    /// Sets the condition for blocks to be ingested. Blocks are ingested as soon as they're
+    /// produced by default.
+    pub fn finality_policy(mut self, finality_policy: FinalityPolicy) -> Self {
+        self.finality_policy = finality_policy;
+        self
+    }
+
+    /// Sets the first block to ingest, overriding the start block given by the orchestrator.
+    pub fn from_block(mut self, from_block: u64) -> Self {
+        self.from_block = Some(from_block);
+        self
+    }
+
+    /// Sets the last block to ingest. Failed blocks keep being retried after this block is queued,
+    /// until the ingestor is shut down or only blocks failed for good are left.
+    pub fn to_block(mut self, to_block: u64) -> Self {
+        self.to_block = Some(to_block);
+        self
+    }
 }
 
-impl<S, DB> BlockIngestorBuilder for PollingBlockIngestorBuilder<S, DB>
+impl<G, DB> BlockIngestorBuilder for PollingBlockIngestorBuilder<G, DB>
 where
-    S: AsRef<[u8]> + Send + Sync + Clone + 'static,
+    G: PieGenerator + Send + Sync + Clone + 'static,
     DB: PersistantStorage + Send + Sync + Clone + 'static,
 {
-    type Ingestor = PollingBlockIngestor<S, DB>;
+    type Ingestor = PollingBlockIngestor<G, DB>;
 
     fn build(self) -> Result<Self::Ingestor> {
         Ok(PollingBlockIngestor {
+            provider: JsonRpcClient::new(HttpTransport::new(self.rpc_url.clone())),
             rpc_url: self.rpc_url,
-            snos: self.snos,
+            pie_generator: self.pie_generator,
             current_block: self
-                .start_block
+                .from_block
+                .or(self.start_block)
                 .ok_or_else(|| anyhow::anyhow!("`start_block` not set"))?,
+            end_block: self.to_block,
             channel: self
                 .channel
                 .ok_or_else(|| anyhow::anyhow!("`channel` not set"))?,
             finish_handle: FinishHandle::new(),
             db: self.db,
             workers_count: self.workers_count,
+            ingestion_handle: self.ingestion_handle,
+            finality_policy: self.finality_policy,
         })
     }
 
@@ -283,16 +542,19 @@ where
     }
 }
 
-impl<S, DB> BlockIngestor for PollingBlockIngestor<S, DB>
+impl<G, DB> BlockIngestor for PollingBlockIngestor<G, DB>
 where
-    S: AsRef<[u8]> + Send + Sync + Clone + 'static,
+    G: PieGenerator + Send + Sync + Clone + 'static,
     DB: PersistantStorage + Send + Sync + Clone + 'static,
 {
+    fn end_block(&self) -> Option<u64> {
+        self.end_block
+    }
 }
 
-impl<S, DB> Daemon for PollingBlockIngestor<S, DB>
+impl<G, DB> Daemon for PollingBlockIngestor<G, DB>
 where
-    S: AsRef<[u8]> + Send + Sync + Clone + 'static,
+    G: PieGenerator + Send + Sync + Clone + 'static,
     DB: PersistantStorage + Send + Sync + Clone + 'static,
 {
     fn shutdown_handle(&self) -> ShutdownHandle {
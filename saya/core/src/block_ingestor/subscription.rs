@@ -0,0 +1,412 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use jsonrpsee::{
+    core::client::{ClientT, Subscription, SubscriptionClientT},
+    rpc_params,
+    ws_client::{WsClient, WsClientBuilder},
+};
+use log::{debug, error, info, warn};
+use serde::Deserialize;
+use starknet::providers::{jsonrpc::HttpTransport, JsonRpcClient, Provider};
+use tokio::{
+    sync::{
+        mpsc::{self, Sender},
+        Mutex,
+    },
+    task::JoinSet,
+    time::sleep,
+};
+use url::Url;
+
+use crate::{
+    block_ingestor::{
+        invalidate_reorged_blocks, BlockInfo, BlockIngestor, BlockIngestorBuilder, FinalityPolicy,
+        IngestionHandle, PollingBlockIngestor,
+    },
+    pie_generator::PieGenerator,
+    service::{join_workers, Daemon, ExitStatus, FinishHandle, ShutdownHandle},
+    storage::PersistantStorage,
+};
+
+const BLOCK_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// 1 minute.
+const RESUBSCRIBE_INTERVAL: Duration = Duration::from_secs(60);
+const REORG_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+const TASK_BUFFER_SIZE: usize = 4;
+
+/// A block ingestor which collects new blocks by subscribing to new heads over Starknet JSON-RPC
+/// WebSocket. The HTTP endpoint is polled instead while the subscription is down, until
+/// subscribing succeeds again.
+#[derive(Debug)]
+pub struct SubscriptionBlockIngestor<G, DB> {
+    rpc_url: Url,
+    ws_url: Url,
+    provider: JsonRpcClient<HttpTransport>,
+    pie_generator: G,
+    current_block: u64,
+    end_block: Option<u64>,
+    channel: Sender<BlockInfo>,
+    finish_handle: FinishHandle,
+    db: DB,
+    workers_count: usize,
+    ingestion_handle: IngestionHandle,
+    finality_policy: FinalityPolicy,
+}
+
+#[derive(Debug)]
+pub struct SubscriptionBlockIngestorBuilder<G, DB> {
+    rpc_url: Url,
+    ws_url: Url,
+    pie_generator: G,
+    start_block: Option<u64>,
+    from_block: Option<u64>,
+    to_block: Option<u64>,
+    channel: Option<Sender<BlockInfo>>,
+    db: DB,
+    workers_count: usize,
+    ingestion_handle: IngestionHandle,
+    finality_policy: FinalityPolicy,
+}
+
+/// An active new heads subscription. The subscription ends when the client is dropped.
+struct NewHeads {
+    _client: WsClient,
+    subscription: Subscription<NewHeadNotification>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NewHeadNotification {
+    result: NewHead,
+}
+
+#[derive(Debug, Deserialize)]
+struct NewHead {
+    block_number: u64,
+}
+
+impl<G, DB> SubscriptionBlockIngestor<G, DB>
+where
+    G: PieGenerator + Send + Sync + Clone + 'static,
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    async fn subscribe(&self) -> Result<NewHeads> {
+        let client = WsClientBuilder::default()
+            .build(self.ws_url.as_str())
+            .await?;
+
+        // Notifications are matched by method, as `jsonrpsee` subscriptions expect the ID in a
+        // `subscription` field while Starknet nodes send it as `subscription_id`.
+        let subscription = client
+            .subscribe_to_method("starknet_subscriptionNewHeads")
+            .await?;
+        let _: serde_json::Value = client
+            .request("starknet_subscribeNewHeads", rpc_params![])
+            .await?;
+
+        Ok(NewHeads {
+            _client: client,
+            subscription,
+        })
+    }
+
+    /// Fetches the latest block number from the StarkNet RPC.
+    async fn get_latest_block(&self) -> Option<u64> {
+        match self.provider.block_number().await {
+            Ok(block_number) => Some(block_number),
+            Err(err) => {
+                error!(error:? = err; "Failed to fetch latest block");
+                None
+            }
+        }
+    }
+
+    /// Checks whether the current block can be ingested under the finality policy.
+    async fn is_current_block_final(&self, latest_block: u64) -> bool {
+        match self
+            .finality_policy
+            .is_final(&self.provider, self.current_block, latest_block)
+            .await
+        {
+            Ok(is_final) => is_final,
+            Err(err) => {
+                error!(
+                    block_number = self.current_block, error:? = err;
+                    "Failed to check block finality"
+                );
+                false
+            }
+        }
+    }
+
+    /// Checks whether the end block, if any, has been queued.
+    fn is_range_ingested(&self) -> bool {
+        self.end_block
+            .is_some_and(|end_block| self.current_block > end_block)
+    }
+
+    async fn run(mut self) {
+        let (task_tx, task_rx) = mpsc::channel(TASK_BUFFER_SIZE);
+        let mut workers = JoinSet::new();
+        let task_rx = Arc::new(Mutex::new(task_rx));
+
+        for _ in 0..self.workers_count {
+            workers.spawn(PollingBlockIngestor::<G, DB>::worker(
+                task_rx.clone(),
+                self.finish_handle.clone(),
+                self.rpc_url.clone(),
+                self.channel.clone(),
+                self.pie_generator.clone(),
+                self.db.clone(),
+            ));
+        }
+
+        let mut latest_block: Option<u64> = None;
+        let mut new_heads: Option<NewHeads> = None;
+        let mut last_subscription_attempt: Option<Instant> = None;
+        let workers = tokio::spawn(join_workers(workers));
+        let mut last_reorg_check = Instant::now();
+
+        while !self.finish_handle.is_shutdown_requested() {
+            // Workers only end by themselves on failure.
+            if workers.is_finished() {
+                break;
+            }
+
+            if self.ingestion_handle.is_paused() {
+                info!(current_block = self.current_block; "Block ingestion paused");
+                tokio::select! {
+                    _ = self.finish_handle.shutdown_requested() => break,
+                    _ = self.ingestion_handle.resumed() => {},
+                }
+                info!(current_block = self.current_block; "Block ingestion resumed");
+            }
+
+            if last_reorg_check.elapsed() >= REORG_CHECK_INTERVAL {
+                last_reorg_check = Instant::now();
+                if let Err(err) =
+                    invalidate_reorged_blocks(&self.provider, &self.db, &self.finality_policy).await
+                {
+                    error!(error:? = err; "Failed to check blocks for reorgs");
+                }
+            }
+
+            if self.is_range_ingested() {
+                if !PollingBlockIngestor::<G, DB>::queue_failed_blocks(&self.db, &task_tx).await {
+                    break;
+                }
+                if PollingBlockIngestor::<G, DB>::is_range_abandoned(
+                    &self.db,
+                    self.current_block - 1,
+                )
+                .await
+                {
+                    break;
+                }
+                tokio::select! {
+                    _ = self.finish_handle.shutdown_requested() => break,
+                    _ = sleep(BLOCK_CHECK_INTERVAL) => continue,
+                }
+            }
+
+            if let Some(latest_block) = latest_block {
+                if self.is_current_block_final(latest_block).await {
+                    if !PollingBlockIngestor::<G, DB>::queue_block(
+                        &self.db,
+                        &task_tx,
+                        self.current_block,
+                    )
+                    .await
+                    {
+                        break;
+                    }
+                    self.current_block += 1;
+                    if self.is_range_ingested() {
+                        info!(end_block = self.current_block - 1; "Block range ingested");
+                    }
+                    continue;
+                }
+            }
+
+            if new_heads.is_none()
+                && last_subscription_attempt
+                    .is_none_or(|attempt| attempt.elapsed() >= RESUBSCRIBE_INTERVAL)
+            {
+                last_subscription_attempt = Some(Instant::now());
+                match self.subscribe().await {
+                    Ok(subscription) => {
+                        info!(ws_url:% = self.ws_url; "Subscribed to new blocks");
+                        new_heads = Some(subscription);
+
+                        // Blocks produced before subscribing are not notified.
+                        if let Some(block_number) = self.get_latest_block().await {
+                            latest_block = Some(block_number);
+                        }
+                        continue;
+                    }
+                    Err(err) => {
+                        warn!(error:% = err; "Failed to subscribe to new blocks, polling instead");
+                    }
+                }
+            }
+
+            match &mut new_heads {
+                Some(heads) => {
+                    let notification = tokio::select! {
+                        _ = self.finish_handle.shutdown_requested() => break,
+                        notification = heads.subscription.next() => notification,
+                    };
+
+                    match notification {
+                        Some(Ok(notification)) => {
+                            let block_number = notification.result.block_number;
+                            latest_block =
+                                Some(latest_block.map_or(block_number, |latest_block| {
+                                    latest_block.max(block_number)
+                                }));
+                        }
+                        Some(Err(err)) => {
+                            warn!(error:% = err; "Invalid new block notification");
+                        }
+                        None => {
+                            warn!("New blocks subscription closed, polling instead");
+                            new_heads = None;
+                        }
+                    }
+                }
+                None => {
+                    if let Some(block_number) = self.get_latest_block().await {
+                        latest_block = Some(block_number);
+                    }
+
+                    let is_final = match latest_block {
+                        Some(latest_block) => self.is_current_block_final(latest_block).await,
+                        None => false,
+                    };
+                    if !is_final {
+                        tokio::select! {
+                            _ = self.finish_handle.shutdown_requested() => break,
+                            _ = sleep(BLOCK_CHECK_INTERVAL) => {},
+                        }
+                    }
+                }
+            }
+        }
+
+        drop(task_tx);
+        let status = workers.await.unwrap_or(ExitStatus::Failed); // Wait for all workers
+        debug!("Graceful shutdown finished");
+        self.finish_handle.exit(status);
+    }
+}
+
+impl<G, DB> SubscriptionBlockIngestorBuilder<G, DB> {
+    pub fn new(rpc_url: Url, ws_url: Url, pie_generator: G, db: DB, workers_count: usize) -> Self {
+        Self {
+            rpc_url,
+            ws_url,
+            pie_generator,
+            start_block: None,
+            from_block: None,
+            to_block: None,
+            channel: None,
+            db,
+            workers_count,
+            ingestion_handle: IngestionHandle::new(),
+            finality_policy: FinalityPolicy::default(),
+        }
+    }
+
+    /// Sets the handle for pausing and resuming ingestion.
+    pub fn ingestion_handle(mut self, ingestion_handle: IngestionHandle) -> Self {
+        self.ingestion_handle = ingestion_handle;
+        self
+    }
+
+    /// Sets the condition for blocks to be ingested. Blocks are ingested as soon as they're
+    /// produced by default.
+    pub fn finality_policy(mut self, finality_policy: FinalityPolicy) -> Self {
+        self.finality_policy = finality_policy;
+        self
+    }
+
+    /// Sets the first block to ingest, overriding the start block given by the orchestrator.
+    pub fn from_block(mut self, from_block: u64) -> Self {
+        self.from_block = Some(from_block);
+        self
+    }
+
+    /// Sets the last block to ingest. Failed blocks keep being retried after this block is queued,
+    /// until the ingestor is shut down or only blocks failed for good are left.
+    pub fn to_block(mut self, to_block: u64) -> Self {
+        self.to_block = Some(to_block);
+        self
+    }
+}
+
+impl<G, DB> BlockIngestorBuilder for SubscriptionBlockIngestorBuilder<G, DB>
+where
+    G: PieGenerator + Send + Sync + Clone + 'static,
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    type Ingestor = SubscriptionBlockIngestor<G, DB>;
+
+    fn build(self) -> Result<Self::Ingestor> {
+        Ok(SubscriptionBlockIngestor {
+            provider: JsonRpcClient::new(HttpTransport::new(self.rpc_url.clone())),
+            rpc_url: self.rpc_url,
+            ws_url: self.ws_url,
+            pie_generator: self.pie_generator,
+            current_block: self
+                .from_block
+                .or(self.start_block)
+                .ok_or_else(|| anyhow::anyhow!("`start_block` not set"))?,
+            end_block: self.to_block,
+            channel: self
+                .channel
+                .ok_or_else(|| anyhow::anyhow!("`channel` not set"))?,
+            finish_handle: FinishHandle::new(),
+            db: self.db,
+            workers_count: self.workers_count,
+            ingestion_handle: self.ingestion_handle,
+            finality_policy: self.finality_policy,
+        })
+    }
+
+    fn start_block(mut self, start_block: u64) -> Self {
+        self.start_block = Some(start_block);
+        self
+    }
+
+    fn channel(mut self, channel: Sender<BlockInfo>) -> Self {
+        self.channel = Some(channel);
+        self
+    }
+}
+
+impl<G, DB> BlockIngestor for SubscriptionBlockIngestor<G, DB>
+where
+    G: PieGenerator + Send + Sync + Clone + 'static,
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    fn end_block(&self) -> Option<u64> {
+        self.end_block
+    }
+}
+
+impl<G, DB> Daemon for SubscriptionBlockIngestor<G, DB>
+where
+    G: PieGenerator + Send + Sync + Clone + 'static,
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    fn shutdown_handle(&self) -> ShutdownHandle {
+        self.finish_handle.shutdown_handle()
+    }
+
+    fn start(self) {
+        tokio::spawn(self.run());
+    }
+}
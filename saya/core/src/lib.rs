@@ -7,6 +7,9 @@
 /// Block ingestor abstraction and built-in implementations.
 pub mod block_ingestor;
 
+/// PIE generator abstraction and built-in implementations.
+pub mod pie_generator;
+
 /// Prover abstraction and built-in implementations.
 pub mod prover;
 
@@ -25,5 +28,11 @@ pub mod orchestrator;
 /// Types related to handling long-running background services.
 pub mod service;
 
+/// Metrics emitted by the pipeline.
+pub mod metrics;
+
+/// Embedded HTTP API for inspecting and controlling the pipeline.
+pub mod admin;
+
 /// Internal utilities.
 mod utils;
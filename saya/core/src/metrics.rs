@@ -0,0 +1,150 @@
+use std::{collections::HashSet, time::Duration};
+
+use ::metrics::{describe_counter, describe_gauge, describe_histogram, gauge, Unit};
+use log::{debug, error};
+
+pub use ::metrics::Label;
+
+use crate::{
+    service::{Daemon, FinishHandle, ShutdownHandle},
+    storage::PersistantStorage,
+};
+
+/// Time taken to generate the SNOS PIE of a block.
+pub const PIE_GENERATION_DURATION: &str = "saya_pie_generation_duration_seconds";
+/// Number of SNOS PIEs generated.
+pub const PIES_GENERATED: &str = "saya_pies_generated_total";
+/// Time spent waiting for Atlantic queries to complete, labelled by `status`.
+pub const ATLANTIC_QUERY_DURATION: &str = "saya_atlantic_query_duration_seconds";
+/// Time taken to verify a layout bridge proof on integrity.
+pub const INTEGRITY_VERIFICATION_DURATION: &str = "saya_integrity_verification_duration_seconds";
+/// Fee paid for verifying a layout bridge proof on integrity, in STRK.
+pub const INTEGRITY_VERIFICATION_FEE: &str = "saya_integrity_verification_fee_strk";
+/// Number of blocks in storage, labelled by `status`.
+pub const BLOCKS: &str = "saya_blocks";
+/// Number of failed blocks that have not been requeued yet.
+pub const FAILED_BLOCKS: &str = "saya_failed_blocks";
+/// Number of the latest block settled.
+pub const LATEST_SETTLED_BLOCK: &str = "saya_latest_settled_block";
+
+/// Registers the descriptions of all metrics with the installed recorder.
+pub fn describe() {
+    describe_histogram!(
+        PIE_GENERATION_DURATION,
+        Unit::Seconds,
+        "Time taken to generate the SNOS PIE of a block"
+    );
+    describe_counter!(PIES_GENERATED, Unit::Count, "Number of SNOS PIEs generated");
+    describe_histogram!(
+        ATLANTIC_QUERY_DURATION,
+        Unit::Seconds,
+        "Time spent waiting for Atlantic queries to complete"
+    );
+    describe_histogram!(
+        INTEGRITY_VERIFICATION_DURATION,
+        Unit::Seconds,
+        "Time taken to verify a layout bridge proof on integrity"
+    );
+    describe_histogram!(
+        INTEGRITY_VERIFICATION_FEE,
+        "Fee paid for verifying a layout bridge proof on integrity, in STRK"
+    );
+    describe_gauge!(BLOCKS, Unit::Count, "Number of blocks in storage by status");
+    describe_gauge!(
+        FAILED_BLOCKS,
+        Unit::Count,
+        "Number of failed blocks that have not been requeued yet"
+    );
+    describe_gauge!(LATEST_SETTLED_BLOCK, "Number of the latest block settled");
+}
+
+/// A service that periodically reports gauges derived from the storage state, such as the number of
+/// blocks per status.
+#[derive(Debug)]
+pub struct StorageMetricsReporter<DB> {
+    db: DB,
+    interval: Duration,
+    labels: Vec<Label>,
+    finish_handle: FinishHandle,
+}
+
+impl<DB> StorageMetricsReporter<DB>
+where
+    DB: PersistantStorage + Send + Sync + 'static,
+{
+    pub fn new(db: DB, interval: Duration) -> Self {
+        Self {
+            db,
+            interval,
+            labels: Vec::new(),
+            finish_handle: FinishHandle::new(),
+        }
+    }
+
+    /// Adds labels to all reported metrics, e.g. for telling shards apart in sharding mode.
+    pub fn labels(mut self, labels: Vec<Label>) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    fn labels_with_status(&self, status: &str) -> Vec<Label> {
+        let mut labels = self.labels.clone();
+        labels.push(Label::new("status", status.to_owned()));
+        labels
+    }
+
+    async fn report(&self, reported_statuses: &mut HashSet<String>) {
+        match self.db.get_block_status_counts().await {
+            Ok(counts) => {
+                let mut current_statuses = HashSet::new();
+                for (status, count) in counts {
+                    let status = status.to_string();
+                    gauge!(BLOCKS, self.labels_with_status(&status)).set(count as f64);
+                    current_statuses.insert(status);
+                }
+
+                // Statuses no longer present in storage must be reset explicitly.
+                for status in reported_statuses.difference(&current_statuses) {
+                    gauge!(BLOCKS, self.labels_with_status(status)).set(0.0);
+                }
+                *reported_statuses = current_statuses;
+            }
+            Err(err) => error!(error:% = err; "Failed to count blocks by status"),
+        }
+
+        match self.db.get_failed_blocks().await {
+            Ok(failed_blocks) => {
+                gauge!(FAILED_BLOCKS, self.labels.clone()).set(failed_blocks.len() as f64)
+            }
+            Err(err) => error!(error:% = err; "Failed to get failed blocks"),
+        }
+    }
+
+    async fn run(self) {
+        let mut reported_statuses = HashSet::new();
+        loop {
+            self.report(&mut reported_statuses).await;
+
+            tokio::select! {
+                _ = self.finish_handle.shutdown_requested() => break,
+                _ = tokio::time::sleep(self.interval) => {},
+            }
+        }
+
+        debug!("Graceful shutdown finished");
+        self.finish_handle.finish();
+    }
+}
+
+impl<DB> Daemon for StorageMetricsReporter<DB>
+where
+    DB: PersistantStorage + Send + Sync + 'static,
+{
+    fn shutdown_handle(&self) -> ShutdownHandle {
+        self.finish_handle.shutdown_handle()
+    }
+
+    fn start(self) {
+        tokio::spawn(self.run());
+    }
+}
@@ -4,6 +4,9 @@ pub use sovereign::{SovereignOrchestrator, SovereignOrchestratorBuilder};
 mod persistent;
 pub use persistent::{PersistentOrchestrator, PersistentOrchestratorBuilder};
 
+mod sharding;
+pub use sharding::{ShardingOrchestrator, ShardingOrchestratorBuilder};
+
 #[derive(Debug)]
 pub struct Genesis {
     /// Number or height of the first block that transforms the genesis state. This is usually `0`
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use log::{debug, info};
+use log::{debug, error, info, warn};
 use tokio::sync::mpsc::Receiver;
 
 use crate::{
@@ -8,7 +8,10 @@ use crate::{
         DataAvailabilityBackend, DataAvailabilityBackendBuilder, DataAvailabilityCursor,
     },
     prover::{Prover, ProverBuilder},
-    service::{Daemon, FinishHandle, ShutdownHandle},
+    service::{
+        first_exit, Daemon, ExitStatus, FinishHandle, RestartPolicy, ShutdownHandle,
+        SupervisedChild,
+    },
     settlement::{SettlementBackend, SettlementBackendBuilder, SettlementCursor},
 };
 
@@ -40,10 +43,12 @@ const SETTLE_CURSOR_BUFFER_SIZE: usize = 4;
 #[derive(Debug)]
 pub struct PersistentOrchestrator<I, P, D, S> {
     cursor_channel: Receiver<SettlementCursor>,
+    start_block: u64,
     ingestor: I,
     prover: P,
     da: D,
     settlement: S,
+    policies: RestartPolicies,
     finish_handle: FinishHandle,
 }
 
@@ -53,14 +58,26 @@ pub struct PersistentOrchestratorBuilder<I, P, D, S> {
     prover_builder: P,
     da_builder: D,
     settlement_builder: S,
+    start_block: Option<u64>,
+    policies: RestartPolicies,
+}
+
+/// Restart policies of the descendant services.
+#[derive(Debug, Default, Clone)]
+struct RestartPolicies {
+    ingestor: RestartPolicy,
+    prover: RestartPolicy,
+    da: RestartPolicy,
+    settlement: RestartPolicy,
 }
 
 struct PersistentOrchestratorState {
     cursor_channel: Receiver<SettlementCursor>,
-    ingestor_handle: ShutdownHandle,
-    prover_handle: ShutdownHandle,
-    da_handle: ShutdownHandle,
-    settlement_handle: ShutdownHandle,
+    /// First block not settled yet.
+    next_block: u64,
+    /// Last block of the range being processed, if any.
+    end_block: Option<u64>,
+    children: Vec<SupervisedChild>,
     finish_handle: FinishHandle,
 }
 
@@ -76,8 +93,41 @@ impl<I, P, D, S> PersistentOrchestratorBuilder<I, P, D, S> {
             prover_builder,
             da_builder,
             settlement_builder,
+            start_block: None,
+            policies: RestartPolicies::default(),
         }
     }
+
+    /// Sets the first block to process. As blocks are settled in order, building fails unless the
+    /// start block is the block following the last settled one.
+    pub fn start_block(mut self, start_block: u64) -> Self {
+        self.start_block = Some(start_block);
+        self
+    }
+
+    /// Sets the policy applied when the block ingestor exits unexpectedly.
+    pub fn ingestor_policy(mut self, policy: RestartPolicy) -> Self {
+        self.policies.ingestor = policy;
+        self
+    }
+
+    /// Sets the policy applied when the prover exits unexpectedly.
+    pub fn prover_policy(mut self, policy: RestartPolicy) -> Self {
+        self.policies.prover = policy;
+        self
+    }
+
+    /// Sets the policy applied when the data availability backend exits unexpectedly.
+    pub fn da_policy(mut self, policy: RestartPolicy) -> Self {
+        self.policies.da = policy;
+        self
+    }
+
+    /// Sets the policy applied when the settlement backend exits unexpectedly.
+    pub fn settlement_policy(mut self, policy: RestartPolicy) -> Self {
+        self.policies.settlement = policy;
+        self
+    }
 }
 
 impl<I, P, PV, D, DB, S> PersistentOrchestratorBuilder<I, P, D, S>
@@ -112,10 +162,23 @@ where
         // need for a special case for the genesis block, and `+1` works as expected.
         //
         // TODO: should we change to `settlement.next_block_number()` instead to always return `u64`?
-        let start_block = settlement.get_block_number().await? + 1;
+        let next_to_settle = settlement.get_block_number().await? + 1;
 
         // Now that the special value of `Felt::MAX` is handled, we can use the block number as `u64`.
-        let start_block: u64 = start_block.try_into()?;
+        let next_to_settle: u64 = next_to_settle.try_into()?;
+
+        // Blocks must be settled in order and settled blocks can't be settled again, so the
+        // pipeline can only ever start from the next block to settle. An explicit start block only
+        // guards against an unexpected settlement state.
+        let start_block = match self.start_block {
+            Some(start_block) if start_block != next_to_settle => anyhow::bail!(
+                "start block {} must be the next block to settle {}: blocks are settled in order \
+                and settled blocks can't be processed again",
+                start_block,
+                next_to_settle
+            ),
+            _ => next_to_settle,
+        };
 
         let ingestor = self
             .ingestor_builder
@@ -140,10 +203,12 @@ where
 
         Ok(PersistentOrchestrator {
             cursor_channel: settle_cursor_rx,
+            start_block,
             ingestor,
             prover,
             da,
             settlement,
+            policies: self.policies,
             finish_handle: FinishHandle::new(),
         })
     }
@@ -151,41 +216,69 @@ where
 
 impl PersistentOrchestratorState {
     async fn run(mut self) {
-        loop {
-            // TODO: handle unexpected exit of descendant services
+        let exited_child = loop {
+            if let Some(end_block) = self.end_block {
+                if self.next_block > end_block {
+                    info!(end_block; "Block range processed, shutting down pipeline");
+                    break None;
+                }
+            }
+
             let new_cursor = tokio::select! {
-                _ = self.finish_handle.shutdown_requested() => break,
+                _ = self.finish_handle.shutdown_requested() => break None,
+                exited_child = first_exit(&self.children) => break Some(exited_child),
                 new_cursor = self.cursor_channel.recv() => new_cursor,
             };
 
-            // This should be fine for now as da backends wouldn't drop senders. This might change
-            // in the future.
-            let new_cursor = new_cursor.unwrap();
+            let new_cursor = match new_cursor {
+                Some(new_cursor) => new_cursor,
+                // The settlement backend dropped the sender, which only happens when it exits.
+                None => break Some(first_exit(&self.children).await),
+            };
 
             info!(
                 block_number = new_cursor.block_number,
                 transaction_hash:% = format!("{:#064x}", new_cursor.transaction_hash);
                 "Chain advanced to new block"
             );
-        }
+            self.next_block = self.next_block.max(new_cursor.block_number + 1);
+        };
+
+        let exit_status = exited_child.map(|(child, status)| match status {
+            // The ingestor only finishes by itself when the blocks left in the range failed for
+            // good, which restarting the ingestor can't fix.
+            ExitStatus::Finished if child.name == "ingestor" && self.end_block.is_some() => {
+                error!(
+                    next_block = self.next_block;
+                    "Block range can't be processed any further, shutting down pipeline"
+                );
+                ExitStatus::Failed
+            }
+            status => {
+                warn!(
+                    service = child.name,
+                    exit_status:? = status,
+                    policy:? = child.policy;
+                    "Service exited unexpectedly, shutting down pipeline"
+                );
+                child.policy.exit_status(child.name)
+            }
+        });
 
         // Request graceful shutdown for all descendant services
-        self.ingestor_handle.shutdown();
-        self.prover_handle.shutdown();
-        self.da_handle.shutdown();
-        self.settlement_handle.shutdown();
+        for child in self.children.iter() {
+            child.handle.shutdown();
+        }
 
         // Wait for all descendant services to finish graceful shutdown
-        futures_util::future::join_all([
-            self.ingestor_handle.finished(),
-            self.prover_handle.finished(),
-            self.da_handle.finished(),
-            self.settlement_handle.finished(),
-        ])
-        .await;
+        futures_util::future::join_all(self.children.iter().map(|child| child.handle.exited()))
+            .await;
 
         debug!("Graceful shutdown finished");
-        self.finish_handle.finish();
+        match exit_status {
+            Some(exit_status) => self.finish_handle.exit(exit_status),
+            None => self.finish_handle.finish(),
+        }
     }
 }
 
@@ -203,10 +296,30 @@ where
     fn start(self) {
         let state = PersistentOrchestratorState {
             cursor_channel: self.cursor_channel,
-            ingestor_handle: self.ingestor.shutdown_handle(),
-            prover_handle: self.prover.shutdown_handle(),
-            da_handle: self.da.shutdown_handle(),
-            settlement_handle: self.settlement.shutdown_handle(),
+            next_block: self.start_block,
+            end_block: self.ingestor.end_block(),
+            children: vec![
+                SupervisedChild {
+                    name: "ingestor",
+                    handle: self.ingestor.shutdown_handle(),
+                    policy: self.policies.ingestor,
+                },
+                SupervisedChild {
+                    name: "prover",
+                    handle: self.prover.shutdown_handle(),
+                    policy: self.policies.prover,
+                },
+                SupervisedChild {
+                    name: "da",
+                    handle: self.da.shutdown_handle(),
+                    policy: self.policies.da,
+                },
+                SupervisedChild {
+                    name: "settlement",
+                    handle: self.settlement.shutdown_handle(),
+                    policy: self.policies.settlement,
+                },
+            ],
             finish_handle: self.finish_handle,
         };
 
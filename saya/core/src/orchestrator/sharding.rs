@@ -0,0 +1,228 @@
+use anyhow::Result;
+use log::{debug, info, warn};
+
+use crate::{
+    block_ingestor::{BlockInfo, BlockIngestor, BlockIngestorBuilder},
+    data_availability::{DataAvailabilityBackend, DataAvailabilityBackendBuilder},
+    orchestrator::{PersistentOrchestrator, PersistentOrchestratorBuilder},
+    prover::{Prover, ProverBuilder},
+    service::{Daemon, ExitStatus, FinishHandle, RestartPolicy, ShutdownHandle},
+    settlement::{SettlementBackend, SettlementBackendBuilder},
+};
+
+/// An orchestrator implementation for running multiple rollup shards side by side.
+///
+/// Each shard is an independent rollup network with its own RPC endpoint and its own settlement
+/// contract. The orchestrator runs one full persistent pipeline (ingestion, proving, data
+/// availability and settlement) per shard, built from the usual builder abstractions, and
+/// supervises them as a whole: when any shard stops, all other shards are shut down as well, and
+/// the exit status of the stopped shard is reported as the orchestrator's own. Shards processing a
+/// block range finish independently, and the orchestrator finishes once all of them have.
+#[derive(Debug)]
+pub struct ShardingOrchestrator<I, P, D, S> {
+    shards: Vec<Shard<PersistentOrchestrator<I, P, D, S>>>,
+    finish_handle: FinishHandle,
+}
+
+#[derive(Debug)]
+pub struct ShardingOrchestratorBuilder<I, P, D, S> {
+    shards: Vec<Shard<PersistentOrchestratorBuilder<I, P, D, S>>>,
+    policy: RestartPolicy,
+    start_block: Option<u64>,
+}
+
+/// A named shard pipeline.
+#[derive(Debug)]
+struct Shard<T> {
+    id: String,
+    inner: T,
+}
+
+struct ShardingOrchestratorState {
+    shard_handles: Vec<(String, ShutdownHandle)>,
+    finish_handle: FinishHandle,
+}
+
+impl<I, P, D, S> ShardingOrchestratorBuilder<I, P, D, S> {
+    pub fn new() -> Self {
+        Self {
+            shards: Vec::new(),
+            policy: RestartPolicy::default(),
+            start_block: None,
+        }
+    }
+
+    /// Sets the first block to process on every shard. Building a shard fails unless the start
+    /// block is the block following the last block settled on that shard.
+    pub fn start_block(mut self, start_block: u64) -> Self {
+        self.start_block = Some(start_block);
+        self
+    }
+
+    /// Sets the policy applied when any service of any shard exits unexpectedly.
+    pub fn restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Adds a shard whose pipeline is built from the given builders.
+    ///
+    /// The `id` is only used for identifying the shard in logs.
+    pub fn shard<T>(
+        mut self,
+        id: T,
+        ingestor_builder: I,
+        prover_builder: P,
+        da_builder: D,
+        settlement_builder: S,
+    ) -> Self
+    where
+        T: Into<String>,
+    {
+        self.shards.push(Shard {
+            id: id.into(),
+            inner: PersistentOrchestratorBuilder::new(
+                ingestor_builder,
+                prover_builder,
+                da_builder,
+                settlement_builder,
+            ),
+        });
+        self
+    }
+}
+
+impl<I, P, D, S> Default for ShardingOrchestratorBuilder<I, P, D, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I, P, PV, D, DB, S> ShardingOrchestratorBuilder<I, P, D, S>
+where
+    I: BlockIngestorBuilder + Send,
+    P: ProverBuilder<Prover = PV> + Send,
+    PV: Prover<Statement = BlockInfo, BlockInfo = BlockInfo>,
+    D: DataAvailabilityBackendBuilder<Backend = DB> + Send,
+    DB: DataAvailabilityBackend<Payload = BlockInfo>,
+    S: SettlementBackendBuilder + Send,
+{
+    pub async fn build(
+        self,
+    ) -> Result<ShardingOrchestrator<I::Ingestor, P::Prover, D::Backend, S::Backend>> {
+        if self.shards.is_empty() {
+            anyhow::bail!("at least one shard must be configured");
+        }
+
+        let mut shards = Vec::with_capacity(self.shards.len());
+        for shard in self.shards {
+            let mut builder = shard.inner;
+            if let Some(start_block) = self.start_block {
+                builder = builder.start_block(start_block);
+            }
+
+            let orchestrator = builder
+                .ingestor_policy(self.policy.clone())
+                .prover_policy(self.policy.clone())
+                .da_policy(self.policy.clone())
+                .settlement_policy(self.policy.clone())
+                .build()
+                .await
+                .map_err(|err| {
+                    anyhow::anyhow!("failed to build pipeline for shard `{}`: {}", shard.id, err)
+                })?;
+
+            shards.push(Shard {
+                id: shard.id,
+                inner: orchestrator,
+            });
+        }
+
+        Ok(ShardingOrchestrator {
+            shards,
+            finish_handle: FinishHandle::new(),
+        })
+    }
+}
+
+impl ShardingOrchestratorState {
+    async fn run(self) {
+        // Shards that finished processing their block range are not waited for anymore.
+        let mut running = (0..self.shard_handles.len()).collect::<Vec<_>>();
+
+        let exit_status = loop {
+            if running.is_empty() {
+                info!("All shards finished");
+                break ExitStatus::Finished;
+            }
+
+            let shard_exited = futures_util::future::select_all(
+                running
+                    .iter()
+                    .map(|index| Box::pin(self.shard_handles[*index].1.exited())),
+            );
+
+            tokio::select! {
+                _ = self.finish_handle.shutdown_requested() => break ExitStatus::Finished,
+                (exit_status, position, _) = shard_exited => {
+                    let index = running.remove(position);
+                    if exit_status == ExitStatus::Finished {
+                        info!(shard:% = self.shard_handles[index].0; "Shard pipeline finished");
+                        continue;
+                    }
+
+                    warn!(
+                        shard:% = self.shard_handles[index].0,
+                        exit_status:? = exit_status;
+                        "Shard pipeline exited, shutting down all shards"
+                    );
+                    break exit_status;
+                },
+            }
+        };
+
+        // Request graceful shutdown for all shards
+        for (_, handle) in self.shard_handles.iter() {
+            handle.shutdown();
+        }
+
+        // Wait for all shards to finish graceful shutdown
+        futures_util::future::join_all(
+            self.shard_handles.iter().map(|(_, handle)| handle.exited()),
+        )
+        .await;
+
+        debug!("Graceful shutdown finished");
+        self.finish_handle.exit(exit_status);
+    }
+}
+
+impl<I, P, D, S> Daemon for ShardingOrchestrator<I, P, D, S>
+where
+    I: BlockIngestor + Send,
+    P: Prover + Send,
+    D: DataAvailabilityBackend + Send,
+    S: SettlementBackend + Send,
+{
+    fn shutdown_handle(&self) -> ShutdownHandle {
+        self.finish_handle.shutdown_handle()
+    }
+
+    fn start(self) {
+        let state = ShardingOrchestratorState {
+            shard_handles: self
+                .shards
+                .iter()
+                .map(|shard| (shard.id.clone(), shard.inner.shutdown_handle()))
+                .collect(),
+            finish_handle: self.finish_handle,
+        };
+
+        for shard in self.shards {
+            info!(shard:% = shard.id; "Starting shard pipeline");
+            shard.inner.start();
+        }
+
+        tokio::spawn(state.run());
+    }
+}
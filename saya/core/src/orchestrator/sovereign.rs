@@ -1,5 +1,5 @@
 use anyhow::Result;
-use log::{debug, info};
+use log::{debug, error, info, warn};
 use swiftness_stark::types::StarkProof;
 use tokio::sync::mpsc::Receiver;
 
@@ -11,7 +11,7 @@ use crate::{
     },
     orchestrator::Genesis,
     prover::{Prover, ProverBuilder, SnosProof},
-    service::{Daemon, FinishHandle, ShutdownHandle},
+    service::{first_exit, Daemon, FinishHandle, RestartPolicy, ShutdownHandle, SupervisedChild},
     storage::{BlockWithDa, ChainHead, StorageBackend},
 };
 
@@ -36,10 +36,12 @@ const CURSOR_BUFFER_SIZE: usize = 1;
 #[derive(Debug)]
 pub struct SovereignOrchestrator<I, P, D, S> {
     cursor_channel: Receiver<DataAvailabilityCursor<SnosProof<StarkProof>>>,
+    start_block: u64,
     ingestor: I,
     prover: P,
     da: D,
     storage: S,
+    policies: RestartPolicies,
     finish_handle: FinishHandle,
 }
 
@@ -50,14 +52,25 @@ pub struct SovereignOrchestratorBuilder<I, P, D, S> {
     da_builder: D,
     storage: S,
     genesis: Option<Genesis>,
+    policies: RestartPolicies,
+}
+
+/// Restart policies of the descendant services.
+#[derive(Debug, Default, Clone)]
+struct RestartPolicies {
+    ingestor: RestartPolicy,
+    prover: RestartPolicy,
+    da: RestartPolicy,
 }
 
 struct SovereignOrchestratorState<S> {
     cursor_channel: Receiver<DataAvailabilityCursor<SnosProof<StarkProof>>>,
+    /// First block not published yet.
+    next_block: u64,
+    /// Last block of the range being processed, if any.
+    end_block: Option<u64>,
     storage: S,
-    ingestor_handle: ShutdownHandle,
-    prover_handle: ShutdownHandle,
-    da_handle: ShutdownHandle,
+    children: Vec<SupervisedChild>,
     finish_handle: FinishHandle,
 }
 
@@ -75,8 +88,27 @@ impl<I, P, D, S> SovereignOrchestratorBuilder<I, P, D, S> {
             da_builder,
             storage,
             genesis,
+            policies: RestartPolicies::default(),
         }
     }
+
+    /// Sets the policy applied when the block ingestor exits unexpectedly.
+    pub fn ingestor_policy(mut self, policy: RestartPolicy) -> Self {
+        self.policies.ingestor = policy;
+        self
+    }
+
+    /// Sets the policy applied when the prover exits unexpectedly.
+    pub fn prover_policy(mut self, policy: RestartPolicy) -> Self {
+        self.policies.prover = policy;
+        self
+    }
+
+    /// Sets the policy applied when the data availability backend exits unexpectedly.
+    pub fn da_policy(mut self, policy: RestartPolicy) -> Self {
+        self.policies.da = policy;
+        self
+    }
 }
 
 impl<I, P, PV, D, DB, S> SovereignOrchestratorBuilder<I, P, D, S>
@@ -99,7 +131,7 @@ where
             DataAvailabilityCursor<SnosProof<StarkProof>>,
         >(CURSOR_BUFFER_SIZE);
 
-        let chain_head = self.storage.get_chain_head().await;
+        let chain_head = self.storage.get_chain_head().await?;
         let (start_block, da_builder) = match chain_head {
             ChainHead::Genesis => match self.genesis {
                 Some(genesis) => (
@@ -144,10 +176,12 @@ where
 
         Ok(SovereignOrchestrator {
             cursor_channel: cursor_rx,
+            start_block,
             ingestor,
             prover,
             da,
             storage: self.storage,
+            policies: self.policies,
             finish_handle: FinishHandle::new(),
         })
     }
@@ -158,44 +192,73 @@ where
     S: StorageBackend,
 {
     async fn run(mut self) {
-        loop {
-            // TODO: handle unexpected exit of descendant services
+        let exited_child = loop {
+            if let Some(end_block) = self.end_block {
+                if self.next_block > end_block {
+                    info!(end_block; "Block range processed, shutting down pipeline");
+                    break None;
+                }
+            }
+
             let new_cursor = tokio::select! {
-                _ = self.finish_handle.shutdown_requested() => break,
+                _ = self.finish_handle.shutdown_requested() => break None,
+                exited_child = first_exit(&self.children) => break Some(exited_child),
                 new_cursor = self.cursor_channel.recv() => new_cursor,
             };
 
-            // This should be fine for now as da backends wouldn't drop senders. This might change
-            // in the future.
-            let new_cursor = new_cursor.unwrap();
+            let new_cursor = match new_cursor {
+                Some(new_cursor) => new_cursor,
+                // The DA backend dropped the sender, which only happens when it exits.
+                None => break Some(first_exit(&self.children).await),
+            };
 
             // TODO: error handling
             let da_pointer = new_cursor.pointer.unwrap();
 
-            self.storage
+            if let Err(err) = self
+                .storage
                 .set_chain_head(BlockWithDa {
                     height: new_cursor.block_number,
                     da_pointer,
                 })
-                .await;
+                .await
+            {
+                // The block is already published to DA. Failing to persist the head only means the
+                // block would be re-processed after a restart.
+                error!(
+                    block_number = new_cursor.block_number,
+                    error:% = err;
+                    "Failed to persist chain head"
+                );
+            }
             info!(block_number = new_cursor.block_number; "Chain advanced");
-        }
+            self.next_block = self.next_block.max(new_cursor.block_number + 1);
+        };
+
+        let exit_status = exited_child.map(|(child, status)| {
+            warn!(
+                service = child.name,
+                exit_status:? = status,
+                policy:? = child.policy;
+                "Service exited unexpectedly, shutting down pipeline"
+            );
+            child.policy.exit_status(child.name)
+        });
 
         // Request graceful shutdown for all descendant services
-        self.ingestor_handle.shutdown();
-        self.prover_handle.shutdown();
-        self.da_handle.shutdown();
+        for child in self.children.iter() {
+            child.handle.shutdown();
+        }
 
         // Wait for all descendant services to finish graceful shutdown
-        futures_util::future::join_all([
-            self.ingestor_handle.finished(),
-            self.prover_handle.finished(),
-            self.da_handle.finished(),
-        ])
-        .await;
+        futures_util::future::join_all(self.children.iter().map(|child| child.handle.exited()))
+            .await;
 
         debug!("Graceful shutdown finished");
-        self.finish_handle.finish();
+        match exit_status {
+            Some(exit_status) => self.finish_handle.exit(exit_status),
+            None => self.finish_handle.finish(),
+        }
     }
 }
 
@@ -213,10 +276,26 @@ where
     fn start(self) {
         let state = SovereignOrchestratorState {
             cursor_channel: self.cursor_channel,
+            next_block: self.start_block,
+            end_block: self.ingestor.end_block(),
             storage: self.storage,
-            ingestor_handle: self.ingestor.shutdown_handle(),
-            prover_handle: self.prover.shutdown_handle(),
-            da_handle: self.da.shutdown_handle(),
+            children: vec![
+                SupervisedChild {
+                    name: "ingestor",
+                    handle: self.ingestor.shutdown_handle(),
+                    policy: self.policies.ingestor,
+                },
+                SupervisedChild {
+                    name: "prover",
+                    handle: self.prover.shutdown_handle(),
+                    policy: self.policies.prover,
+                },
+                SupervisedChild {
+                    name: "da",
+                    handle: self.da.shutdown_handle(),
+                    policy: self.policies.da,
+                },
+            ],
             finish_handle: self.finish_handle,
         };
 
@@ -0,0 +1,31 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::pie_generator::{CairoPie, PieGenerator};
+
+/// A PIE generator which loads PIEs generated ahead of time from a directory, such as the ones
+/// written by `saya prove-block --pie-only`.
+///
+/// The PIE of each block is read from a `block_<number>_pie.zip` file.
+#[derive(Debug, Clone)]
+pub struct FilePieGenerator {
+    dir: PathBuf,
+}
+
+impl FilePieGenerator {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+}
+
+impl PieGenerator for FilePieGenerator {
+    async fn generate_pie(&self, block_number: u64) -> Result<CairoPie> {
+        let path = self.dir.join(format!("block_{}_pie.zip", block_number));
+        let pie_bytes = tokio::fs::read(&path).await.map_err(|err| {
+            anyhow::anyhow!("failed to read PIE file {}: {}", path.display(), err)
+        })?;
+
+        Ok(CairoPie::from_bytes(&pie_bytes)?)
+    }
+}
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use prove_block::prove_block;
+use url::Url;
+
+use crate::pie_generator::{CairoPie, LayoutName, PieGenerator};
+
+/// Version of the Starknet JSON-RPC API used by SNOS.
+pub const DEFAULT_RPC_VERSION: &str = "v0_7";
+
+/// A PIE generator which runs the Starknet OS program with `prove_block` inside the Saya process.
+#[derive(Debug, Clone)]
+pub struct InProcessPieGenerator {
+    snos: Arc<Vec<u8>>,
+    base_url: String,
+    layout: LayoutName,
+}
+
+impl InProcessPieGenerator {
+    /// Creates a generator for the rollup at `rpc_url`, serving version `rpc_version` of the
+    /// Starknet JSON-RPC API, e.g. `v0_7`.
+    ///
+    /// `prove_block` appends the `/rpc/<version>` path to the URL of the node itself, so `rpc_url`
+    /// must either be the base URL of the node or end with `/rpc/<rpc_version>`.
+    pub fn new(snos: Vec<u8>, rpc_url: &Url, rpc_version: &str) -> Result<Self> {
+        Ok(Self {
+            snos: Arc::new(snos),
+            base_url: base_url(rpc_url, rpc_version)?,
+            layout: LayoutName::all_cairo,
+        })
+    }
+
+    /// Sets the layout the Starknet OS program is run with. Defaults to `all_cairo`.
+    pub fn layout(mut self, layout: LayoutName) -> Self {
+        self.layout = layout;
+        self
+    }
+}
+
+impl PieGenerator for InProcessPieGenerator {
+    async fn generate_pie(&self, block_number: u64) -> Result<CairoPie> {
+        let (pie, _) = prove_block(&self.snos, block_number, &self.base_url, self.layout, true)
+            .await
+            .map_err(|err| {
+                anyhow::anyhow!(
+                    "failed to generate PIE for block {}: {:?}",
+                    block_number,
+                    err
+                )
+            })?;
+
+        Ok(pie)
+    }
+}
+
+/// Gets the base URL of the node serving `rpc_url`, without trailing slash.
+fn base_url(rpc_url: &Url, rpc_version: &str) -> Result<String> {
+    let path = rpc_url.path().trim_end_matches('/');
+    let base_path = if path.is_empty() {
+        path
+    } else {
+        path.strip_suffix(&format!("/rpc/{}", rpc_version))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "rollup RPC URL `{}` must be the base URL of the node or end with `/rpc/{}`",
+                    rpc_url,
+                    rpc_version
+                )
+            })?
+    };
+
+    let mut base_url = rpc_url.clone();
+    base_url.set_path(base_path);
+    Ok(base_url.as_str().trim_end_matches('/').to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_url() {
+        for rpc_url in [
+            "http://localhost:5050",
+            "http://localhost:5050/",
+            "http://localhost:5050/rpc/v0_7",
+            "http://localhost:5050/rpc/v0_7/",
+        ] {
+            assert_eq!(
+                base_url(&Url::parse(rpc_url).unwrap(), "v0_7").unwrap(),
+                "http://localhost:5050"
+            );
+        }
+
+        assert_eq!(
+            base_url(
+                &Url::parse("https://example.com/katana/rpc/v0_8").unwrap(),
+                "v0_8"
+            )
+            .unwrap(),
+            "https://example.com/katana"
+        );
+
+        assert!(base_url(
+            &Url::parse("http://localhost:5050/rpc/v0_8").unwrap(),
+            "v0_7"
+        )
+        .is_err());
+        assert!(base_url(&Url::parse("http://localhost:5050/katana").unwrap(), "v0_7").is_err());
+    }
+}
@@ -0,0 +1,19 @@
+use std::future::Future;
+
+use anyhow::Result;
+
+mod file;
+pub use file::FilePieGenerator;
+
+mod in_process;
+pub use in_process::{InProcessPieGenerator, DEFAULT_RPC_VERSION};
+
+mod subprocess;
+pub use subprocess::{PieGenerationLimitExceeded, PieGenerationLimits, SubprocessPieGenerator};
+
+pub use cairo_vm::{types::layout_name::LayoutName, vm::runners::cairo_pie::CairoPie};
+
+/// Generates the PIEs of running the Starknet OS program on rollup blocks.
+pub trait PieGenerator {
+    fn generate_pie(&self, block_number: u64) -> impl Future<Output = Result<CairoPie>> + Send;
+}
@@ -0,0 +1,168 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use tokio::{process::Command, time::sleep};
+
+use crate::pie_generator::{CairoPie, PieGenerator};
+
+/// Interval between two checks of the program limits.
+const LIMITS_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+const BYTES_PER_MIB: u64 = 1024 * 1024;
+
+/// A PIE generator which runs a separate program for each block, so that crashes and memory usage
+/// of PIE generation are isolated from the Saya process.
+///
+/// The program is called with the configured arguments, followed by the block number and the path
+/// to write the zipped PIE to. It must exit successfully once the PIE is written.
+#[derive(Debug, Clone)]
+pub struct SubprocessPieGenerator {
+    program: PathBuf,
+    args: Vec<String>,
+    limits: PieGenerationLimits,
+}
+
+/// Limits of the program generating the PIE of a block. The program is killed when exceeding any
+/// of them.
+#[derive(Debug, Clone, Default)]
+pub struct PieGenerationLimits {
+    /// Maximum resident set size in bytes. Only enforced on Linux.
+    pub max_rss: Option<u64>,
+    /// Maximum wall-clock time.
+    pub timeout: Option<Duration>,
+}
+
+/// Error returned when the program generating a PIE is killed for exceeding its limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PieGenerationLimitExceeded {
+    Timeout(Duration),
+    MaxRss(u64),
+}
+
+impl std::fmt::Display for PieGenerationLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Timeout(timeout) => write!(
+                f,
+                "PIE generation timed out after {} seconds",
+                timeout.as_secs()
+            ),
+            Self::MaxRss(max_rss) => write!(
+                f,
+                "PIE generation exceeded the RSS limit of {} MiB",
+                max_rss / BYTES_PER_MIB
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PieGenerationLimitExceeded {}
+
+impl SubprocessPieGenerator {
+    pub fn new(program: PathBuf) -> Self {
+        Self {
+            program,
+            args: Vec::new(),
+            limits: PieGenerationLimits::default(),
+        }
+    }
+
+    /// Sets the arguments passed to the program before the block number.
+    pub fn args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Sets the limits of the program. No limits are enforced by default.
+    pub fn limits(mut self, limits: PieGenerationLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Runs the program until it exits or exceeds a limit, returning the zipped PIE it wrote.
+    async fn run_program(&self, block_number: u64, pie_path: &Path) -> Result<Vec<u8>> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .arg(block_number.to_string())
+            .arg(pie_path)
+            .kill_on_drop(true)
+            .spawn()?;
+        let start = Instant::now();
+
+        let status = loop {
+            tokio::select! {
+                status = child.wait() => break status?,
+                _ = sleep(LIMITS_CHECK_INTERVAL) => {},
+            }
+
+            if let Some(timeout) = self.limits.timeout {
+                if start.elapsed() >= timeout {
+                    child.kill().await?;
+                    return Err(PieGenerationLimitExceeded::Timeout(timeout).into());
+                }
+            }
+
+            if let (Some(max_rss), Some(pid)) = (self.limits.max_rss, child.id()) {
+                if process_rss(pid).is_some_and(|rss| rss > max_rss) {
+                    child.kill().await?;
+                    return Err(PieGenerationLimitExceeded::MaxRss(max_rss).into());
+                }
+            }
+        };
+
+        if !status.success() {
+            anyhow::bail!(
+                "PIE generation for block {} exited with {}",
+                block_number,
+                status
+            );
+        }
+
+        Ok(tokio::fs::read(pie_path).await?)
+    }
+}
+
+impl PieGenerator for SubprocessPieGenerator {
+    async fn generate_pie(&self, block_number: u64) -> Result<CairoPie> {
+        // Each generation gets its own directory, removed on drop, as the same block can be
+        // generated concurrently, e.g. by the pipelines of different shards.
+        let pie_dir = tempfile::Builder::new()
+            .prefix(&format!("saya_block_{}_", block_number))
+            .tempdir()?;
+        let pie_bytes = self
+            .run_program(block_number, &pie_dir.path().join("pie.zip"))
+            .await?;
+
+        Ok(CairoPie::from_bytes(&pie_bytes)?)
+    }
+}
+
+/// Reads the resident set size in bytes of a process from `/proc`, which is only available on
+/// Linux.
+fn process_rss(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let rss_kib = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+
+    Some(rss_kib * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_process_rss_of_current_process() {
+        let rss = process_rss(std::process::id()).unwrap();
+        assert!(rss > 0);
+    }
+}
@@ -119,6 +119,23 @@ impl AtlanticQueryResult {
         }
     }
 }
+/// Starknet network on which Atlantic verifies proofs, registering their facts on integrity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AtlanticNetwork {
+    Testnet,
+    Mainnet,
+}
+
+impl AtlanticNetwork {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Testnet => "TESTNET",
+            Self::Mainnet => "MAINNET",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
 #[allow(non_camel_case_types)]
 pub enum Layout {
@@ -216,12 +233,15 @@ impl AtlanticClient {
         }
     }
 
+    /// Submits a PIE for proving. When `l2_verification` is set, Atlantic also verifies the proof
+    /// on that network once generated.
     pub async fn submit_proof_generation<T>(
         &self,
         compressed_pie: T,
         layout: Layout,
         label: String,
         atlantic_job_size: AtlanticJobSize,
+        l2_verification: Option<AtlanticNetwork>,
     ) -> Result<String, ProverError>
     where
         T: Into<Cow<'static, [u8]>>,
@@ -240,8 +260,16 @@ impl AtlanticClient {
             )
             .text("layout", layout.to_str())
             .text("externalId", label)
-            .text("declaredJobSize", atlantic_job_size.as_str())
-            .text("result", AtlanticQueryResult::ProofGeneration.as_str());
+            .text("declaredJobSize", atlantic_job_size.as_str());
+        let form = match l2_verification {
+            Some(network) => form
+                .text(
+                    "result",
+                    AtlanticQueryResult::ProofVerificationOnL2.as_str(),
+                )
+                .text("network", network.as_str()),
+            None => form.text("result", AtlanticQueryResult::ProofGeneration.as_str()),
+        };
 
         let response = self.http_client.post(url).multipart(form).send().await?;
         if !response.status().is_success() {
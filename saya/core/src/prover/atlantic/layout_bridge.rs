@@ -4,22 +4,25 @@ use crate::{
     block_ingestor::BlockInfo,
     prover::{
         atlantic::{
-            client::{AtlanticClient, Layout},
+            client::{AtlanticClient, AtlanticNetwork, Layout},
             shared::{calculate_job_size, parse_and_store_proof, wait_for_query},
             snos::compress_pie,
         },
         error::ProverError,
         Prover, ProverBuilder, SnosProof,
     },
-    service::{Daemon, FinishHandle, ShutdownHandle},
+    service::{join_workers, Daemon, FinishHandle, ShutdownHandle},
     storage::{PersistantStorage, Step},
 };
 use anyhow::Result;
 use cairo_vm::vm::runners::cairo_pie::CairoPie;
 use log::{debug, info, trace, warn};
-use tokio::sync::{
-    mpsc::{Receiver, Sender},
-    Mutex,
+use tokio::{
+    sync::{
+        mpsc::{Receiver, Sender},
+        Mutex,
+    },
+    task::JoinSet,
 };
 /// Prover implementation as a client to the hosted [Atlantic Prover](https://atlanticprover.com/)
 /// service.
@@ -32,6 +35,7 @@ pub struct AtlanticLayoutBridgeProver<DB> {
     finish_handle: FinishHandle,
     db: DB,
     workers_count: usize,
+    l2_verification: Option<AtlanticNetwork>,
 }
 
 #[derive(Debug)]
@@ -42,6 +46,7 @@ pub struct AtlanticLayoutBridgeProverBuilder<DB> {
     proof_channel: Option<Sender<BlockInfo>>,
     db: DB,
     workers_count: usize,
+    l2_verification: Option<AtlanticNetwork>,
 }
 
 impl<DB> AtlanticLayoutBridgeProver<DB>
@@ -55,6 +60,7 @@ where
         layout_bridge: Cow<'static, [u8]>,
         finish_handle: FinishHandle,
         db: DB,
+        l2_verification: Option<AtlanticNetwork>,
     ) -> Result<(), ProverError>
     where
         DB: PersistantStorage + Send + Sync + 'static,
@@ -262,6 +268,7 @@ where
                         Layout::recursive_with_poseidon,
                         format!("layout-{}", new_snos_proof.block_number),
                         atlantic_job_size,
+                        l2_verification,
                     )
                 },
                 "submit_proof_generation",
@@ -336,7 +343,7 @@ where
     }
 
     async fn run(self) {
-        let mut workers = Vec::new();
+        let mut workers = JoinSet::new();
         let task_rx = Arc::new(Mutex::new(self.statement_channel));
         for _ in 0..self.workers_count {
             let worker_task_rx = task_rx.clone();
@@ -344,19 +351,20 @@ where
             let client = self.client.clone();
             let layout_bridge = self.layout_bridge.clone();
             let finish_handle = self.finish_handle.clone();
-            workers.push(tokio::spawn(Self::worker(
+            workers.spawn(Self::worker(
                 worker_task_rx,
                 task_tx,
                 client,
                 layout_bridge,
                 finish_handle,
                 self.db.clone(),
-            )));
+                self.l2_verification,
+            ));
         }
-        futures_util::future::join_all(workers).await;
+        let status = join_workers(workers).await;
 
         debug!("Graceful shutdown finished");
-        self.finish_handle.finish();
+        self.finish_handle.exit(status);
     }
 }
 
@@ -373,8 +381,16 @@ impl<DB> AtlanticLayoutBridgeProverBuilder<DB> {
             proof_channel: None,
             db,
             workers_count,
+            l2_verification: None,
         }
     }
+
+    /// Has Atlantic verify layout bridge proofs on `network` once generated, registering their
+    /// facts on integrity so that the settlement backend doesn't have to.
+    pub fn l2_verification(mut self, network: AtlanticNetwork) -> Self {
+        self.l2_verification = Some(network);
+        self
+    }
 }
 
 impl<DB> ProverBuilder for AtlanticLayoutBridgeProverBuilder<DB>
@@ -396,6 +412,7 @@ where
             finish_handle: FinishHandle::new(),
             db: self.db,
             workers_count: self.workers_count,
+            l2_verification: self.l2_verification,
         })
     }
 
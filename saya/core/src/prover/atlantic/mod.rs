@@ -7,12 +7,15 @@ mod client;
 mod snos;
 pub use snos::{AtlanticSnosProver, AtlanticSnosProverBuilder};
 
-mod shared;
+pub(crate) mod shared;
 
 mod layout_bridge;
-pub use client::AtlanticClient;
+
+mod one_shot;
+pub use client::{AtlanticClient, AtlanticNetwork};
 pub use layout_bridge::{AtlanticLayoutBridgeProver, AtlanticLayoutBridgeProverBuilder};
-pub use snos::compress_pie;
+pub use one_shot::AtlanticOneShotProver;
+pub use snos::{compress_pie, mock_snos_proof};
 
 pub trait AtlanticProof: Sized {
     fn parse(raw_proof: String) -> Result<Self>;
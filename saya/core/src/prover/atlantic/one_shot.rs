@@ -0,0 +1,180 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use cairo_vm::vm::runners::cairo_pie::CairoPie;
+use log::info;
+use swiftness_stark::types::StarkProof;
+
+use crate::{
+    prover::{
+        atlantic::{
+            client::{AtlanticClient, Layout},
+            shared::{calculate_job_size, parse_and_store_proof, wait_for_query},
+            snos::compress_pie,
+        },
+        SnosProof,
+    },
+    service::FinishHandle,
+    storage::{PersistantStorage, Query, Step},
+};
+
+/// Proves individual blocks on Atlantic outside of a pipeline, e.g. for debugging historical
+/// blocks.
+///
+/// Artifacts and query IDs are recorded in `db` as they would be by the pipeline provers.
+#[derive(Debug)]
+pub struct AtlanticOneShotProver<DB> {
+    client: AtlanticClient,
+    db: DB,
+    /// Only needed for waiting on queries. Proving is aborted by dropping the futures instead.
+    finish_handle: FinishHandle,
+}
+
+impl<DB> AtlanticOneShotProver<DB>
+where
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    pub fn new(api_key: String, db: DB) -> Self {
+        Self {
+            client: AtlanticClient::new(api_key),
+            db,
+            finish_handle: FinishHandle::new(),
+        }
+    }
+
+    /// Generates the SNOS proof from a compressed PIE. Returns the raw proof JSON.
+    pub async fn prove_snos(&self, block_number: u32, compressed_pie: Vec<u8>) -> Result<String> {
+        self.db.initialize_block(block_number).await?;
+
+        let atlantic_job_size = calculate_job_size(CairoPie::from_bytes(&compressed_pie)?);
+        let atlantic_query_id = crate::utils::retry_with_backoff(
+            || {
+                self.client.submit_proof_generation(
+                    compressed_pie.clone(),
+                    Layout::dynamic,
+                    format!("snos_{}", block_number),
+                    atlantic_job_size,
+                    None,
+                )
+            },
+            "submit_proof_generation",
+            3,
+            Duration::from_secs(5),
+        )
+        .await?;
+        self.db
+            .add_query_id(block_number, atlantic_query_id.clone(), Query::SnosProof)
+            .await?;
+
+        info!(
+            block_number,
+            atlantic_query_id:% = atlantic_query_id;
+            "Atlantic proof generation submitted for block"
+        );
+
+        self.wait_for_proof(block_number, atlantic_query_id, Step::Snos)
+            .await
+    }
+
+    /// Generates the layout bridge proof wrapping a raw SNOS proof, by first running the layout
+    /// bridge program on Atlantic. Returns the compressed layout bridge PIE and the raw proof JSON.
+    pub async fn prove_layout_bridge(
+        &self,
+        block_number: u32,
+        layout_bridge: Vec<u8>,
+        snos_proof: &str,
+    ) -> Result<(Vec<u8>, String)> {
+        self.db.initialize_block(block_number).await?;
+
+        // Same wrapping as in `AtlanticLayoutBridgeProver`.
+        let input = format!("{{\n\t\"proof\": {}\n}}", snos_proof);
+        let label = format!("layout-trace-{}", block_number);
+        let atlantic_query_id = crate::utils::retry_with_backoff(
+            || {
+                self.client.submit_trace_generation(
+                    &label,
+                    layout_bridge.clone(),
+                    input.clone().into_bytes(),
+                )
+            },
+            "trace_gen",
+            3,
+            Duration::from_secs(5),
+        )
+        .await?;
+        self.db
+            .add_query_id(block_number, atlantic_query_id.clone(), Query::BridgeTrace)
+            .await?;
+
+        info!(
+            block_number,
+            atlantic_query_id:% = atlantic_query_id;
+            "Atlantic trace generation submitted"
+        );
+
+        let query_response = wait_for_query(
+            self.client.clone(),
+            atlantic_query_id,
+            self.finish_handle.clone(),
+        )
+        .await?;
+        let pie_bytes = query_response.get_pie(&self.client).await?;
+        let compressed_pie = compress_pie(CairoPie::from_bytes(&pie_bytes)?).await?;
+        self.db
+            .add_pie(block_number, compressed_pie.clone(), Step::Bridge)
+            .await?;
+
+        let atlantic_job_size = calculate_job_size(CairoPie::from_bytes(&compressed_pie)?);
+        let atlantic_query_id = crate::utils::retry_with_backoff(
+            || {
+                self.client.submit_proof_generation(
+                    compressed_pie.clone(),
+                    Layout::recursive_with_poseidon,
+                    format!("layout-{}", block_number),
+                    atlantic_job_size,
+                    None,
+                )
+            },
+            "submit_proof_generation",
+            3,
+            Duration::from_secs(5),
+        )
+        .await?;
+        self.db
+            .add_query_id(block_number, atlantic_query_id.clone(), Query::BridgeProof)
+            .await?;
+
+        info!(
+            block_number,
+            atlantic_query_id:% = atlantic_query_id;
+            "Atlantic layout bridge proof generation submitted"
+        );
+
+        let raw_proof = self
+            .wait_for_proof(block_number, atlantic_query_id, Step::Bridge)
+            .await?;
+
+        Ok((compressed_pie, raw_proof))
+    }
+
+    async fn wait_for_proof(
+        &self,
+        block_number: u32,
+        atlantic_query_id: String,
+        step: Step,
+    ) -> Result<String> {
+        let query_response = wait_for_query(
+            self.client.clone(),
+            atlantic_query_id,
+            self.finish_handle.clone(),
+        )
+        .await?;
+        let raw_proof = query_response.get_proof(&self.client).await?;
+
+        // The proof is stored before being parsed, so invalid proofs are still kept for inspection.
+        let _: SnosProof<StarkProof> =
+            parse_and_store_proof(raw_proof.clone(), self.db.clone(), block_number, step).await?;
+
+        Ok(raw_proof)
+    }
+}
@@ -3,13 +3,15 @@ use super::{
     AtlanticClient, AtlanticProof,
 };
 use crate::{
+    metrics::ATLANTIC_QUERY_DURATION,
     prover::{error::ProverError, SnosProof},
     service::FinishHandle,
     storage::{PersistantStorage, Step},
 };
+use ::metrics::histogram;
 use cairo_vm::vm::runners::cairo_pie::CairoPie;
 use log::info;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 const PROOF_STATUS_POLL_INTERVAL: Duration = Duration::from_secs(10);
 
@@ -33,6 +35,7 @@ pub async fn wait_for_query(
     atlantic_query_id: String,
     finish_handle: FinishHandle,
 ) -> Result<AtlanticQueryResponse, ProverError> {
+    let wait_start = Instant::now();
     let response = loop {
         tokio::time::sleep(PROOF_STATUS_POLL_INTERVAL).await;
 
@@ -42,8 +45,14 @@ pub async fn wait_for_query(
 
         if let Ok(query) = client.clone().get_atlantic_query(&atlantic_query_id).await {
             match query.atlantic_query.status {
-                AtlanticQueryStatus::Done => break query,
+                AtlanticQueryStatus::Done => {
+                    histogram!(ATLANTIC_QUERY_DURATION, "status" => "done")
+                        .record(wait_start.elapsed());
+                    break query;
+                }
                 AtlanticQueryStatus::Failed => {
+                    histogram!(ATLANTIC_QUERY_DURATION, "status" => "failed")
+                        .record(wait_start.elapsed());
                     return Err(ProverError::BlockFail(format!(
                         "Proof generation failed for query: {}",
                         atlantic_query_id
@@ -9,7 +9,7 @@ use tokio::{
         mpsc::{Receiver, Sender},
         Mutex,
     },
-    task,
+    task::{self, JoinSet},
 };
 use zip::{write::FileOptions, ZipWriter};
 
@@ -24,7 +24,7 @@ use crate::{
         error::ProverError,
         Prover, ProverBuilder, SnosProof,
     },
-    service::{Daemon, FinishHandle, ShutdownHandle},
+    service::{join_workers, Daemon, FinishHandle, ShutdownHandle},
     storage::{PersistantStorage, Step},
     utils::{compute_program_hash_from_pie, extract_pie_output, stark_proof_mock},
 };
@@ -182,6 +182,7 @@ where
                         Layout::dynamic,
                         format!("snos_{}", new_block.number),
                         atlantic_job_size,
+                        None,
                     )
                 },
                 "submit_proof_generation",
@@ -248,22 +249,22 @@ where
     }
 
     async fn run(self) {
-        let mut workers = Vec::new();
+        let mut workers = JoinSet::new();
         let task_rx = Arc::new(Mutex::new(self.statement_channel));
         for _ in 0..self.worker_count {
             let worker_task_tx = self.proof_channel.clone();
-            workers.push(task::spawn(Self::worker(
+            workers.spawn(Self::worker(
                 task_rx.clone(),
                 worker_task_tx,
                 self.client.clone(),
                 self.finish_handle.clone(),
                 self.mock_snos_from_pie,
                 self.db.clone(),
-            )));
+            ));
         }
-        futures_util::future::join_all(workers).await;
+        let status = join_workers(workers).await;
         debug!("Graceful shutdown finished");
-        self.finish_handle.finish();
+        self.finish_handle.exit(status);
     }
 
     async fn mock_proof(new_block: BlockInfo, task_tx: Sender<SnosProof<P>>, db: DB) -> Result<()> {
@@ -275,8 +276,7 @@ where
             .await
             .unwrap();
         let cairo_pie = CairoPie::from_bytes(&pie).unwrap();
-        let output = bootloader_snos_output(&cairo_pie);
-        let mock_proof = stark_proof_mock(&output);
+        let mock_proof = mock_snos_proof(&cairo_pie);
 
         info!(
             block_number = new_block.number;
@@ -285,7 +285,7 @@ where
 
         let new_proof = SnosProof {
             block_number: new_block.number,
-            proof: P::parse(serde_json::to_string(&mock_proof).unwrap()).unwrap(),
+            proof: P::parse(mock_proof).unwrap(),
         };
 
         let _ = task_tx.send(new_proof).await;
@@ -391,6 +391,13 @@ pub async fn compress_pie(pie: CairoPie) -> std::result::Result<Vec<u8>, std::io
     .await?
 }
 
+/// Mocks the proof of a bootloaded execution of SNOS from its PIE, without proving it. Returns the
+/// proof JSON.
+pub fn mock_snos_proof(pie: &CairoPie) -> String {
+    let output = bootloader_snos_output(pie);
+    serde_json::to_string(&stark_proof_mock(&output)).unwrap()
+}
+
 /// Mocks a bootloaded execution of SNOS.
 fn bootloader_snos_output(pie: &CairoPie) -> Vec<Felt> {
     let snos_program_hash =
@@ -0,0 +1,307 @@
+use std::{future::Future, time::Duration};
+
+use log::warn;
+use prover_sdk::{
+    access_key::ProverAccessKey, sdk::ProverSDK, Cairo0ProverInput, JobResponse, PieProverInput,
+};
+use serde_json::Value;
+use url::Url;
+
+use crate::{prover::error::ProverError, service::FinishHandle};
+
+const JOB_STATUS_POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// Number of consecutive failures to get the status of a job after which the job is given up on,
+/// i.e. 5 minutes.
+const MAX_JOB_STATUS_FAILURES: u32 = 30;
+
+/// A thin client around the `prover-sdk` for talking to a self-hosted
+/// [http-prover](https://github.com/cartridge-gg/http-prover) instance.
+///
+/// A new SDK session is established for each operation, as sessions are short-lived on the server
+/// side and jobs can run for a long time.
+#[derive(Debug, Clone)]
+pub struct HttpProverClient {
+    url: Url,
+    access_key: String,
+}
+
+impl HttpProverClient {
+    pub fn new(url: Url, access_key: String) -> Self {
+        Self { url, access_key }
+    }
+
+    async fn sdk(&self) -> Result<ProverSDK, ProverError> {
+        let access_key = ProverAccessKey::from_hex_string(&self.access_key)
+            .map_err(|err| ProverError::Prover(format!("invalid access key: {}", err)))?;
+
+        ProverSDK::new(self.url.clone(), access_key)
+            .await
+            .map_err(|err| ProverError::Prover(format!("failed to connect to prover: {}", err)))
+    }
+
+    /// Submits a compressed PIE for proving, returning the job ID.
+    pub async fn submit_pie(
+        &self,
+        compressed_pie: Vec<u8>,
+        layout: &str,
+    ) -> Result<String, ProverError> {
+        let job_id = self
+            .sdk()
+            .await?
+            .prove_pie(PieProverInput {
+                pie: compressed_pie,
+                layout: layout.to_string(),
+            })
+            .await
+            .map_err(|err| ProverError::Prover(format!("failed to submit PIE: {}", err)))?;
+
+        Ok(job_id.to_string())
+    }
+
+    /// Submits a Cairo 0 program along with its input for running and proving, returning the job
+    /// ID.
+    pub async fn submit_program(
+        &self,
+        program: &[u8],
+        input: &str,
+        layout: &str,
+    ) -> Result<String, ProverError> {
+        let program = serde_json::from_slice(program)
+            .map_err(|err| ProverError::Prover(format!("invalid program: {}", err)))?;
+        let program_input = serde_json::from_str::<Value>(input)
+            .map_err(|err| ProverError::Prover(format!("invalid program input: {}", err)))?;
+
+        let job_id = self
+            .sdk()
+            .await?
+            .prove_cairo0(Cairo0ProverInput {
+                program,
+                program_input,
+                layout: layout.to_string(),
+                n_queries: None,
+                pow_bits: None,
+            })
+            .await
+            .map_err(|err| ProverError::Prover(format!("failed to submit program: {}", err)))?;
+
+        Ok(job_id.to_string())
+    }
+
+    /// Waits for a job to complete, returning the raw proof.
+    ///
+    /// Gives up on the job once its status can't be fetched [`MAX_JOB_STATUS_FAILURES`] times in a
+    /// row.
+    pub async fn wait_for_job(
+        &self,
+        job_id: &str,
+        finish_handle: FinishHandle,
+    ) -> Result<String, ProverError> {
+        let parsed_job_id: u64 = job_id
+            .parse()
+            .map_err(|_| ProverError::Prover(format!("invalid job ID: {}", job_id)))?;
+
+        poll_job(job_id, finish_handle, JOB_STATUS_POLL_INTERVAL, || {
+            self.job_state(parsed_job_id)
+        })
+        .await
+    }
+
+    async fn job_state(&self, job_id: u64) -> Result<JobState, ProverError> {
+        let response = self
+            .sdk()
+            .await?
+            .get_job(job_id)
+            .await
+            .map_err(|err| ProverError::Prover(format!("failed to get job: {}", err)))?;
+
+        let response = response
+            .json::<JobResponse>()
+            .await
+            .map_err(|err| ProverError::Prover(format!("invalid job response: {}", err)))?;
+
+        Ok(response.into())
+    }
+}
+
+/// State of a proving job as reported by the prover.
+#[derive(Debug)]
+enum JobState {
+    Running,
+    Completed(String),
+    Failed(String),
+}
+
+impl From<JobResponse> for JobState {
+    fn from(response: JobResponse) -> Self {
+        match response {
+            JobResponse::Completed { result, .. } => Self::Completed(result.proof),
+            JobResponse::Failed { error, .. } => Self::Failed(error),
+            _ => Self::Running,
+        }
+    }
+}
+
+/// Polls the state of a job until it completes or fails.
+async fn poll_job<F, Fut>(
+    job_id: &str,
+    finish_handle: FinishHandle,
+    poll_interval: Duration,
+    mut job_state: F,
+) -> Result<String, ProverError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<JobState, ProverError>>,
+{
+    let mut failures = 0;
+    loop {
+        tokio::time::sleep(poll_interval).await;
+
+        if finish_handle.is_shutdown_requested() {
+            return Err(ProverError::Shutdown);
+        }
+
+        match job_state().await {
+            Ok(JobState::Running) => failures = 0,
+            Ok(JobState::Completed(proof)) => return Ok(proof),
+            Ok(JobState::Failed(error)) => {
+                return Err(ProverError::BlockFail(format!(
+                    "Proof generation failed for job {}: {}",
+                    job_id, error
+                )));
+            }
+            Err(err) => {
+                failures += 1;
+                warn!(job_id:% = job_id, failures, error:% = err; "Failed to get http-prover job status");
+
+                if failures >= MAX_JOB_STATUS_FAILURES {
+                    return Err(ProverError::BlockFail(format!(
+                        "Failed to get status of job {} {} times in a row: {}",
+                        job_id, failures, err
+                    )));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+    };
+
+    use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
+    use serde_json::json;
+
+    use super::*;
+
+    const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+    type Responses = Arc<Mutex<VecDeque<(StatusCode, Value)>>>;
+
+    /// Checks that `body` is a valid http-prover job response, returning it as served by
+    /// http-prover.
+    fn job_response(body: Value) -> Value {
+        let response = serde_json::from_value::<JobResponse>(body).unwrap();
+        serde_json::to_value(response).unwrap()
+    }
+
+    fn running() -> Value {
+        job_response(json!({ "status": "running", "id": 1 }))
+    }
+
+    fn completed(proof: &str) -> Value {
+        job_response(json!({
+            "status": "completed",
+            "result": {
+                "proof": proof,
+                "serialized_proof": ["0x1"],
+                "program_hash": "0x2",
+                "program_output": ["0x3"],
+                "program_output_hash": "0x4",
+            },
+            "verified": "true",
+        }))
+    }
+
+    fn failed(error: &str) -> Value {
+        job_response(json!({ "status": "failed", "error": error }))
+    }
+
+    /// Serves the given responses to job status requests in order, repeating the last one.
+    async fn mock_prover(responses: Vec<(StatusCode, Value)>) -> Url {
+        let responses = Arc::new(Mutex::new(VecDeque::from(responses)));
+        let router = Router::new()
+            .route(
+                "/job",
+                get(|State(responses): State<Responses>| async move {
+                    let mut responses = responses.lock().unwrap();
+                    let (status, body) = if responses.len() > 1 {
+                        responses.pop_front().unwrap()
+                    } else {
+                        responses.front().unwrap().clone()
+                    };
+                    (status, Json(body))
+                }),
+            )
+            .with_state(responses);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        Url::parse(&format!("http://{}/job", addr)).unwrap()
+    }
+
+    /// Gets the job state the same way as [`HttpProverClient::job_state`], without an SDK session.
+    async fn mock_job_state(url: Url) -> Result<JobState, ProverError> {
+        let response = reqwest::get(url).await?.error_for_status()?;
+        Ok(response.json::<JobResponse>().await?.into())
+    }
+
+    #[tokio::test]
+    async fn test_poll_job_completed() {
+        let url = mock_prover(vec![
+            (StatusCode::OK, running()),
+            (StatusCode::INTERNAL_SERVER_ERROR, json!({})),
+            (StatusCode::OK, completed("0x1234")),
+        ])
+        .await;
+
+        let proof = poll_job("1", FinishHandle::new(), POLL_INTERVAL, || {
+            mock_job_state(url.clone())
+        })
+        .await
+        .unwrap();
+        assert_eq!(proof, "0x1234");
+    }
+
+    #[tokio::test]
+    async fn test_poll_job_failed() {
+        let url = mock_prover(vec![
+            (StatusCode::OK, running()),
+            (StatusCode::OK, failed("out of memory")),
+        ])
+        .await;
+
+        let err = poll_job("1", FinishHandle::new(), POLL_INTERVAL, || {
+            mock_job_state(url.clone())
+        })
+        .await
+        .unwrap_err();
+        assert!(matches!(err, ProverError::BlockFail(error) if error.contains("out of memory")));
+    }
+
+    #[tokio::test]
+    async fn test_poll_job_unreachable() {
+        let url = mock_prover(vec![(StatusCode::INTERNAL_SERVER_ERROR, json!({}))]).await;
+
+        let err = poll_job("1", FinishHandle::new(), POLL_INTERVAL, || {
+            mock_job_state(url.clone())
+        })
+        .await
+        .unwrap_err();
+        assert!(matches!(err, ProverError::BlockFail(error) if error.contains("in a row")));
+    }
+}
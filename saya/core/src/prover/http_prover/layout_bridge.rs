@@ -0,0 +1,265 @@
+use std::{borrow::Cow, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use log::{debug, error, info, trace, warn};
+use tokio::{
+    sync::{
+        mpsc::{Receiver, Sender},
+        Mutex,
+    },
+    task::JoinSet,
+};
+use url::Url;
+
+use crate::{
+    block_ingestor::BlockInfo,
+    prover::{
+        atlantic::shared::parse_and_store_proof, error::ProverError,
+        http_prover::client::HttpProverClient, Prover, ProverBuilder, SnosProof,
+    },
+    service::{join_workers, Daemon, FinishHandle, ShutdownHandle},
+    storage::{BlockStatus, PersistantStorage, Query, Step},
+};
+
+/// Layout used for proving the layout bridge program.
+pub(crate) const LAYOUT_BRIDGE_LAYOUT: &str = "recursive_with_poseidon";
+
+/// Layout bridge prover implementation as a client to a self-hosted
+/// [http-prover](https://github.com/cartridge-gg/http-prover) instance.
+///
+/// Unlike with Atlantic, the layout bridge program is run and proven in a single job.
+#[derive(Debug)]
+pub struct HttpProverLayoutBridgeProver<DB> {
+    client: HttpProverClient,
+    layout_bridge: Cow<'static, [u8]>,
+    statement_channel: Receiver<SnosProof<String>>,
+    proof_channel: Sender<BlockInfo>,
+    finish_handle: FinishHandle,
+    db: DB,
+    workers_count: usize,
+}
+
+#[derive(Debug)]
+pub struct HttpProverLayoutBridgeProverBuilder<DB> {
+    url: Url,
+    access_key: String,
+    layout_bridge: Cow<'static, [u8]>,
+    statement_channel: Option<Receiver<SnosProof<String>>>,
+    proof_channel: Option<Sender<BlockInfo>>,
+    db: DB,
+    workers_count: usize,
+}
+
+impl<DB> HttpProverLayoutBridgeProver<DB>
+where
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    async fn worker(
+        task_rx: Arc<Mutex<Receiver<SnosProof<String>>>>,
+        task_tx: Sender<BlockInfo>,
+        client: HttpProverClient,
+        layout_bridge: Cow<'static, [u8]>,
+        finish_handle: FinishHandle,
+        db: DB,
+    ) -> Result<(), ProverError> {
+        loop {
+            let new_snos_proof = if let Some(new_block) = task_rx.lock().await.recv().await {
+                new_block
+            } else {
+                break;
+            };
+
+            let block_number_u32 = new_snos_proof.block_number.try_into().unwrap();
+
+            if let Ok(proof) = db.get_proof(block_number_u32, Step::Bridge).await {
+                // Sanity check if the proof is valid.
+                if swiftness::parse(String::from_utf8(proof).unwrap()).is_ok() {
+                    trace!(
+                        block_number = new_snos_proof.block_number;
+                        "Proof already generated for block"
+                    );
+                    let block_info = BlockInfo {
+                        number: new_snos_proof.block_number,
+                        status: BlockStatus::BridgeProofGenerated,
+                    };
+
+                    task_tx.send(block_info).await.unwrap();
+                    continue;
+                } else {
+                    warn!(
+                        block_number = new_snos_proof.block_number;
+                        "Invalid proof found in db, not using proof from db.",
+                    );
+                }
+            }
+
+            let job_id = match db.get_query_id(block_number_u32, Query::BridgeProof).await {
+                Ok(job_id) => {
+                    info!(
+                        block_number = new_snos_proof.block_number,
+                        job_id:% = job_id;
+                        "Proof generation already submitted for block"
+                    );
+                    job_id
+                }
+                Err(_) => {
+                    // Hacky way to wrap proof due to the lack of serialization support for the parsed type
+                    // TODO: patch `swiftness` and fix this
+                    let input = format!("{{\n\t\"proof\": {}\n}}", new_snos_proof.proof);
+
+                    let job_id = crate::utils::retry_with_backoff(
+                        || client.submit_program(&layout_bridge, &input, LAYOUT_BRIDGE_LAYOUT),
+                        "submit_program",
+                        3,
+                        Duration::from_secs(5),
+                    )
+                    .await?;
+
+                    db.add_query_id(block_number_u32, job_id.clone(), Query::BridgeProof)
+                        .await
+                        .unwrap();
+
+                    info!(
+                        block_number = new_snos_proof.block_number,
+                        job_id:% = job_id;
+                        "http-prover layout bridge proof generation submitted",
+                    );
+                    job_id
+                }
+            };
+
+            let raw_proof = match client.wait_for_job(&job_id, finish_handle.clone()).await {
+                Ok(raw_proof) => raw_proof,
+                Err(ProverError::Shutdown) => break,
+                Err(err) => {
+                    error!(
+                        block_number = new_snos_proof.block_number,
+                        error:% = err;
+                        "Proof generation failed"
+                    );
+                    db.add_failed_block(block_number_u32, err.to_string())
+                        .await
+                        .unwrap();
+                    continue;
+                }
+            };
+
+            let _: SnosProof<String> =
+                parse_and_store_proof(raw_proof, db.clone(), block_number_u32, Step::Bridge)
+                    .await?;
+
+            debug!(
+                block_number = new_snos_proof.block_number,
+                job_id:% = job_id;
+                "http-prover layout bridge proof generation finished",
+            );
+
+            let new_proof = BlockInfo {
+                number: new_snos_proof.block_number,
+                status: BlockStatus::BridgeProofGenerated,
+            };
+
+            tokio::select! {
+                _ = finish_handle.shutdown_requested() => break,
+                _ = task_tx.send(new_proof) => {},
+            }
+        }
+        Ok(())
+    }
+
+    async fn run(self) {
+        let mut workers = JoinSet::new();
+        let task_rx = Arc::new(Mutex::new(self.statement_channel));
+        for _ in 0..self.workers_count {
+            workers.spawn(Self::worker(
+                task_rx.clone(),
+                self.proof_channel.clone(),
+                self.client.clone(),
+                self.layout_bridge.clone(),
+                self.finish_handle.clone(),
+                self.db.clone(),
+            ));
+        }
+        let status = join_workers(workers).await;
+
+        debug!("Graceful shutdown finished");
+        self.finish_handle.exit(status);
+    }
+}
+
+impl<DB> HttpProverLayoutBridgeProverBuilder<DB> {
+    pub fn new<P>(
+        url: Url,
+        access_key: String,
+        layout_bridge: P,
+        db: DB,
+        workers_count: usize,
+    ) -> Self
+    where
+        P: Into<Cow<'static, [u8]>>,
+    {
+        Self {
+            url,
+            access_key,
+            layout_bridge: layout_bridge.into(),
+            statement_channel: None,
+            proof_channel: None,
+            db,
+            workers_count,
+        }
+    }
+}
+
+impl<DB> ProverBuilder for HttpProverLayoutBridgeProverBuilder<DB>
+where
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    type Prover = HttpProverLayoutBridgeProver<DB>;
+
+    fn build(self) -> Result<Self::Prover> {
+        Ok(HttpProverLayoutBridgeProver {
+            client: HttpProverClient::new(self.url, self.access_key),
+            layout_bridge: self.layout_bridge,
+            statement_channel: self
+                .statement_channel
+                .ok_or_else(|| anyhow::anyhow!("`statement_channel` not set"))?,
+            proof_channel: self
+                .proof_channel
+                .ok_or_else(|| anyhow::anyhow!("`proof_channel` not set"))?,
+            finish_handle: FinishHandle::new(),
+            db: self.db,
+            workers_count: self.workers_count,
+        })
+    }
+
+    fn statement_channel(mut self, statement_channel: Receiver<SnosProof<String>>) -> Self {
+        self.statement_channel = Some(statement_channel);
+        self
+    }
+
+    fn proof_channel(mut self, proof_channel: Sender<BlockInfo>) -> Self {
+        self.proof_channel = Some(proof_channel);
+        self
+    }
+}
+
+impl<DB> Prover for HttpProverLayoutBridgeProver<DB>
+where
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    type Statement = SnosProof<String>;
+    type BlockInfo = BlockInfo;
+}
+
+impl<DB> Daemon for HttpProverLayoutBridgeProver<DB>
+where
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    fn shutdown_handle(&self) -> ShutdownHandle {
+        self.finish_handle.shutdown_handle()
+    }
+
+    fn start(self) {
+        tokio::spawn(self.run());
+    }
+}
@@ -0,0 +1,11 @@
+mod client;
+pub use client::HttpProverClient;
+
+mod snos;
+pub use snos::{HttpProverSnosProver, HttpProverSnosProverBuilder};
+
+mod layout_bridge;
+pub use layout_bridge::{HttpProverLayoutBridgeProver, HttpProverLayoutBridgeProverBuilder};
+
+mod one_shot;
+pub use one_shot::HttpProverOneShotProver;
@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use log::info;
+use swiftness_stark::types::StarkProof;
+use url::Url;
+
+use crate::{
+    prover::{
+        atlantic::shared::parse_and_store_proof,
+        http_prover::{
+            client::HttpProverClient, layout_bridge::LAYOUT_BRIDGE_LAYOUT, snos::SNOS_LAYOUT,
+        },
+        SnosProof,
+    },
+    service::FinishHandle,
+    storage::{PersistantStorage, Query, Step},
+};
+
+/// Proves individual blocks on an http-prover instance outside of a pipeline, e.g. for debugging
+/// historical blocks.
+///
+/// Artifacts and job IDs are recorded in `db` as they would be by the pipeline provers.
+#[derive(Debug)]
+pub struct HttpProverOneShotProver<DB> {
+    client: HttpProverClient,
+    db: DB,
+    /// Only needed for waiting on jobs. Proving is aborted by dropping the futures instead.
+    finish_handle: FinishHandle,
+}
+
+impl<DB> HttpProverOneShotProver<DB>
+where
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    pub fn new(url: Url, access_key: String, db: DB) -> Self {
+        Self {
+            client: HttpProverClient::new(url, access_key),
+            db,
+            finish_handle: FinishHandle::new(),
+        }
+    }
+
+    /// Generates the SNOS proof from a compressed PIE. Returns the raw proof JSON.
+    pub async fn prove_snos(&self, block_number: u32, compressed_pie: Vec<u8>) -> Result<String> {
+        self.db.initialize_block(block_number).await?;
+
+        let job_id = crate::utils::retry_with_backoff(
+            || self.client.submit_pie(compressed_pie.clone(), SNOS_LAYOUT),
+            "submit_pie",
+            3,
+            Duration::from_secs(5),
+        )
+        .await?;
+        self.db
+            .add_query_id(block_number, job_id.clone(), Query::SnosProof)
+            .await?;
+
+        info!(
+            block_number,
+            job_id:% = job_id;
+            "http-prover proof generation submitted for block"
+        );
+
+        self.wait_for_proof(block_number, &job_id, Step::Snos).await
+    }
+
+    /// Generates the layout bridge proof wrapping a raw SNOS proof. The layout bridge program is
+    /// run by http-prover itself, so unlike on Atlantic no PIE is produced. Returns the raw proof
+    /// JSON.
+    pub async fn prove_layout_bridge(
+        &self,
+        block_number: u32,
+        layout_bridge: Vec<u8>,
+        snos_proof: &str,
+    ) -> Result<String> {
+        self.db.initialize_block(block_number).await?;
+
+        // Same wrapping as in `HttpProverLayoutBridgeProver`.
+        let input = format!("{{\n\t\"proof\": {}\n}}", snos_proof);
+        let job_id = crate::utils::retry_with_backoff(
+            || {
+                self.client
+                    .submit_program(&layout_bridge, &input, LAYOUT_BRIDGE_LAYOUT)
+            },
+            "submit_program",
+            3,
+            Duration::from_secs(5),
+        )
+        .await?;
+        self.db
+            .add_query_id(block_number, job_id.clone(), Query::BridgeProof)
+            .await?;
+
+        info!(
+            block_number,
+            job_id:% = job_id;
+            "http-prover layout bridge proof generation submitted"
+        );
+
+        self.wait_for_proof(block_number, &job_id, Step::Bridge)
+            .await
+    }
+
+    async fn wait_for_proof(&self, block_number: u32, job_id: &str, step: Step) -> Result<String> {
+        let raw_proof = self
+            .client
+            .wait_for_job(job_id, self.finish_handle.clone())
+            .await?;
+
+        // The proof is stored before being parsed, so invalid proofs are still kept for inspection.
+        let _: SnosProof<StarkProof> =
+            parse_and_store_proof(raw_proof.clone(), self.db.clone(), block_number, step).await?;
+
+        Ok(raw_proof)
+    }
+}
@@ -0,0 +1,246 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use log::{debug, error, info, trace};
+use tokio::{
+    sync::{
+        mpsc::{Receiver, Sender},
+        Mutex,
+    },
+    task::JoinSet,
+};
+use url::Url;
+
+use crate::{
+    block_ingestor::BlockInfo,
+    prover::{
+        atlantic::{shared::parse_and_store_proof, AtlanticProof},
+        error::ProverError,
+        http_prover::client::HttpProverClient,
+        Prover, ProverBuilder, SnosProof,
+    },
+    service::{join_workers, Daemon, FinishHandle, ShutdownHandle},
+    storage::{PersistantStorage, Query, Step},
+};
+
+/// Layout used for proving the Starknet OS PIE.
+pub(crate) const SNOS_LAYOUT: &str = "dynamic";
+
+/// Prover implementation as a client to a self-hosted
+/// [http-prover](https://github.com/cartridge-gg/http-prover) instance.
+#[derive(Debug)]
+pub struct HttpProverSnosProver<P, DB> {
+    client: HttpProverClient,
+    statement_channel: Receiver<BlockInfo>,
+    proof_channel: Sender<SnosProof<P>>,
+    finish_handle: FinishHandle,
+    db: DB,
+    worker_count: usize,
+}
+
+#[derive(Debug)]
+pub struct HttpProverSnosProverBuilder<P, DB> {
+    url: Url,
+    access_key: String,
+    statement_channel: Option<Receiver<BlockInfo>>,
+    proof_channel: Option<Sender<SnosProof<P>>>,
+    db: DB,
+    worker_count: usize,
+}
+
+impl<P, DB> HttpProverSnosProver<P, DB>
+where
+    P: AtlanticProof + Send + Sync + 'static,
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    async fn worker(
+        task_rx: Arc<Mutex<Receiver<BlockInfo>>>,
+        task_tx: Sender<SnosProof<P>>,
+        client: HttpProverClient,
+        finish_handle: FinishHandle,
+        db: DB,
+    ) -> Result<(), ProverError> {
+        loop {
+            let new_block = if let Some(new_block) = task_rx.lock().await.recv().await {
+                new_block
+            } else {
+                break;
+            };
+            let block_number_u32 = new_block.number.try_into().map_err(|_| {
+                ProverError::Prover("Block number too large to fit in u32".to_string())
+            })?;
+
+            if let Ok(proof) = db.get_proof(block_number_u32, Step::Snos).await {
+                info!(block_number = new_block.number; "Proof already generated for block");
+                let raw_proof = String::from_utf8(proof).unwrap();
+                let new_proof = SnosProof {
+                    block_number: new_block.number,
+                    proof: P::parse(raw_proof).unwrap(),
+                };
+                let _ = task_tx.send(new_proof).await;
+                continue;
+            }
+
+            let job_id = match db.get_query_id(block_number_u32, Query::SnosProof).await {
+                Ok(job_id) => {
+                    info!(
+                        block_number = new_block.number,
+                        job_id:% = job_id;
+                        "Proof generation already submitted for block",
+                    );
+                    job_id
+                }
+                Err(_) => {
+                    trace!(
+                        block_number = new_block.number;
+                        "Proof generation not submitted for block"
+                    );
+
+                    // TODO: error handling
+                    let compressed_pie = db.get_pie(block_number_u32, Step::Snos).await.unwrap();
+                    debug!(
+                        "Compressed PIE size for block #{}: {} bytes",
+                        new_block.number,
+                        compressed_pie.len()
+                    );
+
+                    let job_id = match crate::utils::retry_with_backoff(
+                        || client.submit_pie(compressed_pie.clone(), SNOS_LAYOUT),
+                        "submit_pie",
+                        3,
+                        Duration::from_secs(5),
+                    )
+                    .await
+                    {
+                        Ok(job_id) => job_id,
+                        Err(err) => {
+                            error!(block_number = new_block.number, error:% = err; "Failed to submit PIE");
+                            db.add_failed_block(block_number_u32, err.to_string())
+                                .await
+                                .unwrap();
+                            continue;
+                        }
+                    };
+
+                    db.add_query_id(block_number_u32, job_id.clone(), Query::SnosProof)
+                        .await
+                        .unwrap();
+
+                    info!(
+                        block_number = new_block.number,
+                        job_id:% = job_id;
+                        "http-prover proof generation submitted for block"
+                    );
+                    job_id
+                }
+            };
+
+            let raw_proof = match client.wait_for_job(&job_id, finish_handle.clone()).await {
+                Ok(raw_proof) => raw_proof,
+                Err(ProverError::Shutdown) => break,
+                Err(err) => {
+                    error!(block_number = new_block.number, error:% = err; "Proof generation failed");
+                    db.add_failed_block(block_number_u32, err.to_string())
+                        .await
+                        .unwrap();
+                    continue;
+                }
+            };
+
+            let new_proof =
+                parse_and_store_proof(raw_proof, db.clone(), block_number_u32, Step::Snos).await?;
+
+            tokio::select! {
+                _ = finish_handle.shutdown_requested() => break,
+                _ = task_tx.send(new_proof) => {},
+            }
+        }
+        Ok(())
+    }
+
+    async fn run(self) {
+        let mut workers = JoinSet::new();
+        let task_rx = Arc::new(Mutex::new(self.statement_channel));
+        for _ in 0..self.worker_count {
+            workers.spawn(Self::worker(
+                task_rx.clone(),
+                self.proof_channel.clone(),
+                self.client.clone(),
+                self.finish_handle.clone(),
+                self.db.clone(),
+            ));
+        }
+        let status = join_workers(workers).await;
+        debug!("Graceful shutdown finished");
+        self.finish_handle.exit(status);
+    }
+}
+
+impl<P, DB> HttpProverSnosProverBuilder<P, DB> {
+    pub fn new(url: Url, access_key: String, db: DB, worker_count: usize) -> Self {
+        Self {
+            url,
+            access_key,
+            statement_channel: None,
+            proof_channel: None,
+            db,
+            worker_count,
+        }
+    }
+}
+
+impl<P, DB> ProverBuilder for HttpProverSnosProverBuilder<P, DB>
+where
+    P: AtlanticProof + Send + Sync + 'static,
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    type Prover = HttpProverSnosProver<P, DB>;
+
+    fn build(self) -> Result<Self::Prover> {
+        Ok(HttpProverSnosProver {
+            client: HttpProverClient::new(self.url, self.access_key),
+            statement_channel: self
+                .statement_channel
+                .ok_or_else(|| anyhow::anyhow!("`statement_channel` not set"))?,
+            proof_channel: self
+                .proof_channel
+                .ok_or_else(|| anyhow::anyhow!("`proof_channel` not set"))?,
+            finish_handle: FinishHandle::new(),
+            db: self.db,
+            worker_count: self.worker_count,
+        })
+    }
+
+    fn statement_channel(mut self, statement_channel: Receiver<BlockInfo>) -> Self {
+        self.statement_channel = Some(statement_channel);
+        self
+    }
+
+    fn proof_channel(mut self, proof_channel: Sender<SnosProof<P>>) -> Self {
+        self.proof_channel = Some(proof_channel);
+        self
+    }
+}
+
+impl<P, DB> Prover for HttpProverSnosProver<P, DB>
+where
+    P: AtlanticProof + Send + Sync + 'static,
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    type Statement = BlockInfo;
+    type BlockInfo = SnosProof<P>;
+}
+
+impl<P, DB> Daemon for HttpProverSnosProver<P, DB>
+where
+    P: AtlanticProof + Send + Sync + 'static,
+    DB: PersistantStorage + Send + Sync + Clone + 'static,
+{
+    fn shutdown_handle(&self) -> ShutdownHandle {
+        self.finish_handle.shutdown_handle()
+    }
+
+    fn start(self) {
+        tokio::spawn(self.run());
+    }
+}
@@ -14,17 +14,33 @@ use crate::{
 
 mod atlantic;
 pub use atlantic::{
-    AtlanticLayoutBridgeProver, AtlanticLayoutBridgeProverBuilder, AtlanticSnosProver,
-    AtlanticSnosProverBuilder,
+    AtlanticLayoutBridgeProver, AtlanticLayoutBridgeProverBuilder, AtlanticOneShotProver,
+    AtlanticSnosProver, AtlanticSnosProverBuilder,
+};
+
+mod http_prover;
+pub use http_prover::{
+    HttpProverClient, HttpProverLayoutBridgeProver, HttpProverLayoutBridgeProverBuilder,
+    HttpProverOneShotProver, HttpProverSnosProver, HttpProverSnosProverBuilder,
 };
 
 mod mock;
 pub use mock::{MockLayoutBridgeProver, MockLayoutBridgeProverBuilder};
 mod recursive;
-pub use atlantic::compress_pie;
-pub use atlantic::AtlanticClient;
+pub use atlantic::AtlanticProof;
+pub use atlantic::{compress_pie, mock_snos_proof};
+pub use atlantic::{AtlanticClient, AtlanticNetwork};
 pub use recursive::{RecursiveProver, RecursiveProverBuilder};
 
+mod os_output;
+pub use os_output::{
+    ClassChange, ContractChanges, MessageToL1, MessageToL2, StarknetOsOutput, StateDiff,
+    StorageChange,
+};
+
+mod verifier;
+pub use verifier::{integrity_fact_hash, verify_proof, VerifiedProof};
+
 pub mod error;
 
 pub trait ProverBuilder {
@@ -0,0 +1,580 @@
+use anyhow::Result;
+use cairo_vm::vm::runners::cairo_pie::CairoPie;
+use starknet_types_core::felt::{Felt, NonZeroFelt};
+
+use crate::utils::extract_pie_output;
+
+/// Offset of the SNOS output in the output of the bootloader running it, after the bootloader
+/// config, output length and SNOS program hash.
+const BOOTLOADER_OUTPUT_HEADER_LEN: usize = 3;
+
+/// Bounds of the number of storage updates packed in a contract header, depending on whether the
+/// number is flagged as small. The flag was introduced along with the state diff compression in
+/// Starknet OS v0.13.3, see [`StarknetOsOutput::parse`].
+const N_UPDATES_SMALL_PACKING_BOUND: u128 = 1 << 8;
+const N_UPDATES_BOUND: u128 = 1 << 64;
+
+/// The output of a Starknet OS run, i.e. what the SNOS proof of a block commits to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StarknetOsOutput {
+    pub initial_root: Felt,
+    pub final_root: Felt,
+    pub previous_block_number: Felt,
+    pub block_number: Felt,
+    pub previous_block_hash: Felt,
+    pub block_hash: Felt,
+    pub os_program_hash: Felt,
+    pub config_hash: Felt,
+    pub use_kzg_da: bool,
+    pub full_output: bool,
+    pub messages_to_l1: Vec<MessageToL1>,
+    pub messages_to_l2: Vec<MessageToL2>,
+    pub state_diff: StateDiff,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageToL1 {
+    pub from_address: Felt,
+    pub to_address: Felt,
+    pub payload: Vec<Felt>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageToL2 {
+    pub from_address: Felt,
+    pub to_address: Felt,
+    pub nonce: Felt,
+    pub selector: Felt,
+    pub payload: Vec<Felt>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateDiff {
+    /// The state diff is published in blobs, which are committed to by KZG commitments.
+    Kzg {
+        /// Evaluation point of the blob polynomials.
+        z: Felt,
+        /// Commitment of each blob, split into two felts.
+        commitments: Vec<[Felt; 2]>,
+        /// Evaluation of each blob at `z`, split into two felts.
+        evaluations: Vec<[Felt; 2]>,
+    },
+    /// The state diff is compressed with the Starknet OS stateless compression, which is left
+    /// as is.
+    Compressed(Vec<Felt>),
+    Uncompressed {
+        contracts: Vec<ContractChanges>,
+        classes: Vec<ClassChange>,
+    },
+}
+
+/// Changes to a contract. Previous values are only part of the output in full output mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractChanges {
+    pub address: Felt,
+    pub previous_nonce: Option<Felt>,
+    /// `None` if not updated, unless in full output mode.
+    pub nonce: Option<Felt>,
+    pub previous_class_hash: Option<Felt>,
+    /// `None` if not updated, unless in full output mode.
+    pub class_hash: Option<Felt>,
+    pub storage_changes: Vec<StorageChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageChange {
+    pub key: Felt,
+    pub previous_value: Option<Felt>,
+    pub value: Felt,
+}
+
+/// A class declared in the block, with its previous compiled class hash in full output mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassChange {
+    pub class_hash: Felt,
+    pub previous_compiled_class_hash: Option<Felt>,
+    pub compiled_class_hash: Felt,
+}
+
+impl StarknetOsOutput {
+    /// Parses the output of a Starknet OS run.
+    ///
+    /// The layout is the one of `serialize_os_output` in the Starknet OS v0.13.3 run by the `snos`
+    /// revision pinned in the workspace manifest (`cartridge-gg/snos@03f07963`). Earlier OS
+    /// versions neither compress the state diff nor flag small numbers of storage updates in
+    /// contract headers, and are not supported. The decoder must be updated along with that
+    /// revision.
+    pub fn parse(output: &[Felt]) -> Result<Self> {
+        let mut reader = OutputReader::new(output);
+
+        let initial_root = reader.next()?;
+        let final_root = reader.next()?;
+        let previous_block_number = reader.next()?;
+        let block_number = reader.next()?;
+        let previous_block_hash = reader.next()?;
+        let block_hash = reader.next()?;
+        let os_program_hash = reader.next()?;
+        let config_hash = reader.next()?;
+        let use_kzg_da = reader.next()? != Felt::ZERO;
+        let full_output = reader.next()? != Felt::ZERO;
+
+        let kzg = if use_kzg_da {
+            let z = reader.next()?;
+            let n_blobs = reader.next_usize()?;
+            let commitments = reader.next_pairs(n_blobs)?;
+            let evaluations = reader.next_pairs(n_blobs)?;
+            Some(StateDiff::Kzg {
+                z,
+                commitments,
+                evaluations,
+            })
+        } else {
+            None
+        };
+
+        let mut messages_to_l1 = vec![];
+        let segment_len = reader.next_usize()?;
+        let mut segment = reader.segment("messages to L1 segment", segment_len)?;
+        while !segment.is_empty() {
+            let from_address = segment.next()?;
+            let to_address = segment.next()?;
+            let payload_len = segment.next_usize()?;
+            messages_to_l1.push(MessageToL1 {
+                from_address,
+                to_address,
+                payload: segment.take(payload_len)?.to_vec(),
+            });
+        }
+
+        let mut messages_to_l2 = vec![];
+        let segment_len = reader.next_usize()?;
+        let mut segment = reader.segment("messages to L2 segment", segment_len)?;
+        while !segment.is_empty() {
+            let from_address = segment.next()?;
+            let to_address = segment.next()?;
+            let nonce = segment.next()?;
+            let selector = segment.next()?;
+            let payload_len = segment.next_usize()?;
+            messages_to_l2.push(MessageToL2 {
+                from_address,
+                to_address,
+                nonce,
+                selector,
+                payload: segment.take(payload_len)?.to_vec(),
+            });
+        }
+
+        // The OS compresses the state diff unless in full output mode, before publishing it either
+        // in blobs or in the output. The compressed state diff is left as is.
+        let state_diff = match kzg {
+            Some(kzg) => kzg,
+            None if !full_output => {
+                StateDiff::Compressed(reader.take(reader.remaining())?.to_vec())
+            }
+            None => reader.next_uncompressed_state_diff(full_output)?,
+        };
+
+        if !reader.is_empty() {
+            anyhow::bail!(
+                "unexpected trailing data in SNOS output: {} felts",
+                reader.remaining()
+            );
+        }
+
+        Ok(Self {
+            initial_root,
+            final_root,
+            previous_block_number,
+            block_number,
+            previous_block_hash,
+            block_hash,
+            os_program_hash,
+            config_hash,
+            use_kzg_da,
+            full_output,
+            messages_to_l1,
+            messages_to_l2,
+            state_diff,
+        })
+    }
+
+    /// Parses the output of a bootloaded SNOS run, as returned by `calculate_output` on SNOS
+    /// proofs.
+    pub fn from_bootloader_output(output: &[Felt]) -> Result<Self> {
+        match output.get(BOOTLOADER_OUTPUT_HEADER_LEN..) {
+            Some(output) => Self::parse(output),
+            None => anyhow::bail!(
+                "bootloader output too short: {} felts, at least {} needed",
+                output.len(),
+                BOOTLOADER_OUTPUT_HEADER_LEN
+            ),
+        }
+    }
+
+    /// Parses the output of the SNOS run of a PIE.
+    pub fn from_pie(pie: &CairoPie) -> Result<Self> {
+        Self::parse(&extract_pie_output(pie))
+    }
+
+    /// Checks that `next` applies on top of the state resulting from `self`.
+    pub fn check_followed_by(&self, next: &Self) -> Result<()> {
+        if self.final_root != next.initial_root {
+            anyhow::bail!(
+                "state root mismatch: block {} ends with root {:#064x} but block {} starts from \
+                root {:#064x}",
+                self.block_number,
+                self.final_root,
+                next.block_number,
+                next.initial_root
+            );
+        }
+        Ok(())
+    }
+}
+
+struct OutputReader<'a> {
+    output: &'a [Felt],
+    position: usize,
+    /// Name and offset of `output` in the whole SNOS output, for error messages.
+    name: &'static str,
+    offset: usize,
+}
+
+impl<'a> OutputReader<'a> {
+    fn new(output: &'a [Felt]) -> Self {
+        Self {
+            output,
+            position: 0,
+            name: "SNOS output",
+            offset: 0,
+        }
+    }
+
+    /// Reads a segment of `len` felts, e.g. the messages to L1, to be read on its own.
+    fn segment(&mut self, name: &'static str, len: usize) -> Result<Self> {
+        let offset = self.offset + self.position;
+        Ok(Self {
+            output: self.take(len)?,
+            position: 0,
+            name,
+            offset,
+        })
+    }
+
+    fn remaining(&self) -> usize {
+        self.output.len() - self.position
+    }
+
+    fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [Felt]> {
+        let output = self.output;
+        match output.get(self.position..).and_then(|rest| rest.get(..len)) {
+            Some(felts) => {
+                self.position += len;
+                Ok(felts)
+            }
+            None => anyhow::bail!(
+                "{} too short: {} felts needed at offset {} of the SNOS output, but only {} left",
+                self.name,
+                len,
+                self.offset + self.position,
+                self.remaining()
+            ),
+        }
+    }
+
+    fn next(&mut self) -> Result<Felt> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn next_usize(&mut self) -> Result<usize> {
+        let value = self.next()?;
+        usize::try_from(value)
+            .map_err(|_| anyhow::anyhow!("invalid length in SNOS output: {}", value))
+    }
+
+    fn next_pairs(&mut self, len: usize) -> Result<Vec<[Felt; 2]>> {
+        (0..len).map(|_| Ok([self.next()?, self.next()?])).collect()
+    }
+
+    fn next_if(&mut self, condition: bool) -> Result<Option<Felt>> {
+        condition.then(|| self.next()).transpose()
+    }
+
+    fn next_uncompressed_state_diff(&mut self, full_output: bool) -> Result<StateDiff> {
+        let n_contracts = self.next_usize()?;
+        let contracts = (0..n_contracts)
+            .map(|_| self.next_contract_changes(full_output))
+            .collect::<Result<_>>()?;
+
+        let n_classes = self.next_usize()?;
+        let classes = (0..n_classes)
+            .map(|_| {
+                Ok(ClassChange {
+                    class_hash: self.next()?,
+                    previous_compiled_class_hash: self.next_if(full_output)?,
+                    compiled_class_hash: self.next()?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(StateDiff::Uncompressed { contracts, classes })
+    }
+
+    fn next_contract_changes(&mut self, full_output: bool) -> Result<ContractChanges> {
+        let address = self.next()?;
+
+        // Header: ((nonce * n_updates_bound + n_updates) * 2 + is_n_updates_small) * 2 +
+        // class_updated, where the nonce is the previous one in full output mode.
+        let header = self.next()?;
+        let (header, class_updated) = header.div_rem(&NonZeroFelt::TWO);
+        let (header, is_n_updates_small) = header.div_rem(&NonZeroFelt::TWO);
+        let n_updates_bound = if is_n_updates_small == Felt::ONE {
+            N_UPDATES_SMALL_PACKING_BOUND
+        } else {
+            N_UPDATES_BOUND
+        };
+        let (nonce, n_updates) = header.div_rem(&NonZeroFelt::from_felt_unchecked(Felt::from(
+            n_updates_bound,
+        )));
+        let n_updates = usize::try_from(n_updates)
+            .map_err(|_| anyhow::anyhow!("invalid length in SNOS output: {}", n_updates))?;
+
+        let (previous_nonce, nonce) = if full_output {
+            (Some(nonce), Some(self.next()?))
+        } else {
+            (None, (nonce != Felt::ZERO).then_some(nonce))
+        };
+        let (previous_class_hash, class_hash) = if full_output {
+            (Some(self.next()?), Some(self.next()?))
+        } else {
+            (None, self.next_if(class_updated == Felt::ONE)?)
+        };
+
+        let storage_changes = (0..n_updates)
+            .map(|_| {
+                Ok(StorageChange {
+                    key: self.next()?,
+                    previous_value: self.next_if(full_output)?,
+                    value: self.next()?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(ContractChanges {
+            address,
+            previous_nonce,
+            nonce,
+            previous_class_hash,
+            class_hash,
+            storage_changes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_full_output() {
+        let felt = |value: u64| Felt::from(value);
+        let output = [
+            // Header.
+            felt(0x10),
+            felt(0x11),
+            felt(4),
+            felt(5),
+            felt(0x20),
+            felt(0x21),
+            felt(0x30),
+            felt(0x31),
+            felt(0),
+            felt(1),
+            // Messages to L1.
+            felt(4),
+            felt(0x40),
+            felt(0x41),
+            felt(1),
+            felt(0x42),
+            // Messages to L2.
+            felt(0),
+            // One contract with a storage update and a class update.
+            felt(1),
+            felt(0x50),
+            felt(((((2 << 8) + 1) * 2 + 1) * 2) + 1),
+            felt(3),
+            felt(0x51),
+            felt(0x52),
+            felt(0x53),
+            felt(0x54),
+            felt(0x55),
+            // One declared class.
+            felt(1),
+            felt(0x60),
+            felt(0),
+            felt(0x61),
+        ];
+
+        let parsed = StarknetOsOutput::parse(&output).unwrap();
+
+        assert_eq!(parsed.previous_block_number, felt(4));
+        assert_eq!(parsed.block_hash, felt(0x21));
+        assert_eq!(parsed.config_hash, felt(0x31));
+        assert!(parsed.full_output);
+        assert_eq!(
+            parsed.messages_to_l1,
+            vec![MessageToL1 {
+                from_address: felt(0x40),
+                to_address: felt(0x41),
+                payload: vec![felt(0x42)],
+            }]
+        );
+        assert!(parsed.messages_to_l2.is_empty());
+        assert_eq!(
+            parsed.state_diff,
+            StateDiff::Uncompressed {
+                contracts: vec![ContractChanges {
+                    address: felt(0x50),
+                    previous_nonce: Some(felt(2)),
+                    nonce: Some(felt(3)),
+                    previous_class_hash: Some(felt(0x51)),
+                    class_hash: Some(felt(0x52)),
+                    storage_changes: vec![StorageChange {
+                        key: felt(0x53),
+                        previous_value: Some(felt(0x54)),
+                        value: felt(0x55),
+                    }],
+                }],
+                classes: vec![ClassChange {
+                    class_hash: felt(0x60),
+                    previous_compiled_class_hash: Some(felt(0)),
+                    compiled_class_hash: felt(0x61),
+                }],
+            }
+        );
+    }
+
+    fn felts(values: &[u64]) -> Vec<Felt> {
+        values.iter().copied().map(Felt::from).collect()
+    }
+
+    /// Header of a block output, up to the `use_kzg_da` and `full_output` flags.
+    fn header(use_kzg_da: u64, full_output: u64) -> Vec<Felt> {
+        felts(&[
+            0x10,
+            0x11,
+            4,
+            5,
+            0x20,
+            0x21,
+            0x30,
+            0x31,
+            use_kzg_da,
+            full_output,
+        ])
+    }
+
+    #[test]
+    fn test_parse_compressed_output_with_messages() {
+        let felt = |value: u64| Felt::from(value);
+        let output = [
+            header(0, 0),
+            // Messages to L1, the second one without payload.
+            felts(&[8, 0x40, 0x41, 2, 0x42, 0x43, 0x44, 0x45, 0]),
+            // Messages to L2.
+            felts(&[6, 0x70, 0x71, 7, 0x72, 1, 0x73]),
+            // Compressed state diff.
+            felts(&[0x80, 0x81, 0x82]),
+        ]
+        .concat();
+
+        let parsed = StarknetOsOutput::parse(&output).unwrap();
+
+        assert!(!parsed.use_kzg_da);
+        assert!(!parsed.full_output);
+        assert_eq!(
+            parsed.messages_to_l1,
+            vec![
+                MessageToL1 {
+                    from_address: felt(0x40),
+                    to_address: felt(0x41),
+                    payload: vec![felt(0x42), felt(0x43)],
+                },
+                MessageToL1 {
+                    from_address: felt(0x44),
+                    to_address: felt(0x45),
+                    payload: vec![],
+                },
+            ]
+        );
+        assert_eq!(
+            parsed.messages_to_l2,
+            vec![MessageToL2 {
+                from_address: felt(0x70),
+                to_address: felt(0x71),
+                nonce: felt(7),
+                selector: felt(0x72),
+                payload: vec![felt(0x73)],
+            }]
+        );
+        assert_eq!(
+            parsed.state_diff,
+            StateDiff::Compressed(felts(&[0x80, 0x81, 0x82]))
+        );
+    }
+
+    #[test]
+    fn test_parse_kzg_output() {
+        let felt = |value: u64| Felt::from(value);
+        let mut output = [
+            header(1, 0),
+            // KZG commitment info of two blobs.
+            felts(&[0x90, 2, 0x91, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98]),
+            // Messages to L1.
+            felts(&[3, 0x40, 0x41, 0]),
+            // Messages to L2.
+            felts(&[0]),
+        ]
+        .concat();
+
+        let parsed = StarknetOsOutput::parse(&output).unwrap();
+
+        assert!(parsed.use_kzg_da);
+        assert_eq!(parsed.messages_to_l1.len(), 1);
+        assert_eq!(
+            parsed.state_diff,
+            StateDiff::Kzg {
+                z: felt(0x90),
+                commitments: vec![[felt(0x91), felt(0x92)], [felt(0x93), felt(0x94)]],
+                evaluations: vec![[felt(0x95), felt(0x96)], [felt(0x97), felt(0x98)]],
+            }
+        );
+
+        // The state diff is published in blobs only.
+        output.push(felt(0));
+        assert!(StarknetOsOutput::parse(&output).is_err());
+    }
+
+    #[test]
+    fn test_parse_truncated_message_segment() {
+        let output = [
+            header(0, 0),
+            // The payload of the message to L1 overflows its segment.
+            felts(&[4, 0x40, 0x41, 2, 0x42]),
+            felts(&[0x43, 0]),
+        ]
+        .concat();
+
+        let err = StarknetOsOutput::parse(&output).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "messages to L1 segment too short: 2 felts needed at offset 14 of the SNOS output, \
+            but only 1 left"
+        );
+    }
+}
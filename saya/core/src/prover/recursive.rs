@@ -1,10 +1,10 @@
 use anyhow::Result;
-use log::debug;
+use log::{debug, warn};
 use tokio::sync::mpsc::{Receiver, Sender};
 
 use crate::{
     prover::{Prover, ProverBuilder},
-    service::{Daemon, FinishHandle, ShutdownHandle},
+    service::{Daemon, ExitStatus, FinishHandle, ShutdownHandle},
 };
 
 const BRIDGE_BUFFER_SIZE: usize = 4;
@@ -84,21 +84,31 @@ where
 
 impl RecursiveProverState {
     async fn run(self) {
-        self.finish_handle.shutdown_requested().await;
+        let status = tokio::select! {
+            _ = self.finish_handle.shutdown_requested() => ExitStatus::Finished,
+            status = self.upstream_prover_handle.exited() => {
+                warn!(exit_status:? = status; "Upstream prover exited");
+                status
+            },
+            status = self.downstream_prover_handle.exited() => {
+                warn!(exit_status:? = status; "Downstream prover exited");
+                status
+            },
+        };
 
         // Request graceful shutdown for all descendant services
         self.upstream_prover_handle.shutdown();
         self.downstream_prover_handle.shutdown();
 
-        // Wait for all descendant services to finish graceful shutdown
+        // Wait for all descendant services to end, including those that failed
         futures_util::future::join_all([
-            self.upstream_prover_handle.finished(),
-            self.downstream_prover_handle.finished(),
+            self.upstream_prover_handle.exited(),
+            self.downstream_prover_handle.exited(),
         ])
         .await;
 
         debug!("Graceful shutdown finished");
-        self.finish_handle.finish();
+        self.finish_handle.exit(status);
     }
 }
 
@@ -0,0 +1,57 @@
+use anyhow::Result;
+use starknet_crypto::poseidon_hash_many;
+use starknet_types_core::felt::Felt;
+use swiftness_air::layout::{recursive_with_poseidon::Layout, LayoutTrait};
+use swiftness_stark::types::StarkProof;
+
+/// Public values attested by a successfully verified proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifiedProof {
+    pub program_hash: Felt,
+    pub output_hash: Felt,
+}
+
+/// Verifies a STARK proof locally with the `swiftness` verifier, without spending any fee on
+/// on-chain verification.
+///
+/// Only proofs for the `recursive_with_poseidon` layout are supported, which is the configuration
+/// layout bridge proofs are verified with on integrity. Verification is CPU-bound and should be run
+/// on a blocking thread from async contexts.
+pub fn verify_proof(proof: &StarkProof) -> Result<VerifiedProof> {
+    let security_bits = proof.config.security_bits();
+    let (program_hash, output_hash) = proof
+        .verify::<Layout>(security_bits)
+        .map_err(|err| anyhow::anyhow!("proof verification failed: {:?}", err))?;
+
+    Ok(VerifiedProof {
+        program_hash,
+        output_hash,
+    })
+}
+
+/// Computes the fact registered on integrity once the proof is verified, from the program hash and
+/// output hash committed to by its public input.
+///
+/// The proof itself is not verified, which makes this cheap enough to check whether a proof needs
+/// to be verified at all.
+pub fn integrity_fact_hash(proof: &StarkProof) -> Result<Felt> {
+    let (program_hash, output_hash) = Layout::verify_public_input(&proof.public_input)
+        .map_err(|err| anyhow::anyhow!("invalid public input: {:?}", err))?;
+
+    Ok(poseidon_hash_many(&[program_hash, output_hash]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::stark_proof_mock;
+
+    use super::*;
+
+    #[test]
+    fn test_verify_proof_rejects_mock_proof() {
+        // Mock proofs carry the right output but no valid commitments.
+        let proof = stark_proof_mock(&[Felt::from(1), Felt::from(2)]);
+
+        assert!(verify_proof(&proof).is_err());
+    }
+}
@@ -1,4 +1,14 @@
-use tokio_util::sync::CancellationToken;
+use std::sync::{Arc, OnceLock};
+
+use log::error;
+use tokio::task::JoinSet;
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+use crate::prover::error::ProverError;
+
+mod supervisor;
+pub(crate) use supervisor::{first_exit, SupervisedChild};
+pub use supervisor::{RestartPolicy, Supervisor};
 
 /// Long-running background services that support graceful shutdown.
 pub trait Daemon: Send {
@@ -7,13 +17,39 @@ pub trait Daemon: Send {
     fn start(self);
 }
 
+/// How a service ended its execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// The service finished execution, either through a requested shutdown or voluntary exit.
+    Finished,
+    /// The service failed and leaves it to its supervisor to decide how to handle the failure.
+    ///
+    /// This is also the status of services that ended without signaling it, e.g. due to a panic.
+    Failed,
+    /// A descendant service failed and asks for the whole pipeline to be rebuilt.
+    RestartRequested {
+        /// Name of the service that failed.
+        service: String,
+        /// Maximum number of restarts allowed for the service.
+        max_restarts: usize,
+        /// Delay before restarting.
+        backoff: std::time::Duration,
+    },
+}
+
 /// A type for background running services to detect cancellation requests made to them via
 /// [`ShutdownHandle`], and for them to signal they've ended execution, either through such a
 /// requested shutdown or voluntary exit.
-#[derive(Debug, Default, Clone)]
+///
+/// Dropping all clones of a handle without calling [`finish`] (e.g. when a service task panics)
+/// is also observable via [`ShutdownHandle::exited`].
+#[derive(Debug, Clone)]
 pub struct FinishHandle {
     cancellation: CancellationToken,
     finish: CancellationToken,
+    exit: CancellationToken,
+    status: Arc<OnceLock<ExitStatus>>,
+    _exit_guard: Arc<DropGuard>,
 }
 
 /// A type for requesting cancellation of background running services and waiting for them to have
@@ -22,6 +58,8 @@ pub struct FinishHandle {
 pub struct ShutdownHandle {
     cancellation: CancellationToken,
     finish: CancellationToken,
+    exit: CancellationToken,
+    status: Arc<OnceLock<ExitStatus>>,
 }
 
 impl FinishHandle {
@@ -33,11 +71,21 @@ impl FinishHandle {
         ShutdownHandle {
             cancellation: self.cancellation.clone(),
             finish: self.finish.clone(),
+            exit: self.exit.clone(),
+            status: self.status.clone(),
         }
     }
 
     /// Signals that the service has finish executing.
     pub fn finish(&self) {
+        self.exit(ExitStatus::Finished);
+    }
+
+    /// Signals that the service has ended execution with the given status.
+    ///
+    /// Only the first status reported is retained.
+    pub fn exit(&self, status: ExitStatus) {
+        let _ = self.status.set(status);
         self.finish.cancel();
     }
 
@@ -55,6 +103,19 @@ impl FinishHandle {
     }
 }
 
+impl Default for FinishHandle {
+    fn default() -> Self {
+        let exit = CancellationToken::new();
+        Self {
+            cancellation: CancellationToken::new(),
+            finish: CancellationToken::new(),
+            _exit_guard: Arc::new(exit.clone().drop_guard()),
+            exit,
+            status: Default::default(),
+        }
+    }
+}
+
 impl ShutdownHandle {
     /// Request a shutdown without waiting for the process to finish.
     ///
@@ -63,9 +124,104 @@ impl ShutdownHandle {
         self.cancellation.cancel();
     }
 
+    /// Checks whether any shutdown request has been made via [`shutdown`].
+    pub fn is_shutdown_requested(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
     /// Waits asynchronously for the service to finish execution, either through a requested
     /// shutdown or voluntary exit.
     pub async fn finished(&self) {
         self.finish.cancelled().await
     }
+
+    /// Waits asynchronously for the service to end execution in any way, including unexpected
+    /// ones like panics, and returns how it ended.
+    pub async fn exited(&self) -> ExitStatus {
+        tokio::select! {
+            _ = self.finish.cancelled() => {},
+            _ = self.exit.cancelled() => {},
+        }
+
+        self.status.get().cloned().unwrap_or(ExitStatus::Failed)
+    }
+}
+
+/// The result of a worker task spawned by a service.
+pub(crate) trait WorkerOutcome {
+    /// Gets the error the worker ended with, if any.
+    fn error(self) -> Option<String>;
+}
+
+impl WorkerOutcome for () {
+    fn error(self) -> Option<String> {
+        None
+    }
+}
+
+impl WorkerOutcome for Result<(), ProverError> {
+    fn error(self) -> Option<String> {
+        match self {
+            Ok(()) | Err(ProverError::Shutdown) => None,
+            Err(err) => Some(err.to_string()),
+        }
+    }
+}
+
+/// Waits for the worker tasks of a service to end, and gets the status the service should exit
+/// with.
+///
+/// Returns [`ExitStatus::Failed`] as soon as any worker panics or ends with an error. The remaining
+/// workers are left running until the service is shut down by its supervisor.
+pub(crate) async fn join_workers<T>(mut workers: JoinSet<T>) -> ExitStatus
+where
+    T: WorkerOutcome + 'static,
+{
+    while let Some(result) = workers.join_next().await {
+        let error = match result {
+            Ok(outcome) => outcome.error(),
+            Err(err) if err.is_panic() => Some("worker panicked".to_string()),
+            Err(err) => Some(err.to_string()),
+        };
+
+        if let Some(error) = error {
+            error!(error:% = error; "Worker failed");
+            workers.detach_all();
+            return ExitStatus::Failed;
+        }
+    }
+
+    ExitStatus::Finished
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_join_workers_finished() {
+        let mut workers = JoinSet::new();
+        workers.spawn(async { Ok(()) });
+        workers.spawn(async { Err(ProverError::Shutdown) });
+
+        assert_eq!(join_workers(workers).await, ExitStatus::Finished);
+    }
+
+    #[tokio::test]
+    async fn test_join_workers_error() {
+        let mut workers = JoinSet::new();
+        workers.spawn(async { Ok(()) });
+        workers.spawn(async { Err(ProverError::Prover("boom".to_string())) });
+
+        assert_eq!(join_workers(workers).await, ExitStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_join_workers_panic() {
+        let mut workers = JoinSet::new();
+        workers.spawn(std::future::pending::<()>());
+        workers.spawn(async { panic!("boom") });
+
+        assert_eq!(join_workers(workers).await, ExitStatus::Failed);
+    }
 }
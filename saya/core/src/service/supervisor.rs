@@ -0,0 +1,334 @@
+use std::{collections::HashMap, future::Future, time::Duration};
+
+use anyhow::Result;
+use log::{debug, error, info, warn};
+
+use crate::service::{Daemon, ExitStatus, FinishHandle, ShutdownHandle};
+
+/// Policy for handling the unexpected exit of a service, i.e. when it ends execution without a
+/// shutdown request being made to it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum RestartPolicy {
+    /// Restart the service after waiting for `backoff`, up to `max_restarts` times. The failure is
+    /// escalated once the limit is reached.
+    ///
+    /// Since services in a pipeline are connected through channels, restarting a single service
+    /// would lose any in-flight blocks. Restarts are therefore always performed by rebuilding the
+    /// whole pipeline, which resumes from persisted state.
+    Restart {
+        max_restarts: usize,
+        backoff: Duration,
+    },
+    /// Shut down the whole pipeline and leave it to the parent supervisor to handle the failure.
+    #[default]
+    Escalate,
+    /// Gracefully shut down the whole pipeline.
+    Shutdown,
+}
+
+impl RestartPolicy {
+    /// Gets the exit status a pipeline reports to its own supervisor after shutting down due to the
+    /// unexpected exit of `service`.
+    pub fn exit_status<T>(&self, service: T) -> ExitStatus
+    where
+        T: Into<String>,
+    {
+        match self {
+            Self::Restart {
+                max_restarts,
+                backoff,
+            } => ExitStatus::RestartRequested {
+                service: service.into(),
+                max_restarts: *max_restarts,
+                backoff: *backoff,
+            },
+            Self::Escalate => ExitStatus::Failed,
+            Self::Shutdown => ExitStatus::Finished,
+        }
+    }
+}
+
+/// A child service watched by an orchestrator.
+#[derive(Debug)]
+pub(crate) struct SupervisedChild {
+    pub name: &'static str,
+    pub handle: ShutdownHandle,
+    pub policy: RestartPolicy,
+}
+
+/// Waits for the first child to exit and returns it along with its exit status.
+pub(crate) async fn first_exit(children: &[SupervisedChild]) -> (&SupervisedChild, ExitStatus) {
+    let (status, index, _) = futures_util::future::select_all(
+        children.iter().map(|child| Box::pin(child.handle.exited())),
+    )
+    .await;
+
+    (&children[index], status)
+}
+
+/// A service that keeps a [`Daemon`] running by rebuilding it from a factory whenever it exits
+/// unexpectedly.
+///
+/// The daemon is typically an orchestrator, which reports the restart policy of its failed child
+/// through [`ExitStatus::RestartRequested`]. Failures without a specific policy (e.g. a panic of
+/// the daemon itself) are handled by the supervisor's own policy.
+///
+/// Restarts are counted per failed service over the lifetime of the supervisor.
+pub struct Supervisor<F, D> {
+    name: String,
+    policy: RestartPolicy,
+    factory: F,
+    daemon: D,
+    finish_handle: FinishHandle,
+}
+
+impl<F, Fut, D> Supervisor<F, D>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<D>> + Send,
+    D: Daemon + 'static,
+{
+    /// Creates a new supervisor, building the first instance of the daemon eagerly so that
+    /// configuration errors surface immediately.
+    pub async fn new<T>(name: T, policy: RestartPolicy, mut factory: F) -> Result<Self>
+    where
+        T: Into<String>,
+    {
+        let daemon = factory().await?;
+
+        Ok(Self {
+            name: name.into(),
+            policy,
+            factory,
+            daemon,
+            finish_handle: FinishHandle::new(),
+        })
+    }
+
+    async fn run(mut self) {
+        let mut restarts: HashMap<String, usize> = HashMap::new();
+        let mut daemon = Some(self.daemon);
+
+        let status = loop {
+            let next = match daemon.take() {
+                Some(current) => {
+                    let handle = current.shutdown_handle();
+                    current.start();
+
+                    let status = tokio::select! {
+                        _ = self.finish_handle.shutdown_requested() => {
+                            handle.shutdown();
+                            handle.exited().await;
+                            break ExitStatus::Finished;
+                        },
+                        status = handle.exited() => status,
+                    };
+
+                    match status {
+                        ExitStatus::Finished => {
+                            info!(service:% = self.name; "Service finished");
+                            break ExitStatus::Finished;
+                        }
+                        ExitStatus::Failed => {
+                            warn!(service:% = self.name; "Service exited unexpectedly");
+                            Self::next_action(&self.name, &self.policy, &mut restarts)
+                        }
+                        ExitStatus::RestartRequested {
+                            service,
+                            max_restarts,
+                            backoff,
+                        } => {
+                            warn!(service:% = service; "Service exited unexpectedly");
+                            Self::next_action(
+                                &service,
+                                &RestartPolicy::Restart {
+                                    max_restarts,
+                                    backoff,
+                                },
+                                &mut restarts,
+                            )
+                        }
+                    }
+                }
+                // The previous attempt to rebuild the daemon failed. Building failures are handled
+                // like a failure of the daemon itself.
+                None => Self::next_action(&self.name, &self.policy, &mut restarts),
+            };
+
+            let backoff = match next {
+                Ok(backoff) => backoff,
+                Err(status) => break status,
+            };
+
+            info!(service:% = self.name, backoff:? = backoff; "Restarting service");
+            tokio::select! {
+                _ = self.finish_handle.shutdown_requested() => break ExitStatus::Finished,
+                _ = tokio::time::sleep(backoff) => {},
+            }
+
+            match (self.factory)().await {
+                Ok(rebuilt) => daemon = Some(rebuilt),
+                Err(err) => {
+                    error!(service:% = self.name, error:% = err; "Failed to rebuild service");
+                }
+            }
+        };
+
+        debug!(service:% = self.name; "Supervisor finished");
+        self.finish_handle.exit(status);
+    }
+
+    /// Decides how to handle a failure of `service` under `policy`. Returns the backoff to wait for
+    /// before restarting, or the status the supervisor should exit with if the failure must not be
+    /// retried.
+    fn next_action(
+        service: &str,
+        policy: &RestartPolicy,
+        restarts: &mut HashMap<String, usize>,
+    ) -> Result<Duration, ExitStatus> {
+        match policy {
+            RestartPolicy::Restart {
+                max_restarts,
+                backoff,
+            } => {
+                let count = restarts.entry(service.to_owned()).or_default();
+                if *count < *max_restarts {
+                    *count += 1;
+                    Ok(*backoff)
+                } else {
+                    error!(
+                        service:% = service,
+                        max_restarts = *max_restarts;
+                        "Service exceeded maximum number of restarts"
+                    );
+                    Err(ExitStatus::Failed)
+                }
+            }
+            RestartPolicy::Escalate => Err(ExitStatus::Failed),
+            RestartPolicy::Shutdown => Err(ExitStatus::Finished),
+        }
+    }
+}
+
+impl<F, Fut, D> Daemon for Supervisor<F, D>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<D>> + Send,
+    D: Daemon + 'static,
+{
+    fn shutdown_handle(&self) -> ShutdownHandle {
+        self.finish_handle.shutdown_handle()
+    }
+
+    fn start(self) {
+        tokio::spawn(self.run());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    const BACKOFF: Duration = Duration::from_millis(1);
+
+    /// How a [`TestDaemon`] ends its execution.
+    #[derive(Debug, Clone, Copy)]
+    enum Outcome {
+        Finish,
+        Panic,
+    }
+
+    struct TestDaemon {
+        outcome: Outcome,
+        finish_handle: FinishHandle,
+    }
+
+    impl Daemon for TestDaemon {
+        fn shutdown_handle(&self) -> ShutdownHandle {
+            self.finish_handle.shutdown_handle()
+        }
+
+        fn start(self) {
+            tokio::spawn(async move {
+                let finish_handle = self.finish_handle;
+                match self.outcome {
+                    Outcome::Finish => finish_handle.finish(),
+                    Outcome::Panic => panic!("test daemon panicked"),
+                }
+            });
+        }
+    }
+
+    /// Runs a supervisor whose daemon ends with the outcomes in order, repeating the last one.
+    /// Returns the exit status of the supervisor and the number of daemons built.
+    async fn supervise(policy: RestartPolicy, outcomes: Vec<Outcome>) -> (ExitStatus, usize) {
+        let builds = Arc::new(AtomicUsize::new(0));
+        let factory_builds = builds.clone();
+        let supervisor = Supervisor::new("test", policy, move || {
+            let build = factory_builds.fetch_add(1, Ordering::SeqCst);
+            let outcome = outcomes[build.min(outcomes.len() - 1)];
+            async move {
+                Ok(TestDaemon {
+                    outcome,
+                    finish_handle: FinishHandle::new(),
+                })
+            }
+        })
+        .await
+        .unwrap();
+
+        let handle = supervisor.shutdown_handle();
+        supervisor.start();
+        let status = handle.exited().await;
+
+        (status, builds.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_clean_exit() {
+        let policy = RestartPolicy::Restart {
+            max_restarts: 3,
+            backoff: BACKOFF,
+        };
+
+        let (status, builds) = supervise(policy, vec![Outcome::Finish]).await;
+        assert_eq!(status, ExitStatus::Finished);
+        assert_eq!(builds, 1);
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_restarts_after_panic() {
+        let policy = RestartPolicy::Restart {
+            max_restarts: 3,
+            backoff: BACKOFF,
+        };
+
+        let (status, builds) = supervise(policy, vec![Outcome::Panic, Outcome::Finish]).await;
+        assert_eq!(status, ExitStatus::Finished);
+        assert_eq!(builds, 2);
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_restart_limit() {
+        let policy = RestartPolicy::Restart {
+            max_restarts: 2,
+            backoff: BACKOFF,
+        };
+
+        let (status, builds) = supervise(policy, vec![Outcome::Panic]).await;
+        assert_eq!(status, ExitStatus::Failed);
+        assert_eq!(builds, 3);
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_escalates_panic() {
+        let (status, builds) = supervise(RestartPolicy::Escalate, vec![Outcome::Panic]).await;
+        assert_eq!(status, ExitStatus::Failed);
+        assert_eq!(builds, 1);
+    }
+}
@@ -0,0 +1,521 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use log::{debug, warn};
+use num_traits::ToPrimitive;
+use starknet::{
+    accounts::{Account, ConnectedAccount, SingleOwnerAccount},
+    core::types::{
+        BroadcastedInvokeTransaction, BroadcastedInvokeTransactionV3, BroadcastedTransaction, Call,
+        DataAvailabilityMode, ExecutionResult, ResourceBounds, ResourceBoundsMapping,
+        SimulatedTransaction, SimulationFlag, TransactionReceiptWithBlockInfo,
+    },
+    macros::{felt, short_string},
+    providers::{jsonrpc::HttpTransport, JsonRpcClient, Provider},
+    signers::{LocalWallet, SigningKey},
+};
+use starknet_crypto::poseidon_hash_many;
+use starknet_types_core::felt::Felt;
+
+use crate::utils::{felt_to_bigdecimal, find_any_receipt, watch_any_tx};
+
+/// Same as the `starknet-rs` default for V3 transactions.
+const DEFAULT_FEE_MULTIPLIER: f64 = 1.5;
+const DEFAULT_FEE_BUMP_MULTIPLIER: f64 = 1.2;
+/// Transaction version of V3 transactions only meant for simulation or fee estimation.
+const QUERY_VERSION_THREE: Felt = felt!("0x100000000000000000000000000000003");
+
+type SettlementAccount = SingleOwnerAccount<Arc<JsonRpcClient<HttpTransport>>, LocalWallet>;
+
+/// Fee settings for the transactions sent by the settlement backend.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeConfig {
+    /// Multiplier applied to the estimated gas amount and gas price.
+    pub multiplier: f64,
+    /// Maximum fee in FRI spent on a block, applied separately to the verification of its proof on
+    /// integrity and to its share of a settlement transaction. Unlimited if `None`.
+    pub max_fee_per_block: Option<Felt>,
+    /// Time after which an unconfirmed transaction is sent again with the same nonce and a higher
+    /// gas price and tip. Transactions are never resubmitted if `None`.
+    pub resubmit_timeout: Option<Duration>,
+    /// Multiplier applied to the gas price and tip on each resubmission.
+    pub bump_multiplier: f64,
+    /// Tip per unit of gas offered to the sequencer for prioritizing transactions.
+    pub tip: u64,
+}
+
+impl Default for FeeConfig {
+    fn default() -> Self {
+        Self {
+            multiplier: DEFAULT_FEE_MULTIPLIER,
+            max_fee_per_block: None,
+            resubmit_timeout: None,
+            bump_multiplier: DEFAULT_FEE_BUMP_MULTIPLIER,
+            tip: 0,
+        }
+    }
+}
+
+/// Limits on the overall spending of the settlement account. Settlement is paused while they would
+/// be exceeded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SettlementBudget {
+    /// Maximum fee in FRI spent per UTC day.
+    pub max_daily_fee: Option<Felt>,
+    /// STRK balance in FRI to always keep on the settlement account.
+    pub min_balance: Option<Felt>,
+}
+
+/// Error returned when a transaction is not sent because its fee exceeds the given limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FeeLimitExceeded {
+    pub fee: Felt,
+    pub max_fee: Felt,
+}
+
+impl std::fmt::Display for FeeLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "transaction fee of {} STRK exceeds the limit of {} STRK",
+            felt_to_bigdecimal(self.fee, 18),
+            felt_to_bigdecimal(self.max_fee, 18)
+        )
+    }
+}
+
+impl std::error::Error for FeeLimitExceeded {}
+
+/// Error returned when a transaction is included but reverted. Its fee is charged all the same.
+#[derive(Debug, Clone)]
+pub(crate) struct TransactionReverted {
+    pub reason: String,
+    pub receipt: TransactionReceiptWithBlockInfo,
+}
+
+impl std::fmt::Display for TransactionReverted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "transaction reverted: {}", self.reason)
+    }
+}
+
+impl std::error::Error for TransactionReverted {}
+
+/// Sends the transactions of the settlement account, managing their fees and nonces.
+///
+/// Nonces are tracked locally across integrity and settlement transactions, and only fetched from
+/// the network again after a failure.
+#[derive(Debug)]
+pub(crate) struct TransactionSender {
+    account: SettlementAccount,
+    /// Key of `account`, for signing transactions built without `starknet-rs` executions.
+    signing_key: SigningKey,
+    config: FeeConfig,
+    next_nonce: Mutex<Option<Felt>>,
+}
+
+impl TransactionSender {
+    pub fn new(account: SettlementAccount, signing_key: SigningKey, config: FeeConfig) -> Self {
+        Self {
+            account,
+            signing_key,
+            config,
+            next_nonce: Mutex::new(None),
+        }
+    }
+
+    pub fn config(&self) -> &FeeConfig {
+        &self.config
+    }
+
+    /// Sends `calls` in a single transaction and waits for it to be confirmed. Fails without
+    /// sending anything if the estimated fee exceeds `max_fee`.
+    ///
+    /// When `resubmit_timeout` is set, unconfirmed transactions are replaced with the same nonce
+    /// and a higher gas price and tip, as long as the fee stays under `max_fee`.
+    pub async fn send(
+        &self,
+        calls: Vec<Call>,
+        max_fee: Option<Felt>,
+        poll_interval: Duration,
+    ) -> Result<TransactionReceiptWithBlockInfo> {
+        let nonce = self.nonce().await?;
+
+        let execution = self.account.execute_v3(calls.clone()).nonce(nonce);
+        let estimate = match crate::utils::retry_with_backoff(
+            || execution.estimate_fee(),
+            "estimate_fee",
+            3,
+            Duration::from_secs(3),
+        )
+        .await
+        {
+            Ok(estimate) => estimate,
+            Err(err) => {
+                self.reset_nonce();
+                anyhow::bail!("fee estimation failed: {}", err);
+            }
+        };
+
+        let (Some(estimated_gas_price), Some(overall_fee)) =
+            (estimate.gas_price.to_u128(), estimate.overall_fee.to_u128())
+        else {
+            anyhow::bail!("fee estimate out of range: {:?}", estimate);
+        };
+        let gas = (overall_fee.div_ceil(estimated_gas_price.max(1)) as f64 * self.config.multiplier)
+            as u64;
+        let mut gas_price = (estimated_gas_price as f64 * self.config.multiplier) as u128;
+        let mut tip = self.config.tip;
+
+        let fee = max_transaction_fee(gas, gas_price, tip);
+        if let Some(max_fee) = max_fee {
+            if fee > max_fee {
+                return Err(FeeLimitExceeded { fee, max_fee }.into());
+            }
+        }
+
+        let mut transaction_hashes = vec![];
+        loop {
+            match crate::utils::retry_with_backoff(
+                || self.send_invoke(&calls, nonce, gas, gas_price, tip),
+                "send_transaction",
+                3,
+                Duration::from_secs(3),
+            )
+            .await
+            {
+                Ok(transaction_hash) => {
+                    debug!(
+                        transaction_hash:% = format!("{:#064x}", transaction_hash),
+                        nonce:% = nonce;
+                        "Transaction sent with a fee of up to {} STRK",
+                        felt_to_bigdecimal(max_transaction_fee(gas, gas_price, tip), 18)
+                    );
+                    transaction_hashes.push(transaction_hash);
+                }
+                // A previous submission may have been included in the meantime.
+                Err(err) if !transaction_hashes.is_empty() => {
+                    warn!(error:% = err; "Failed to resubmit transaction");
+                }
+                Err(err) => {
+                    self.reset_nonce();
+                    anyhow::bail!("failed to send transaction: {}", err);
+                }
+            }
+
+            // Sequencers only replace a pending transaction with one paying a higher tip.
+            let bumped_gas_price = (gas_price as f64 * self.config.bump_multiplier) as u128;
+            let bumped_tip = ((tip as f64 * self.config.bump_multiplier) as u64).max(tip + 1);
+            let can_bump = max_fee.is_none_or(|max_fee| {
+                max_transaction_fee(gas, bumped_gas_price, bumped_tip) <= max_fee
+            });
+
+            let watch = self.watch(&transaction_hashes, poll_interval);
+            let result = match self.config.resubmit_timeout {
+                Some(resubmit_timeout) if can_bump => {
+                    tokio::time::timeout(resubmit_timeout, watch).await.ok()
+                }
+                _ => Some(watch.await),
+            };
+
+            match result {
+                Some(Ok(receipt)) => {
+                    *self.next_nonce.lock().unwrap() = Some(nonce + Felt::ONE);
+                    return Ok(receipt);
+                }
+                Some(Err(err)) => {
+                    if err.is::<TransactionReverted>() {
+                        self.reset_nonce();
+                    } else {
+                        // The transactions may still be pending, in which case the nonce is
+                        // reused for the next ones to replace them instead of being paid for too.
+                        *self.next_nonce.lock().unwrap() = Some(nonce);
+                    }
+                    return Err(err);
+                }
+                None => {
+                    warn!(
+                        nonce:% = nonce;
+                        "Transaction not confirmed in time, resubmitting with a higher gas price \
+                        and tip"
+                    );
+                    gas_price = bumped_gas_price;
+                    tip = bumped_tip;
+                }
+            }
+        }
+    }
+
+    /// Waits for the first of `transaction_hashes` to be included. Errors while watching are
+    /// checked against the status of the transactions, so that a transaction included in the
+    /// meantime is not reported as failed.
+    async fn watch(
+        &self,
+        transaction_hashes: &[Felt],
+        poll_interval: Duration,
+    ) -> Result<TransactionReceiptWithBlockInfo> {
+        loop {
+            let err = match watch_any_tx(self.account.provider(), transaction_hashes, poll_interval)
+                .await
+            {
+                Ok(receipt) => return Ok(receipt),
+                Err(err) => err,
+            };
+
+            warn!(error:% = err; "Failed to watch transaction, checking its status");
+            let receipt = crate::utils::retry_with_backoff(
+                || find_any_receipt(self.account.provider(), transaction_hashes),
+                "get_transaction_receipt",
+                3,
+                Duration::from_secs(3),
+            )
+            .await
+            .map_err(|status_err| {
+                anyhow::anyhow!("{}, transaction status unknown: {}", err, status_err)
+            })?;
+
+            // The transactions are watched again if none of them is included yet.
+            if let Some(receipt) = receipt {
+                return match receipt.receipt.execution_result() {
+                    ExecutionResult::Succeeded => Ok(receipt),
+                    ExecutionResult::Reverted { reason } => Err(TransactionReverted {
+                        reason: reason.clone(),
+                        receipt,
+                    }
+                    .into()),
+                };
+            }
+        }
+    }
+
+    /// Signs and sends an invoke transaction. Unlike `starknet-rs` executions, the tip can be set,
+    /// which pending transactions need to be raised for being replaced.
+    async fn send_invoke(
+        &self,
+        calls: &[Call],
+        nonce: Felt,
+        gas: u64,
+        gas_price: u128,
+        tip: u64,
+    ) -> Result<Felt> {
+        let mut transaction = BroadcastedInvokeTransactionV3 {
+            sender_address: self.account.address(),
+            calldata: self.account.encode_calls(calls),
+            signature: vec![],
+            nonce,
+            resource_bounds: ResourceBoundsMapping {
+                l1_gas: ResourceBounds {
+                    max_amount: gas,
+                    max_price_per_unit: gas_price,
+                },
+                l2_gas: ResourceBounds {
+                    max_amount: 0,
+                    max_price_per_unit: 0,
+                },
+            },
+            tip,
+            paymaster_data: vec![],
+            account_deployment_data: vec![],
+            nonce_data_availability_mode: DataAvailabilityMode::L1,
+            fee_data_availability_mode: DataAvailabilityMode::L1,
+            is_query: false,
+        };
+
+        let transaction_hash = invoke_v3_transaction_hash(self.account.chain_id(), &transaction);
+        let signature = self.signing_key.sign(&transaction_hash)?;
+        transaction.signature = vec![signature.r, signature.s];
+
+        let result = self
+            .account
+            .provider()
+            .add_invoke_transaction(BroadcastedInvokeTransaction::V3(transaction))
+            .await?;
+        Ok(result.transaction_hash)
+    }
+
+    /// Simulates `transactions` in sequence from the next nonce, so that each transaction sees
+    /// the changes of the previous ones. Nothing is signed nor sent, and fees are not charged.
+    pub async fn simulate(&self, transactions: &[Vec<Call>]) -> Result<Vec<SimulatedTransaction>> {
+        let nonce = self.nonce().await?;
+        let no_resources = ResourceBounds {
+            max_amount: 0,
+            max_price_per_unit: 0,
+        };
+
+        let transactions = transactions
+            .iter()
+            .zip(0_u64..)
+            .map(|(calls, ind)| {
+                BroadcastedTransaction::Invoke(BroadcastedInvokeTransaction::V3(
+                    BroadcastedInvokeTransactionV3 {
+                        sender_address: self.account.address(),
+                        calldata: self.account.encode_calls(calls),
+                        signature: vec![],
+                        nonce: nonce + Felt::from(ind),
+                        resource_bounds: ResourceBoundsMapping {
+                            l1_gas: no_resources.clone(),
+                            l2_gas: no_resources.clone(),
+                        },
+                        tip: 0,
+                        paymaster_data: vec![],
+                        account_deployment_data: vec![],
+                        nonce_data_availability_mode: DataAvailabilityMode::L1,
+                        fee_data_availability_mode: DataAvailabilityMode::L1,
+                        is_query: true,
+                    },
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        Ok(self
+            .account
+            .provider()
+            .simulate_transactions(
+                self.account.block_id(),
+                transactions,
+                [SimulationFlag::SkipValidate, SimulationFlag::SkipFeeCharge],
+            )
+            .await?)
+    }
+
+    async fn nonce(&self) -> Result<Felt> {
+        let next_nonce = *self.next_nonce.lock().unwrap();
+        match next_nonce {
+            Some(nonce) => Ok(nonce),
+            None => Ok(self.account.get_nonce().await?),
+        }
+    }
+
+    /// Forgets the tracked nonce so that it's fetched from the network for the next transaction.
+    fn reset_nonce(&self) {
+        *self.next_nonce.lock().unwrap() = None;
+    }
+}
+
+/// Maximum fee of a transaction with the given L1 gas bounds and tip.
+fn max_transaction_fee(gas: u64, gas_price: u128, tip: u64) -> Felt {
+    Felt::from(gas) * (Felt::from(gas_price) + Felt::from(tip))
+}
+
+/// Computes the hash of a V3 invoke transaction as defined in SNIP-8, which is what the account
+/// signs.
+fn invoke_v3_transaction_hash(
+    chain_id: Felt,
+    transaction: &BroadcastedInvokeTransactionV3,
+) -> Felt {
+    let resource_bounds = &transaction.resource_bounds;
+    let data_availability_modes =
+        (data_availability_mode(&transaction.nonce_data_availability_mode) << 32)
+            + data_availability_mode(&transaction.fee_data_availability_mode);
+
+    poseidon_hash_many(&[
+        short_string!("invoke"),
+        if transaction.is_query {
+            QUERY_VERSION_THREE
+        } else {
+            Felt::THREE
+        },
+        transaction.sender_address,
+        poseidon_hash_many(&[
+            Felt::from(transaction.tip),
+            encode_resource_bounds(b"L1_GAS", &resource_bounds.l1_gas),
+            encode_resource_bounds(b"L2_GAS", &resource_bounds.l2_gas),
+        ]),
+        poseidon_hash_many(&transaction.paymaster_data),
+        chain_id,
+        transaction.nonce,
+        Felt::from(data_availability_modes),
+        poseidon_hash_many(&transaction.account_deployment_data),
+        poseidon_hash_many(&transaction.calldata),
+    ])
+}
+
+/// Packs the resource name, maximum amount and maximum price per unit in a single felt.
+fn encode_resource_bounds(name: &[u8; 6], bounds: &ResourceBounds) -> Felt {
+    let mut buffer = [0u8; 32];
+    buffer[2..8].copy_from_slice(name);
+    buffer[8..16].copy_from_slice(&bounds.max_amount.to_be_bytes());
+    buffer[16..].copy_from_slice(&bounds.max_price_per_unit.to_be_bytes());
+    Felt::from_bytes_be(&buffer)
+}
+
+fn data_availability_mode(mode: &DataAvailabilityMode) -> u64 {
+    match mode {
+        DataAvailabilityMode::L1 => 0,
+        DataAvailabilityMode::L2 => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::{accounts::ExecutionEncoding, macros::selector};
+    use url::Url;
+
+    use super::*;
+
+    #[test]
+    fn test_invoke_v3_transaction_hash_matches_starknet_rs() {
+        let provider = Arc::new(JsonRpcClient::new(HttpTransport::new(
+            Url::parse("http://localhost:5050").unwrap(),
+        )));
+        let signing_key = SigningKey::from_secret_scalar(felt!("0x1234"));
+        let chain_id = short_string!("SN_SEPOLIA");
+        let account = SingleOwnerAccount::new(
+            provider,
+            LocalWallet::from_signing_key(signing_key.clone()),
+            felt!("0x5678"),
+            chain_id,
+            ExecutionEncoding::New,
+        );
+        let sender = TransactionSender::new(account, signing_key, FeeConfig::default());
+
+        let calls = vec![Call {
+            to: felt!("0x9abc"),
+            selector: selector!("update_state"),
+            calldata: vec![Felt::ONE, Felt::TWO],
+        }];
+        let expected = sender
+            .account
+            .execute_v3(calls.clone())
+            .nonce(Felt::THREE)
+            .gas(1_000)
+            .gas_price(2_000)
+            .prepared()
+            .unwrap()
+            .transaction_hash(false);
+
+        // `starknet-rs` always uses a zero tip.
+        let transaction = BroadcastedInvokeTransactionV3 {
+            sender_address: felt!("0x5678"),
+            calldata: sender.account.encode_calls(&calls),
+            signature: vec![],
+            nonce: Felt::THREE,
+            resource_bounds: ResourceBoundsMapping {
+                l1_gas: ResourceBounds {
+                    max_amount: 1_000,
+                    max_price_per_unit: 2_000,
+                },
+                l2_gas: ResourceBounds {
+                    max_amount: 0,
+                    max_price_per_unit: 0,
+                },
+            },
+            tip: 0,
+            paymaster_data: vec![],
+            account_deployment_data: vec![],
+            nonce_data_availability_mode: DataAvailabilityMode::L1,
+            fee_data_availability_mode: DataAvailabilityMode::L1,
+            is_query: false,
+        };
+        assert_eq!(invoke_v3_transaction_hash(chain_id, &transaction), expected);
+
+        let tipped = BroadcastedInvokeTransactionV3 {
+            tip: 1,
+            ..transaction
+        };
+        assert_ne!(invoke_v3_transaction_hash(chain_id, &tipped), expected);
+    }
+}
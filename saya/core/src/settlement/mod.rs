@@ -8,6 +8,9 @@ use crate::{
     block_ingestor::BlockInfo, data_availability::DataAvailabilityCursor, service::Daemon,
 };
 
+mod fees;
+pub use fees::{FeeConfig, SettlementBudget};
+
 mod piltover;
 pub use piltover::{PiltoverSettlementBackend, PiltoverSettlementBackendBuilder};
 
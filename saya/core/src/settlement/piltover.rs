@@ -1,48 +1,80 @@
 use std::{
     collections::BTreeMap,
     sync::Arc,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
+use ::metrics::{gauge, histogram};
 use anyhow::Result;
 use integrity::{split_proof, VerifierConfiguration};
-use log::{debug, info};
+use log::{debug, error, info, warn};
+use num_traits::ToPrimitive;
 use starknet::{
-    accounts::{Account, ConnectedAccount, SingleOwnerAccount},
+    accounts::SingleOwnerAccount,
     core::{
         codec::{Decode, Encode},
-        types::{BlockId, BlockTag, Call, FunctionCall, TransactionReceipt, U256},
+        types::{
+            BlockId, BlockTag, Call, ExecuteInvocation, FunctionCall, InvokeTransactionTrace,
+            TransactionReceipt, TransactionReceiptWithBlockInfo, TransactionTrace, U256,
+        },
     },
-    macros::{selector, short_string},
+    macros::{felt, selector, short_string},
     providers::{jsonrpc::HttpTransport, JsonRpcClient, Provider},
     signers::{LocalWallet, SigningKey},
 };
 use starknet_types_core::felt::Felt;
 use swiftness::TransformTo;
+use swiftness_stark::types::StarkProof;
 use tokio::sync::mpsc::{Receiver, Sender};
 use url::Url;
 
 use crate::{
     block_ingestor::BlockInfo,
     data_availability::DataAvailabilityCursor,
+    metrics::{
+        Label, INTEGRITY_VERIFICATION_DURATION, INTEGRITY_VERIFICATION_FEE, LATEST_SETTLED_BLOCK,
+    },
+    prover::{integrity_fact_hash, verify_proof, StarknetOsOutput, VerifiedProof},
     service::{Daemon, FinishHandle},
-    settlement::{SettlementBackend, SettlementBackendBuilder, SettlementCursor},
-    storage::PersistantStorage,
-    utils::{calculate_output, felt_to_bigdecimal, split_calls, watch_tx},
+    settlement::{
+        fees::{
+            FeeConfig, FeeLimitExceeded, SettlementBudget, TransactionReverted, TransactionSender,
+        },
+        SettlementBackend, SettlementBackendBuilder, SettlementCursor,
+    },
+    storage::{BlockStatus, IntegrityJob, PersistantStorage, SettlementCost, SettlementCostKind},
+    utils::{calculate_output, felt_to_bigdecimal, pack_call_groups, split_calls},
 };
 
 const POLLING_INTERVAL: Duration = Duration::from_secs(1);
+const FACT_POLLING_INTERVAL: Duration = Duration::from_secs(10);
+const DEFAULT_FACT_TIMEOUT: Duration = Duration::from_secs(3600);
+const BUDGET_POLLING_INTERVAL: Duration = Duration::from_secs(60);
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(10);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(600);
+const SECONDS_PER_DAY: u64 = 86_400;
+const STRK_ADDRESS: Felt =
+    felt!("0x04718f5a0fc34cc1af16a1cdee98ffb20c31f5cd61d6ab07201858f4287c938d");
+const DEFAULT_BATCH_SIZE: usize = 1;
 
 #[derive(Debug)]
 pub struct PiltoverSettlementBackend<DB> {
     provider: Arc<JsonRpcClient<HttpTransport>>,
-    account: SingleOwnerAccount<Arc<JsonRpcClient<HttpTransport>>, LocalWallet>,
+    sender: TransactionSender,
+    account_address: Felt,
+    budget: SettlementBudget,
     fact_registration: FactRegistrationConfig,
+    fact_timeout: Duration,
     piltover_address: Felt,
     da_channel: Receiver<DataAvailabilityCursor<BlockInfo>>,
     cursor_channel: Sender<SettlementCursor>,
     finish_handle: FinishHandle,
     db: DB,
+    verify_proofs_locally: bool,
+    dry_run: bool,
+    batch_size: usize,
+    batch_window: Duration,
+    metrics_labels: Vec<Label>,
 }
 
 #[derive(Debug)]
@@ -50,23 +82,63 @@ pub struct PiltoverSettlementBackendBuilder<DB> {
     rpc_url: Url,
     integrity_address: Option<Felt>,
     skip_fact_registration: bool,
+    fact_registered_by_prover: bool,
+    fact_timeout: Duration,
     piltover_address: Felt,
     account_address: Felt,
     account_private_key: Felt,
     da_channel: Option<Receiver<DataAvailabilityCursor<BlockInfo>>>,
     cursor_channel: Option<Sender<SettlementCursor>>,
     db: DB,
+    verify_proofs_locally: bool,
+    dry_run: bool,
+    batch_size: usize,
+    batch_window: Duration,
+    fee_config: FeeConfig,
+    budget: SettlementBudget,
+    metrics_labels: Vec<Label>,
 }
 
 #[derive(Debug, Decode)]
 struct AppchainState {
-    #[allow(unused)]
     state_root: Felt,
     block_number: Felt,
-    #[allow(unused)]
     block_hash: Felt,
 }
 
+impl AppchainState {
+    /// Checks that `os_output` applies on top of the settled state, as checked by `update_state`.
+    fn check_followed_by(&self, os_output: &StarknetOsOutput) -> Result<()> {
+        if self.block_number != os_output.previous_block_number {
+            anyhow::bail!(
+                "block number mismatch: block {} follows block {} but the last settled block is {}",
+                os_output.block_number,
+                os_output.previous_block_number,
+                self.block_number
+            );
+        }
+        if self.state_root != os_output.initial_root {
+            anyhow::bail!(
+                "state root mismatch: block {} starts from root {:#064x} but the settled root is \
+                {:#064x}",
+                os_output.block_number,
+                os_output.initial_root,
+                self.state_root
+            );
+        }
+        if self.block_hash != os_output.previous_block_hash {
+            anyhow::bail!(
+                "block hash mismatch: block {} follows block hash {:#064x} but the settled block \
+                hash is {:#064x}",
+                os_output.block_number,
+                os_output.previous_block_hash,
+                self.block_hash
+            );
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Encode)]
 struct UpdateStateCalldata {
     snos_output: Vec<Felt>,
@@ -75,9 +147,33 @@ struct UpdateStateCalldata {
     onchain_data_size: U256,
 }
 
+/// A block waiting to be settled as part of a batch.
+#[derive(Debug)]
+struct ReadyBlock {
+    cursor: DataAvailabilityCursor<BlockInfo>,
+    os_output: StarknetOsOutput,
+    call: Call,
+    /// Layout bridge proof whose fact still needs to be registered on integrity before settlement.
+    unverified_proof: Option<StarkProof>,
+}
+
+/// Outcome of preparing a block for settlement.
+#[derive(Debug)]
+enum PreparedBlock {
+    Ready(ReadyBlock),
+    /// The block can't be settled yet, e.g. because it's being proven again after a reset, and is
+    /// kept pending to be prepared again later.
+    Pending(DataAvailabilityCursor<BlockInfo>),
+    /// The block was failed or requeued, and comes back through the data availability channel
+    /// once it's processed again.
+    Dropped,
+}
+
 #[derive(Debug)]
 enum FactRegistrationConfig {
     Integrity(Felt),
+    /// Facts are registered on integrity by the prover, e.g. with Atlantic's L2 verification.
+    Prover(Felt),
     Skipped,
 }
 
@@ -101,267 +197,967 @@ where
         Ok(AppchainState::decode(&raw_result)?)
     }
 
-    async fn run(mut self) {
-        let mut pending_blocks: BTreeMap<u64, DataAvailabilityCursor<BlockInfo>> = BTreeMap::new();
-        loop {
-            let last_settled_block = self.get_block_number().await.unwrap();
+    /// Verifies the layout bridge proof of a block locally. Returns whether the proof is valid.
+    async fn verify_locally(&self, block_number: u64, proof: &StarkProof) -> bool {
+        let verification_start = Instant::now();
+        let proof = proof.clone();
+        let result = match tokio::task::spawn_blocking(move || verify_proof(&proof)).await {
+            Ok(result) => result,
+            Err(err) => Err(anyhow::anyhow!("verification task failed: {}", err)),
+        };
 
-            let next_to_settle = if last_settled_block == Felt::MAX {
-                0
-            } else {
-                <Felt as TryInto<u64>>::try_into(last_settled_block).unwrap() + 1
-            };
+        handle_local_verification(&self.db, block_number, result, verification_start.elapsed())
+            .await
+    }
 
-            let da = pending_blocks.remove(&next_to_settle);
+    /// Checks that a block can be settled after `previous`, or after the last settled block if
+    /// `None`, and builds its `update_state` call.
+    ///
+    /// Facts are registered on integrity later on for the whole batch, see [`Self::verify_batch`].
+    async fn prepare_block(
+        &self,
+        new_da: DataAvailabilityCursor<BlockInfo>,
+        previous: Option<&StarknetOsOutput>,
+        appchain_state: &AppchainState,
+    ) -> PreparedBlock {
+        let status = match new_da.block_number.try_into() {
+            Ok(block_number) => self.db.get_status(block_number).await,
+            Err(err) => Err(anyhow::Error::from(err)),
+        };
+        let verified = match status {
+            Ok(BlockStatus::BridgeProofGenerated) => false,
+            Ok(BlockStatus::VerifiedProof) => {
+                info!(
+                    block_number = new_da.block_number;
+                    "Block already verified, skipping verification",
+                );
+                true
+            }
+            Ok(BlockStatus::Failed) => {
+                error!(
+                    block_number = new_da.block_number;
+                    "Block failed, settlement halted until it's reset",
+                );
+                return PreparedBlock::Dropped;
+            }
+            Ok(status) => {
+                warn!(
+                    block_number = new_da.block_number, status:% = status;
+                    "Block in unexpected state, retrying settlement later",
+                );
+                return PreparedBlock::Pending(new_da);
+            }
+            Err(err) => {
+                error!(
+                    block_number = new_da.block_number, error:% = err;
+                    "Failed to get block status, retrying settlement later",
+                );
+                return PreparedBlock::Pending(new_da);
+            }
+        };
 
-            let Some(new_da) = da else {
-                let new_da = tokio::select! {
-                    _ = self.finish_handle.shutdown_requested() => break,
-                    new_da = self.da_channel.recv() => new_da,
-                };
-                let new_da = match new_da {
-                    Some(new_da) => new_da,
-                    None => {
-                        debug!("Data availability channel closed, shutting down");
-                        break;
-                    }
-                };
+        // Stored proofs that can't be read would fail the same way again.
+        let proofs = match self.get_snos_output(new_da.block_number).await {
+            Ok(snos_output) => self
+                .get_layout_bridge_proof(new_da.block_number)
+                .await
+                .map(|layout_bridge_proof| (snos_output, layout_bridge_proof)),
+            Err(err) => Err(err),
+        };
+        let (snos_output, layout_bridge_proof) = match proofs {
+            Ok(proofs) => proofs,
+            Err(err) => {
+                self.fail_block(
+                    new_da.block_number,
+                    format!("invalid stored proof: {}", err),
+                )
+                .await;
+                return PreparedBlock::Dropped;
+            }
+        };
 
-                pending_blocks.insert(new_da.block_number, new_da.clone());
-                continue;
-            };
+        let os_output = match StarknetOsOutput::from_bootloader_output(&snos_output) {
+            Ok(os_output) => os_output,
+            Err(err) => {
+                self.fail_block(new_da.block_number, format!("invalid SNOS output: {}", err))
+                    .await;
+                return PreparedBlock::Dropped;
+            }
+        };
 
-            debug!("Received new DA cursor");
-            let layout_bridge_proof = self
-                .db
-                .get_proof(
-                    new_da.block_number.try_into().unwrap(),
-                    crate::storage::Step::Bridge,
-                )
-                .await
-                .unwrap();
-            let raw_proof = String::from_utf8(layout_bridge_proof).unwrap();
-            let layout_bridge_proof = swiftness::parse(raw_proof).unwrap().transform_to();
+        // Blocks must chain from the settled state, otherwise `update_state` and the whole
+        // multicall would revert.
+        let chained = match previous {
+            Some(previous) => previous.check_followed_by(&os_output),
+            None => appchain_state.check_followed_by(&os_output),
+        };
+        if let Err(err) = chained {
+            self.fail_block(new_da.block_number, err.to_string()).await;
+            return PreparedBlock::Dropped;
+        }
 
-            match self
-                .db
-                .get_status(new_da.block_number.try_into().unwrap())
-                .await
-                .unwrap()
+        let call = self.update_state_call(snos_output, &layout_bridge_proof);
+        let unverified_proof = match self.fact_registration {
+            _ if verified => None,
+            FactRegistrationConfig::Integrity(integrity_address)
+                if self
+                    .is_fact_registered(
+                        new_da.block_number,
+                        integrity_address,
+                        &layout_bridge_proof,
+                    )
+                    .await =>
             {
-                crate::storage::BlockStatus::BridgeProofGenerated => {
-                    match self.fact_registration {
-                        FactRegistrationConfig::Integrity(integrity_address) => {
-                            // TODO: error handling
-                            let split_proof = split_proof::<
-                                swiftness_air::layout::recursive_with_poseidon::Layout,
-                            >(
-                                layout_bridge_proof.clone()
-                            )
-                            .unwrap();
-                            let integrity_job_id = SigningKey::from_random().secret_scalar();
-                            let integrity_calls = split_proof
-                                .into_calls(
-                                    integrity_job_id,
-                                    VerifierConfiguration {
-                                        layout: short_string!("recursive_with_poseidon"),
-                                        hasher: short_string!("keccak_160_lsb"),
-                                        stone_version: short_string!("stone6"),
-                                        memory_verification: short_string!("relaxed"),
-                                    },
-                                )
-                                .collect_calls(integrity_address);
-                            let integrity_call_chunks = split_calls(integrity_calls);
-                            debug!(
-                                integrity_job_id:% = format!("{:#064x}",integrity_job_id);
-                                "{} transactions to integrity verifier generated",
-                                integrity_call_chunks.len()
-                            );
-
-                            // TODO: error handling
-                            let mut nonce = self.account.get_nonce().await.unwrap();
-                            let mut total_fee = Felt::ZERO;
-
-                            let proof_start = Instant::now();
-
-                            for (ind, chunk) in integrity_call_chunks.iter().enumerate() {
-                                let execution =
-                                    self.account.execute_v3(chunk.to_owned()).nonce(nonce);
-                                let tx = crate::utils::retry_with_backoff(
-                                    || execution.send(),
-                                    "integrity_verification",
-                                    3,
-                                    Duration::from_secs(3),
-                                )
-                                .await
-                                .unwrap();
-                                debug!(
-                                    "[{} / {}] Integrity verification transaction sent: {:#064x}",
-                                    ind + 1,
-                                    integrity_call_chunks.len(),
-                                    tx.transaction_hash
-                                );
-
-                                // TODO: error handling
-                                let receipt =
-                                    watch_tx(&self.provider, tx.transaction_hash, POLLING_INTERVAL)
-                                        .await
-                                        .unwrap();
-
-                                let fee = match &receipt.receipt {
-                                    TransactionReceipt::Invoke(receipt) => &receipt.actual_fee,
-                                    TransactionReceipt::L1Handler(receipt) => &receipt.actual_fee,
-                                    TransactionReceipt::Declare(receipt) => &receipt.actual_fee,
-                                    TransactionReceipt::Deploy(receipt) => &receipt.actual_fee,
-                                    TransactionReceipt::DeployAccount(receipt) => {
-                                        &receipt.actual_fee
-                                    }
-                                };
-
-                                debug!(
-                                    transaction_hash:% = format!("{:#064x}", tx.transaction_hash);
-                                    "[{} / {}] Integrity verification transaction confirmed",
-                                    ind + 1,
-                                    integrity_call_chunks.len()
-                                );
-
-                                nonce += Felt::ONE;
-                                total_fee += fee.amount;
-                            }
-
-                            let proof_end = Instant::now();
-                            info!(
-                                "Proof successfully verified on integrity in {:.2} \
-                                seconds. Total cost: {} STRK",
-                                proof_end.duration_since(proof_start).as_secs_f32(),
-                                felt_to_bigdecimal(total_fee, 18)
-                            );
-                            self.db
-                                .set_status(
-                                    next_to_settle.try_into().unwrap(),
-                                    "verified_proof".to_string(),
-                                )
-                                .await
-                                .unwrap();
-                        }
-                        FactRegistrationConfig::Skipped => {
-                            info!(
-                                block_number = new_da.block_number;
-                                "On-chain fact-registration skipped for block",
-                            );
-                        }
+                info!(
+                    block_number = new_da.block_number;
+                    "Fact already registered on integrity, skipping verification",
+                );
+                // The fact is checked again after a restart if the status can't be recorded.
+                if let Err(err) = self
+                    .db
+                    .set_status(
+                        new_da.block_number.try_into().unwrap(),
+                        "verified_proof".to_string(),
+                    )
+                    .await
+                {
+                    error!(
+                        block_number = new_da.block_number, error:% = err;
+                        "Failed to mark block as verified"
+                    );
+                }
+                None
+            }
+            FactRegistrationConfig::Integrity(_) => {
+                if self.verify_proofs_locally
+                    && !self
+                        .verify_locally(new_da.block_number, &layout_bridge_proof)
+                        .await
+                {
+                    return PreparedBlock::Dropped;
+                }
+                Some(layout_bridge_proof)
+            }
+            FactRegistrationConfig::Prover(_) => Some(layout_bridge_proof),
+            FactRegistrationConfig::Skipped => {
+                info!(
+                    block_number = new_da.block_number;
+                    "On-chain fact-registration skipped for block",
+                );
+                None
+            }
+        };
+
+        PreparedBlock::Ready(ReadyBlock {
+            cursor: new_da,
+            os_output,
+            call,
+            unverified_proof,
+        })
+    }
+
+    /// Marks a block that can never be settled as failed. Proving it again would produce the same
+    /// output, so it's not requeued, and settlement halts until it's reset, e.g. via the admin API.
+    async fn fail_block(&self, block_number: u64, reason: String) {
+        error!(
+            block_number, reason:% = reason;
+            "Block cannot be settled, settlement halted until it's reset"
+        );
+        if let Err(err) = self
+            .db
+            .fail_block(block_number.try_into().unwrap(), reason)
+            .await
+        {
+            error!(block_number, error:% = err; "Failed to mark block as failed");
+        }
+    }
+
+    /// Registers the facts of the layout bridge proofs of a batch that are not verified yet, and
+    /// marks their blocks as verified. Returns `false` if shutdown is requested before then.
+    async fn verify_batch(&self, batch: &mut [ReadyBlock]) -> Result<bool> {
+        match self.fact_registration {
+            FactRegistrationConfig::Integrity(integrity_address) => {
+                let proofs = batch
+                    .iter()
+                    .filter_map(|block| {
+                        let proof = block.unverified_proof.as_ref()?;
+                        Some((block.cursor.block_number, proof))
+                    })
+                    .collect::<Vec<_>>();
+                if !proofs.is_empty() && !self.register_facts(integrity_address, &proofs).await? {
+                    return Ok(false);
+                }
+            }
+            FactRegistrationConfig::Prover(integrity_address) => {
+                let mut unregistered = Vec::new();
+                for block in batch.iter() {
+                    let Some(proof) = &block.unverified_proof else {
+                        continue;
+                    };
+                    match self
+                        .wait_for_fact(block.cursor.block_number, integrity_address, proof)
+                        .await
+                    {
+                        Some(true) => {}
+                        Some(false) => unregistered.push((block.cursor.block_number, proof)),
+                        None => return Ok(false),
                     }
                 }
-                crate::storage::BlockStatus::VerifiedProof => {
+
+                // Facts the prover failed to register in time are registered by the settlement
+                // account instead, so that settlement doesn't stall on the prover.
+                if !unregistered.is_empty() {
                     info!(
-                        block_number = new_da.block_number;
-                        "Block already verified, skipping verification",
+                        "Registering {} facts on integrity after the prover timed out",
+                        unregistered.len()
                     );
+                    if !self
+                        .register_facts(integrity_address, &unregistered)
+                        .await?
+                    {
+                        return Ok(false);
+                    }
                 }
-                _ => {
-                    info!(
-                        block_number = new_da.block_number;
-                        "Block in unexpected state, skipping settlement",
-                    );
-                    continue;
+            }
+            FactRegistrationConfig::Skipped => {}
+        }
+
+        for block in batch.iter_mut() {
+            if block.unverified_proof.take().is_some() {
+                self.db
+                    .set_status(
+                        block.cursor.block_number.try_into()?,
+                        "verified_proof".to_string(),
+                    )
+                    .await?;
+            }
+        }
+        Ok(true)
+    }
+
+    async fn get_layout_bridge_proof(&self, block_number: u64) -> Result<StarkProof> {
+        let layout_bridge_proof = self
+            .db
+            .get_proof(block_number.try_into()?, crate::storage::Step::Bridge)
+            .await?;
+        Ok(swiftness::parse(String::from_utf8(layout_bridge_proof)?)?.transform_to())
+    }
+
+    /// Gets the bootloader output of the SNOS proof of a block.
+    async fn get_snos_output(&self, block_number: u64) -> Result<Vec<Felt>> {
+        let snos_proof = self
+            .db
+            .get_proof(block_number.try_into()?, crate::storage::Step::Snos)
+            .await?;
+        let parsed_snos_proof: StarkProof =
+            swiftness::parse(String::from_utf8(snos_proof)?)?.transform_to();
+        Ok(calculate_output(&parsed_snos_proof))
+    }
+
+    fn update_state_call(&self, snos_output: Vec<Felt>, layout_bridge_proof: &StarkProof) -> Call {
+        let calldata = UpdateStateCalldata {
+            snos_output,
+            program_output: calculate_output(layout_bridge_proof),
+            onchain_data_hash: Felt::ZERO,
+            onchain_data_size: U256::from_words(0, 0),
+        };
+        let mut raw_calldata = vec![];
+
+        // Encoding `UpdateStateCalldata` never fails
+        calldata.encode(&mut raw_calldata).unwrap();
+
+        Call {
+            to: self.piltover_address,
+            selector: selector!("update_state"),
+            calldata: raw_calldata,
+        }
+    }
+
+    /// Checks whether the fact of a layout bridge proof is already registered on integrity, e.g. by
+    /// another operator or an earlier run. Failing to check is treated as not registered.
+    async fn is_fact_registered(
+        &self,
+        block_number: u64,
+        integrity_address: Felt,
+        proof: &StarkProof,
+    ) -> bool {
+        let fact_hash = match integrity_fact_hash(proof) {
+            Ok(fact_hash) => fact_hash,
+            Err(err) => {
+                warn!(block_number, error:% = err; "Failed to compute fact hash");
+                return false;
+            }
+        };
+
+        let verifications = self
+            .provider
+            .call(
+                FunctionCall {
+                    contract_address: integrity_address,
+                    entry_point_selector: selector!("get_all_verifications_for_fact_hash"),
+                    calldata: vec![fact_hash],
+                },
+                BlockId::Tag(BlockTag::Pending),
+            )
+            .await;
+
+        match verifications {
+            // The result is an array of verifications, prefixed by its length.
+            Ok(verifications) => verifications
+                .first()
+                .is_some_and(|count| *count != Felt::ZERO),
+            Err(err) => {
+                warn!(
+                    block_number,
+                    fact_hash:% = format!("{:#064x}", fact_hash),
+                    error:% = err;
+                    "Failed to query fact registry"
+                );
+                false
+            }
+        }
+    }
+
+    /// Waits for the fact of a layout bridge proof to be registered on integrity by the prover.
+    /// Returns whether the fact got registered before `fact_timeout`, or `None` if shutdown is
+    /// requested in the meantime.
+    async fn wait_for_fact(
+        &self,
+        block_number: u64,
+        integrity_address: Felt,
+        proof: &StarkProof,
+    ) -> Option<bool> {
+        let deadline = Instant::now() + self.fact_timeout;
+        loop {
+            if self
+                .is_fact_registered(block_number, integrity_address, proof)
+                .await
+            {
+                info!(block_number; "Fact registered on integrity by the prover");
+                return Some(true);
+            }
+            if Instant::now() >= deadline {
+                warn!(
+                    block_number;
+                    "Fact not registered by the prover after {} seconds",
+                    self.fact_timeout.as_secs()
+                );
+                return Some(false);
+            }
+
+            debug!(block_number; "Waiting for fact to be registered on integrity");
+            tokio::select! {
+                _ = self.finish_handle.shutdown_requested() => return None,
+                _ = tokio::time::sleep(FACT_POLLING_INTERVAL) => {},
+            }
+        }
+    }
+
+    /// Sends a transaction on behalf of `blocks` and records its fee, split between them, even if it
+    /// reverts. Settlement is paused while the transaction would exceed the settlement budget.
+    /// Returns `None` if shutdown is requested while paused.
+    async fn send_transaction(
+        &self,
+        blocks: &[u64],
+        kind: SettlementCostKind,
+        calls: Vec<Call>,
+        max_fee: Option<Felt>,
+    ) -> Result<Option<TransactionReceiptWithBlockInfo>> {
+        loop {
+            let budget = self.remaining_budget().await?;
+            let limit = match (max_fee, budget) {
+                (Some(max_fee), Some(budget)) => Some(max_fee.min(budget)),
+                (max_fee, budget) => max_fee.or(budget),
+            };
+
+            let result = if budget == Some(Felt::ZERO) {
+                Err(anyhow::anyhow!("settlement budget exhausted"))
+            } else {
+                self.sender
+                    .send(calls.clone(), limit, POLLING_INTERVAL)
+                    .await
+            };
+
+            match result {
+                Ok(receipt) => {
+                    self.record_cost(blocks, kind, &receipt).await?;
+                    return Ok(Some(receipt));
+                }
+                Err(err)
+                    if budget == Some(Felt::ZERO)
+                        || (budget == limit && err.is::<FeeLimitExceeded>()) =>
+                {
+                    error!(error:% = err; "Settlement budget would be exceeded, pausing settlement");
+                    tokio::select! {
+                        _ = self.finish_handle.shutdown_requested() => return Ok(None),
+                        _ = tokio::time::sleep(BUDGET_POLLING_INTERVAL) => {},
+                    }
+                }
+                Err(err) => {
+                    // Reverted transactions are paid for, and count against the budget.
+                    if let Some(reverted) = err.downcast_ref::<TransactionReverted>() {
+                        self.record_cost(blocks, kind, &reverted.receipt).await?;
+                    }
+                    return Err(err);
                 }
             }
+        }
+    }
 
-            let new_snos_proof = self
+    /// Computes the fee that can be spent without exceeding the settlement budget, or `None` if
+    /// the budget is unlimited.
+    async fn remaining_budget(&self) -> Result<Option<Felt>> {
+        let mut remaining = None;
+
+        if let Some(max_daily_fee) = self.budget.max_daily_fee {
+            let now = unix_timestamp();
+            let spent_today = self
                 .db
-                .get_proof(
-                    new_da.block_number.try_into().unwrap(),
-                    crate::storage::Step::Snos,
-                )
-                .await
-                .unwrap();
-
-            let new_snos_proof = String::from_utf8(new_snos_proof).unwrap();
-            let parsed_snos_proof = swiftness::parse(&new_snos_proof).unwrap().transform_to();
-            let snos_output = calculate_output(&parsed_snos_proof);
-
-            let update_state_call = Call {
-                to: self.piltover_address,
-                selector: selector!("update_state"),
-                calldata: {
-                    let calldata = UpdateStateCalldata {
-                        snos_output,
-                        program_output: calculate_output(&layout_bridge_proof),
-                        onchain_data_hash: Felt::ZERO,
-                        onchain_data_size: U256::from_words(0, 0),
-                    };
-                    let mut raw_calldata = vec![];
+                .list_settlement_costs(now - now % SECONDS_PER_DAY)
+                .await?
+                .iter()
+                .fold(Felt::ZERO, |spent, cost| spent + cost.fee);
+            remaining = Some(if spent_today < max_daily_fee {
+                max_daily_fee - spent_today
+            } else {
+                Felt::ZERO
+            });
+        }
+
+        if let Some(min_balance) = self.budget.min_balance {
+            let balance = self.get_balance().await?;
+            let available = if balance > min_balance {
+                balance - min_balance
+            } else {
+                Felt::ZERO
+            };
+            remaining =
+                Some(remaining.map_or(available, |remaining: Felt| remaining.min(available)));
+        }
 
-                    // Encoding `UpdateStateCalldata` never fails
-                    calldata.encode(&mut raw_calldata).unwrap();
+        Ok(remaining)
+    }
 
-                    raw_calldata
+    /// Gets the STRK balance of the settlement account in FRI.
+    async fn get_balance(&self) -> Result<Felt> {
+        let raw_result = self
+            .provider
+            .call(
+                FunctionCall {
+                    contract_address: STRK_ADDRESS,
+                    entry_point_selector: selector!("balance_of"),
+                    calldata: vec![self.account_address],
                 },
+                BlockId::Tag(BlockTag::Pending),
+            )
+            .await?;
+
+        // The total supply of STRK fits in the lower 128 bits.
+        Ok(Felt::from(U256::decode(&raw_result)?.low()))
+    }
+
+    async fn record_cost(
+        &self,
+        blocks: &[u64],
+        kind: SettlementCostKind,
+        receipt: &TransactionReceiptWithBlockInfo,
+    ) -> Result<()> {
+        let fee = actual_fee(&receipt.receipt)
+            .to_u128()
+            .ok_or_else(|| anyhow::anyhow!("transaction fee out of range"))?;
+        let blocks_count = blocks.len() as u128;
+        let timestamp = unix_timestamp();
+
+        for (ind, block_number) in blocks.iter().enumerate() {
+            // The first block gets the remainder of the split.
+            let share = if ind == 0 {
+                fee / blocks_count + fee % blocks_count
+            } else {
+                fee / blocks_count
             };
+            self.db
+                .add_settlement_cost(SettlementCost {
+                    block_number: (*block_number).try_into()?,
+                    kind,
+                    transaction_hash: *receipt.receipt.transaction_hash(),
+                    fee: Felt::from(share),
+                    timestamp,
+                })
+                .await?;
+        }
 
-            let execution = self.account.execute_v3(vec![update_state_call]);
+        Ok(())
+    }
 
-            // TODO: error handling
-            let fees = crate::utils::retry_with_backoff(
-                || execution.estimate_fee(),
-                "estimate_fee",
-                3,
-                Duration::from_secs(3),
-            )
-            .await
-            .unwrap();
+    /// Builds the integrity and `update_state` transactions of a block and simulates them in
+    /// sequence instead of sending them. The database is left untouched.
+    async fn dry_run_block(&self, block_number: u64) {
+        let proofs = match self.get_snos_output(block_number).await {
+            Ok(snos_output) => self
+                .get_layout_bridge_proof(block_number)
+                .await
+                .map(|layout_bridge_proof| (snos_output, layout_bridge_proof)),
+            Err(err) => Err(err),
+        };
+        let (snos_output, layout_bridge_proof) = match proofs {
+            Ok(proofs) => proofs,
+            Err(err) => {
+                error!(block_number, error:% = err; "Dry run: invalid stored proof");
+                return;
+            }
+        };
+
+        let integrity_chunks = match self.fact_registration {
+            FactRegistrationConfig::Integrity(integrity_address)
+                if !self
+                    .is_fact_registered(block_number, integrity_address, &layout_bridge_proof)
+                    .await =>
+            {
+                integrity_call_chunks(
+                    integrity_address,
+                    &new_integrity_job(),
+                    layout_bridge_proof.clone(),
+                )
+            }
+            _ => vec![],
+        };
+        let settlement_chunks = split_calls(vec![
+            self.update_state_call(snos_output, &layout_bridge_proof)
+        ]);
+        let integrity_count = integrity_chunks.len();
+        let transactions = integrity_chunks
+            .into_iter()
+            .chain(settlement_chunks)
+            .collect::<Vec<_>>();
+
+        info!(
+            block_number;
+            "Dry run: simulating {} integrity and {} settlement transactions",
+            integrity_count,
+            transactions.len() - integrity_count
+        );
+
+        let simulations = match self.sender.simulate(&transactions).await {
+            Ok(simulations) => simulations,
+            Err(err) => {
+                error!(block_number, error:% = err; "Dry run: failed to simulate transactions");
+                return;
+            }
+        };
+
+        let mut total_fee = Felt::ZERO;
+        for (ind, (calls, simulation)) in transactions.iter().zip(simulations).enumerate() {
+            let kind = if ind < integrity_count {
+                SettlementCostKind::Integrity
+            } else {
+                SettlementCostKind::Settlement
+            };
+            let calldata_len = calls.iter().map(|call| call.calldata.len()).sum::<usize>();
+            let fee = simulation.fee_estimation.overall_fee;
+            total_fee += fee;
+
+            let revert_reason = match &simulation.transaction_trace {
+                TransactionTrace::Invoke(InvokeTransactionTrace {
+                    execute_invocation: ExecuteInvocation::Reverted(reverted),
+                    ..
+                }) => Some(&reverted.revert_reason),
+                _ => None,
+            };
+            match revert_reason {
+                Some(revert_reason) => warn!(
+                    block_number, kind:%, calldata_len, revert_reason:%;
+                    "Dry run: [{} / {}] transaction would revert",
+                    ind + 1,
+                    transactions.len()
+                ),
+                None => info!(
+                    block_number, kind:%, calldata_len;
+                    "Dry run: [{} / {}] transaction would cost {} STRK",
+                    ind + 1,
+                    transactions.len(),
+                    felt_to_bigdecimal(fee, 18)
+                ),
+            }
+        }
+
+        info!(
+            block_number;
+            "Dry run: estimated total cost of {} STRK",
+            felt_to_bigdecimal(total_fee, 18)
+        );
+    }
+
+    /// Verifies the layout bridge proofs of a batch of blocks on integrity. Returns `false` if
+    /// shutdown is requested before all verifications are complete.
+    ///
+    /// Each proof is verified in its own integrity job, whose transactions are sent in order. Only
+    /// the last transaction of a job leaves room for more calls, so the last transactions of all
+    /// jobs are packed together into as few transactions as possible.
+    async fn register_facts(
+        &self,
+        integrity_address: Felt,
+        proofs: &[(u64, &StarkProof)],
+    ) -> Result<bool> {
+        // Resuming with the same job ID and configuration generates the same transactions, so the
+        // ones already confirmed before a restart can be skipped instead of paying for them twice.
+        let mut jobs = Vec::with_capacity(proofs.len());
+        for (block_number, proof) in proofs {
+            let block_number_u32 = (*block_number).try_into()?;
+            let integrity_job = match self.db.get_integrity_job(block_number_u32).await? {
+                Some(job) => job,
+                None => {
+                    let job = new_integrity_job();
+                    self.db
+                        .add_integrity_job(block_number_u32, job.clone())
+                        .await?;
+                    job
+                }
+            };
+            let chunks = integrity_call_chunks(integrity_address, &integrity_job, (*proof).clone());
+            let confirmed_chunks = integrity_job.confirmed_chunks as usize;
             debug!(
-                block_number = new_da.block_number;
-                "Estimated settlement transaction cost for block: {} STRK",
-                felt_to_bigdecimal(fees.overall_fee, 18)
+                block_number = *block_number,
+                integrity_job_id:% = format!("{:#064x}", integrity_job.job_id);
+                "{} transactions to integrity verifier generated",
+                chunks.len()
             );
+            if confirmed_chunks > 0 {
+                info!(
+                    block_number = *block_number;
+                    "Resuming integrity verification after {} of {} confirmed transactions",
+                    confirmed_chunks,
+                    chunks.len()
+                );
+            }
+            // Fees spent before a restart count against the limit of the block.
+            let total_fee = self
+                .db
+                .get_block_fee(block_number_u32, SettlementCostKind::Integrity)
+                .await?;
+            jobs.push(IntegrityProgress {
+                block_number: *block_number,
+                chunks,
+                confirmed_chunks,
+                total_fee,
+            });
+        }
 
-            // TODO: wait for transaction to confirm
-            // TODO: error handling
-            let transaction = crate::utils::retry_with_backoff(
-                || execution.send(),
-                "settlement",
-                3,
-                Duration::from_secs(3),
-            )
-            .await
-            .unwrap();
+        let proof_start = Instant::now();
+
+        for ind in 0..jobs.len() {
+            while jobs[ind].confirmed_chunks + 1 < jobs[ind].chunks.len() {
+                let chunk = jobs[ind].chunks[jobs[ind].confirmed_chunks].clone();
+                if !self
+                    .send_integrity_transaction(&mut jobs, &[ind], chunk)
+                    .await?
+                {
+                    return Ok(false);
+                }
+            }
+        }
+
+        let last_chunks = jobs
+            .iter()
+            .enumerate()
+            .filter(|(_, job)| job.confirmed_chunks < job.chunks.len())
+            .map(|(ind, job)| (ind, job.chunks[job.confirmed_chunks].clone()))
+            .collect::<Vec<_>>();
+        let (job_indices, last_chunks): (Vec<_>, Vec<_>) = last_chunks.into_iter().unzip();
+        for (packed, calls) in pack_call_groups(last_chunks) {
+            let members = packed
+                .into_iter()
+                .map(|ind| job_indices[ind])
+                .collect::<Vec<_>>();
+            if !self
+                .send_integrity_transaction(&mut jobs, &members, calls)
+                .await?
+            {
+                return Ok(false);
+            }
+        }
+
+        let verification_duration = proof_start.elapsed();
+        for job in jobs {
+            let total_fee = felt_to_bigdecimal(job.total_fee, 18);
+            histogram!(INTEGRITY_VERIFICATION_DURATION, self.metrics_labels.clone())
+                .record(verification_duration);
+            histogram!(INTEGRITY_VERIFICATION_FEE, self.metrics_labels.clone())
+                .record(total_fee.to_f64().unwrap_or_default());
             info!(
-                block_number = new_da.block_number,
-                transaction_hash:% = format!("{:#064x}", transaction.transaction_hash);
-                "Piltover statement transaction sent",
+                block_number = job.block_number;
+                "Proof successfully verified on integrity in {:.2} seconds. Total cost: {} STRK",
+                verification_duration.as_secs_f32(),
+                total_fee
             );
+        }
+        Ok(true)
+    }
 
-            // TODO: timeout
-            // TODO: error handling
-            watch_tx(
-                &self.provider,
-                transaction.transaction_hash,
-                POLLING_INTERVAL,
-            )
-            .await
-            .unwrap();
+    /// Sends the next integrity transaction of the jobs at `members`, and records their progress.
+    /// Returns `false` if shutdown is requested before the transaction is sent.
+    async fn send_integrity_transaction(
+        &self,
+        jobs: &mut [IntegrityProgress],
+        members: &[usize],
+        calls: Vec<Call>,
+    ) -> Result<bool> {
+        let blocks = members
+            .iter()
+            .map(|ind| jobs[*ind].block_number)
+            .collect::<Vec<_>>();
+        let max_fee = self.sender.config().max_fee_per_block.map(|max_fee| {
+            members.iter().fold(Felt::ZERO, |limit, ind| {
+                limit + max_fee - max_fee.min(jobs[*ind].total_fee)
+            })
+        });
 
-            info!(
-                block_number = new_da.block_number,
-                transaction_hash:% = format!("{:#064x}", transaction.transaction_hash);
-                "Piltover statement transaction confirmed",
+        let Some(receipt) = self
+            .send_transaction(&blocks, SettlementCostKind::Integrity, calls, max_fee)
+            .await?
+        else {
+            return Ok(false);
+        };
+
+        let fee = actual_fee(&receipt.receipt)
+            .to_u128()
+            .ok_or_else(|| anyhow::anyhow!("transaction fee out of range"))?;
+        let fee_share = Felt::from(fee / members.len() as u128);
+        for ind in members {
+            let job = &mut jobs[*ind];
+            job.confirmed_chunks += 1;
+            job.total_fee += fee_share;
+
+            debug!(
+                block_number = job.block_number,
+                transaction_hash:% = format!("{:#064x}", receipt.receipt.transaction_hash());
+                "[{} / {}] Integrity verification transaction confirmed",
+                job.confirmed_chunks,
+                job.chunks.len()
             );
 
             self.db
-                .remove_block(new_da.block_number.try_into().unwrap())
-                .await
-                .unwrap();
-            let new_cursor = SettlementCursor {
-                block_number: new_da.block_number,
-                transaction_hash: transaction.transaction_hash,
+                .set_integrity_job_progress(
+                    job.block_number.try_into()?,
+                    job.confirmed_chunks.try_into()?,
+                )
+                .await?;
+        }
+        Ok(true)
+    }
+
+    /// Settles a batch of consecutive blocks with as few `update_state` multicalls as the
+    /// transaction size limit allows. The settlement transaction hash of each block is pushed to
+    /// `transaction_hashes` as it's confirmed, which only covers the first blocks of the batch if
+    /// shutdown is requested or a transaction fails in the middle.
+    async fn settle_batch(
+        &self,
+        batch: &[ReadyBlock],
+        transaction_hashes: &mut Vec<Felt>,
+    ) -> Result<()> {
+        let first_block = batch.first().map(|block| block.cursor.block_number);
+        let last_block = batch.last().map(|block| block.cursor.block_number);
+        let chunks = split_calls(batch.iter().map(|block| block.call.clone()).collect());
+
+        for chunk in chunks {
+            let chunk_blocks = batch[transaction_hashes.len()..][..chunk.len()]
+                .iter()
+                .map(|block| block.cursor.block_number)
+                .collect::<Vec<_>>();
+            let chunk_size = chunk.len();
+            let max_fee = self
+                .sender
+                .config()
+                .max_fee_per_block
+                .map(|max_fee| max_fee * Felt::from(chunk_size));
+
+            let Some(receipt) = self
+                .send_transaction(
+                    &chunk_blocks,
+                    SettlementCostKind::Settlement,
+                    chunk,
+                    max_fee,
+                )
+                .await?
+            else {
+                break;
             };
+            let transaction_hash = *receipt.receipt.transaction_hash();
 
-            // Since the channel is bounded, it's possible
-            tokio::select! {
+            info!(
+                first_block:?, last_block:?,
+                transaction_hash:% = format!("{:#064x}", transaction_hash);
+                "Piltover statement transaction confirmed for {} blocks",
+                chunk_size
+            );
+
+            transaction_hashes.extend(std::iter::repeat_n(transaction_hash, chunk_size));
+        }
+
+        Ok(())
+    }
+
+    /// Waits for `delay` before retrying a failed operation. Returns `false` if shutdown is
+    /// requested in the meantime.
+    async fn back_off(&self, delay: Duration) -> bool {
+        tokio::select! {
+            _ = self.finish_handle.shutdown_requested() => false,
+            _ = tokio::time::sleep(delay) => true,
+        }
+    }
+
+    async fn run(mut self) {
+        let mut pending_blocks: BTreeMap<u64, DataAvailabilityCursor<BlockInfo>> = BTreeMap::new();
+        // Blocks ready for settlement but not settled yet, in order.
+        let mut batch: Vec<ReadyBlock> = Vec::new();
+        let mut batch_deadline: Option<Instant> = None;
+        // When the next block to settle is kept pending, blocks are not gathered until then, or
+        // until the block is received again.
+        let mut hold_deadline: Option<Instant> = None;
+        let mut hold_delay = MIN_RETRY_DELAY;
+        let mut retry_delay = MIN_RETRY_DELAY;
+
+        'settlement: loop {
+            let appchain_state = match self.get_state().await {
+                Ok(appchain_state) => appchain_state,
+                Err(err) => {
+                    error!(error:% = err; "Failed to get piltover state");
+                    if !self.back_off(retry_delay).await {
+                        break;
+                    }
+                    retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+                    continue;
+                }
+            };
+            let last_settled_block = appchain_state.block_number;
+
+            let next_to_settle = if last_settled_block == Felt::MAX {
+                0
+            } else {
+                <Felt as TryInto<u64>>::try_into(last_settled_block).unwrap() + 1
+            } + batch.len() as u64;
+
+            // Consecutive blocks already received are gathered first, up to the batch size.
+            let held = hold_deadline.is_some_and(|deadline| Instant::now() < deadline);
+            if batch.len() < self.batch_size && !held {
+                if let Some(new_da) = pending_blocks.remove(&next_to_settle) {
+                    debug!("Received new DA cursor");
+                    let previous = batch.last().map(|block| &block.os_output);
+                    match self.prepare_block(new_da, previous, &appchain_state).await {
+                        PreparedBlock::Ready(block) => {
+                            hold_deadline = None;
+                            hold_delay = MIN_RETRY_DELAY;
+                            batch_deadline
+                                .get_or_insert_with(|| Instant::now() + self.batch_window);
+                            batch.push(block);
+                        }
+                        PreparedBlock::Pending(new_da) => {
+                            // The blocks already gathered are still settled in the meantime.
+                            hold_deadline = Some(Instant::now() + hold_delay);
+                            hold_delay = (hold_delay * 2).min(MAX_RETRY_DELAY);
+                            pending_blocks.insert(new_da.block_number, new_da);
+                        }
+                        PreparedBlock::Dropped => {
+                            hold_deadline = None;
+                            hold_delay = MIN_RETRY_DELAY;
+                        }
+                    }
+                    continue;
+                }
+            }
+
+            let batch_due = batch.len() >= self.batch_size
+                || batch_deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            if !batch.is_empty() && batch_due {
+                let mut transaction_hashes = Vec::with_capacity(batch.len());
+                let result = match self.verify_batch(&mut batch).await {
+                    Ok(true) => self.settle_batch(&batch, &mut transaction_hashes).await,
+                    Ok(false) => break,
+                    Err(err) => Err(err),
+                };
+                batch_deadline = None;
+
+                let settled_count = transaction_hashes.len();
+                for (block, transaction_hash) in
+                    batch.drain(..settled_count).zip(transaction_hashes)
+                {
+                    gauge!(LATEST_SETTLED_BLOCK, self.metrics_labels.clone())
+                        .set(block.cursor.block_number as f64);
+
+                    self.db
+                        .remove_block(block.cursor.block_number.try_into().unwrap())
+                        .await
+                        .unwrap();
+                    let new_cursor = SettlementCursor {
+                        block_number: block.cursor.block_number,
+                        transaction_hash,
+                    };
+
+                    // Since the channel is bounded, it's possible
+                    tokio::select! {
+                        _ = self.finish_handle.shutdown_requested() => break 'settlement,
+                        _ = self.cursor_channel.send(new_cursor) => {},
+                    }
+                }
+
+                match result {
+                    // Shutdown was requested in the middle of the batch.
+                    Ok(()) if !batch.is_empty() => break,
+                    Ok(()) => retry_delay = MIN_RETRY_DELAY,
+                    Err(err) => {
+                        let first_block = batch.first().map(|block| block.cursor.block_number);
+                        error!(
+                            first_block:?, error:% = err;
+                            "Failed to settle batch, retrying in {} seconds",
+                            retry_delay.as_secs()
+                        );
+
+                        // The unsettled blocks are prepared again, so that they are checked
+                        // against the state left by the failed transactions.
+                        for block in batch.drain(..) {
+                            pending_blocks.insert(block.cursor.block_number, block.cursor);
+                        }
+                        if !self.back_off(retry_delay).await {
+                            break;
+                        }
+                        retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+                    }
+                }
+                continue;
+            }
+
+            let wake_up = [batch_deadline, hold_deadline.filter(|_| held)]
+                .into_iter()
+                .flatten()
+                .min();
+            let timeout = async {
+                match wake_up {
+                    Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+                    None => std::future::pending().await,
+                }
+            };
+            let new_da = tokio::select! {
                 _ = self.finish_handle.shutdown_requested() => break,
-                _ = self.cursor_channel.send(new_cursor) => {},
+                _ = timeout => continue,
+                new_da = self.da_channel.recv() => new_da,
+            };
+            let new_da = match new_da {
+                Some(new_da) => new_da,
+                None => {
+                    debug!("Data availability channel closed, shutting down");
+                    break;
+                }
+            };
+
+            if self.dry_run {
+                self.dry_run_block(new_da.block_number).await;
+                continue;
             }
+
+            if pending_blocks.contains_key(&new_da.block_number) {
+                // The block kept pending was processed again.
+                hold_deadline = None;
+            }
+            pending_blocks.insert(new_da.block_number, new_da.clone());
         }
 
         debug!("Graceful shutdown finished");
@@ -369,6 +1165,113 @@ where
     }
 }
 
+/// Handles the result of verifying the layout bridge proof of a block locally. Returns whether the
+/// proof is valid.
+///
+/// Blocks with invalid proofs are reset for their layout bridge proof to be generated again.
+async fn handle_local_verification<DB>(
+    db: &DB,
+    block_number: u64,
+    result: Result<VerifiedProof>,
+    elapsed: Duration,
+) -> bool
+where
+    DB: PersistantStorage,
+{
+    match result {
+        Ok(verified) => {
+            info!(
+                block_number,
+                program_hash:% = format!("{:#064x}", verified.program_hash),
+                output_hash:% = format!("{:#064x}", verified.output_hash);
+                "Layout bridge proof verified locally in {:.2} seconds",
+                elapsed.as_secs_f32()
+            );
+            true
+        }
+        Err(err) => {
+            error!(block_number, error:% = err; "Invalid layout bridge proof, requeuing block");
+
+            let reason = format!("invalid layout bridge proof: {}", err);
+            let block_number = block_number.try_into().unwrap();
+            if let Err(err) = db
+                .reset_block(block_number, BlockStatus::SnosProofGenerated)
+                .await
+            {
+                error!(block_number, error:% = err; "Failed to reset block");
+            }
+            if let Err(err) = db.requeue_block(block_number, reason).await {
+                error!(block_number, error:% = err; "Failed to requeue block");
+            }
+            false
+        }
+    }
+}
+
+/// Progress of the integrity job verifying the layout bridge proof of a block.
+#[derive(Debug)]
+struct IntegrityProgress {
+    block_number: u64,
+    chunks: Vec<Vec<Call>>,
+    confirmed_chunks: usize,
+    /// Fee paid for the integrity transactions of the block so far, including before restarts.
+    total_fee: Felt,
+}
+
+fn new_integrity_job() -> IntegrityJob {
+    IntegrityJob {
+        job_id: SigningKey::from_random().secret_scalar(),
+        layout: short_string!("recursive_with_poseidon"),
+        hasher: short_string!("keccak_160_lsb"),
+        stone_version: short_string!("stone6"),
+        memory_verification: short_string!("relaxed"),
+        confirmed_chunks: 0,
+    }
+}
+
+/// Splits the verification of a layout bridge proof on integrity into transactions.
+fn integrity_call_chunks(
+    integrity_address: Felt,
+    job: &IntegrityJob,
+    layout_bridge_proof: StarkProof,
+) -> Vec<Vec<Call>> {
+    // TODO: error handling
+    let split_proof =
+        split_proof::<swiftness_air::layout::recursive_with_poseidon::Layout>(layout_bridge_proof)
+            .unwrap();
+
+    let integrity_calls = split_proof
+        .into_calls(
+            job.job_id,
+            VerifierConfiguration {
+                layout: job.layout,
+                hasher: job.hasher,
+                stone_version: job.stone_version,
+                memory_verification: job.memory_verification,
+            },
+        )
+        .collect_calls(integrity_address);
+    split_calls(integrity_calls)
+}
+
+fn actual_fee(receipt: &TransactionReceipt) -> Felt {
+    let fee = match receipt {
+        TransactionReceipt::Invoke(receipt) => &receipt.actual_fee,
+        TransactionReceipt::L1Handler(receipt) => &receipt.actual_fee,
+        TransactionReceipt::Declare(receipt) => &receipt.actual_fee,
+        TransactionReceipt::Deploy(receipt) => &receipt.actual_fee,
+        TransactionReceipt::DeployAccount(receipt) => &receipt.actual_fee,
+    };
+    fee.amount
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
 impl<DB> PiltoverSettlementBackendBuilder<DB> {
     pub fn new(
         rpc_url: Url,
@@ -381,12 +1284,21 @@ impl<DB> PiltoverSettlementBackendBuilder<DB> {
             rpc_url,
             integrity_address: None,
             skip_fact_registration: false,
+            fact_registered_by_prover: false,
+            fact_timeout: DEFAULT_FACT_TIMEOUT,
             piltover_address,
             account_address,
             account_private_key,
             da_channel: None,
             cursor_channel: None,
             db,
+            verify_proofs_locally: false,
+            dry_run: false,
+            batch_size: DEFAULT_BATCH_SIZE,
+            batch_window: Duration::ZERO,
+            fee_config: FeeConfig::default(),
+            budget: SettlementBudget::default(),
+            metrics_labels: Vec::new(),
         }
     }
 
@@ -399,6 +1311,68 @@ impl<DB> PiltoverSettlementBackendBuilder<DB> {
         self.skip_fact_registration = skip_fact_registration;
         self
     }
+
+    /// Whether facts are registered on integrity by the prover, e.g. with Atlantic's L2
+    /// verification. The backend then waits for facts to appear at `integrity_address` instead of
+    /// sending verification transactions itself.
+    pub fn fact_registered_by_prover(mut self, fact_registered_by_prover: bool) -> Self {
+        self.fact_registered_by_prover = fact_registered_by_prover;
+        self
+    }
+
+    /// Sets how long to wait for the prover to register the fact of a proof before sending the
+    /// verification transactions from the settlement account instead. Defaults to one hour.
+    pub fn fact_timeout(mut self, fact_timeout: Duration) -> Self {
+        self.fact_timeout = fact_timeout;
+        self
+    }
+
+    /// Whether to verify layout bridge proofs locally before sending them to integrity. Blocks with
+    /// invalid proofs are requeued for proving instead of wasting fees on failing transactions.
+    pub fn verify_proofs_locally(mut self, verify_proofs_locally: bool) -> Self {
+        self.verify_proofs_locally = verify_proofs_locally;
+        self
+    }
+
+    /// Sets the maximum number of consecutive blocks settled together in a single multicall.
+    /// Defaults to `1`, i.e. one settlement transaction per block.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Sets how long to wait for more blocks to become ready once the first block of a batch is
+    /// ready. Blocks already received are always batched regardless of the window. Defaults to
+    /// zero.
+    pub fn batch_window(mut self, batch_window: Duration) -> Self {
+        self.batch_window = batch_window;
+        self
+    }
+
+    /// Sets the fee limits and resubmission policy of settlement and integrity transactions.
+    pub fn fee_config(mut self, fee_config: FeeConfig) -> Self {
+        self.fee_config = fee_config;
+        self
+    }
+
+    /// Sets the limits on the overall spending of the settlement account.
+    pub fn budget(mut self, budget: SettlementBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Simulates the integrity and settlement transactions of each block instead of sending them.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Adds labels to all metrics reported by the backend, e.g. for telling shards apart in
+    /// sharding mode.
+    pub fn metrics_labels(mut self, metrics_labels: Vec<Label>) -> Self {
+        self.metrics_labels = metrics_labels;
+        self
+    }
 }
 
 impl<DB> SettlementBackendBuilder for PiltoverSettlementBackendBuilder<DB>
@@ -408,6 +1382,10 @@ where
     type Backend = PiltoverSettlementBackend<DB>;
 
     async fn build(self) -> Result<Self::Backend> {
+        if self.batch_size == 0 {
+            anyhow::bail!("`batch_size` must be at least 1");
+        }
+
         let provider = Arc::new(JsonRpcClient::new(HttpTransport::new(self.rpc_url)));
         let chain_id = provider.chain_id().await?;
 
@@ -422,15 +1400,26 @@ where
 
         Ok(PiltoverSettlementBackend {
             provider,
-            account,
+            sender: TransactionSender::new(
+                account,
+                SigningKey::from_secret_scalar(self.account_private_key),
+                self.fee_config,
+            ),
+            account_address: self.account_address,
+            budget: self.budget,
             fact_registration: if self.skip_fact_registration {
                 FactRegistrationConfig::Skipped
             } else {
-                FactRegistrationConfig::Integrity(
-                    self.integrity_address
-                        .ok_or_else(|| anyhow::anyhow!("`integrity_address` not set"))?,
-                )
+                let integrity_address = self
+                    .integrity_address
+                    .ok_or_else(|| anyhow::anyhow!("`integrity_address` not set"))?;
+                if self.fact_registered_by_prover {
+                    FactRegistrationConfig::Prover(integrity_address)
+                } else {
+                    FactRegistrationConfig::Integrity(integrity_address)
+                }
             },
+            fact_timeout: self.fact_timeout,
             piltover_address: self.piltover_address,
             da_channel: self
                 .da_channel
@@ -440,6 +1429,11 @@ where
                 .ok_or_else(|| anyhow::anyhow!("`cursor_channel` not set"))?,
             finish_handle: FinishHandle::new(),
             db: self.db,
+            verify_proofs_locally: self.verify_proofs_locally,
+            dry_run: self.dry_run,
+            batch_size: self.batch_size,
+            batch_window: self.batch_window,
+            metrics_labels: self.metrics_labels,
         })
     }
 
@@ -476,3 +1470,66 @@ where
         tokio::spawn(self.run());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::{SqliteDb, Step};
+
+    use super::*;
+
+    async fn block_with_bridge_proof() -> SqliteDb {
+        let db = SqliteDb::in_memory().await.unwrap();
+        db.initialize_block(1).await.unwrap();
+        db.add_proof(1, b"snos_proof".to_vec(), Step::Snos)
+            .await
+            .unwrap();
+        db.add_proof(1, b"bridge_proof".to_vec(), Step::Bridge)
+            .await
+            .unwrap();
+        db.set_status(1, BlockStatus::BridgeProofGenerated.to_string())
+            .await
+            .unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn test_local_verification_accepted() {
+        let db = block_with_bridge_proof().await;
+
+        let verified = VerifiedProof {
+            program_hash: Felt::from(0x1234),
+            output_hash: Felt::from(0x5678),
+        };
+        assert!(handle_local_verification(&db, 1, Ok(verified), Duration::ZERO).await);
+
+        assert_eq!(
+            db.get_status(1).await.unwrap(),
+            BlockStatus::BridgeProofGenerated
+        );
+        assert!(db.get_proof(1, Step::Bridge).await.is_ok());
+        assert!(db.get_failed_blocks().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_local_verification_rejected() {
+        let db = block_with_bridge_proof().await;
+
+        let result = Err(anyhow::anyhow!("proof verification failed"));
+        assert!(!handle_local_verification(&db, 1, result, Duration::ZERO).await);
+
+        // The SNOS proof is kept for the layout bridge proof to be generated again.
+        assert_eq!(
+            db.get_status(1).await.unwrap(),
+            BlockStatus::SnosProofGenerated
+        );
+        assert!(db.get_proof(1, Step::Snos).await.is_ok());
+        assert!(db.get_proof(1, Step::Bridge).await.is_err());
+        assert_eq!(
+            db.get_failed_blocks().await.unwrap(),
+            vec![(
+                1,
+                "invalid layout bridge proof: proof verification failed".to_string()
+            )]
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use anyhow::Result;
+
 use crate::storage::{BlockWithDa, ChainHead, StorageBackend};
 
 /// An entirely in-memory storage backend useful for development and testing purposes.
@@ -13,14 +15,15 @@ impl InMemoryStorageBackend {
 }
 
 impl StorageBackend for InMemoryStorageBackend {
-    async fn get_chain_head(&self) -> ChainHead {
-        match self.last_block {
+    async fn get_chain_head(&self) -> Result<ChainHead> {
+        Ok(match self.last_block {
             Some(last_block) => ChainHead::Block(last_block),
             None => ChainHead::Genesis,
-        }
+        })
     }
 
-    async fn set_chain_head(&mut self, block: BlockWithDa) {
+    async fn set_chain_head(&mut self, block: BlockWithDa) -> Result<()> {
         self.last_block = Some(block);
+        Ok(())
     }
 }
@@ -1,5 +1,7 @@
 use crate::data_availability::DataAvailabilityPointer;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use starknet_types_core::felt::Felt;
 use std::future::Future;
 
 mod in_memory;
@@ -9,9 +11,9 @@ mod sql_lite;
 pub use sql_lite::SqliteDb;
 
 pub trait StorageBackend {
-    fn get_chain_head(&self) -> impl Future<Output = ChainHead>;
+    fn get_chain_head(&self) -> impl Future<Output = Result<ChainHead>> + Send;
 
-    fn set_chain_head(&mut self, block: BlockWithDa) -> impl Future<Output = ()> + Send;
+    fn set_chain_head(&mut self, block: BlockWithDa) -> impl Future<Output = Result<()>> + Send;
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -36,7 +38,8 @@ pub enum Query {
     BridgeTrace,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum BlockStatus {
     Mined,
     SnosPieGenerated,
@@ -88,6 +91,73 @@ impl From<&str> for BlockStatus {
     }
 }
 
+/// A block tracked in storage along with the IDs of the prover queries submitted for it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BlockRecord {
+    pub block_number: u32,
+    pub status: BlockStatus,
+    pub snos_proof_query_id: Option<String>,
+    pub trace_gen_query_id: Option<String>,
+    pub bridge_proof_query_id: Option<String>,
+    /// Reason of the failure of blocks with the `Failed` status.
+    pub failure_reason: Option<String>,
+}
+
+/// The on-chain verification of a layout bridge proof on integrity, which is split over multiple
+/// transactions sent one after the other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityJob {
+    pub job_id: Felt,
+    pub layout: Felt,
+    pub hasher: Felt,
+    pub stone_version: Felt,
+    pub memory_verification: Felt,
+    /// Number of transactions already confirmed, in the order they are sent.
+    pub confirmed_chunks: u32,
+}
+
+/// Kind of transaction sent by the settlement backend for a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettlementCostKind {
+    /// Verification of the layout bridge proof on integrity.
+    Integrity,
+    /// `update_state` call on piltover.
+    Settlement,
+}
+
+impl std::fmt::Display for SettlementCostKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettlementCostKind::Integrity => write!(f, "integrity"),
+            SettlementCostKind::Settlement => write!(f, "settlement"),
+        }
+    }
+}
+
+impl From<&str> for SettlementCostKind {
+    fn from(s: &str) -> Self {
+        match s {
+            "integrity" => SettlementCostKind::Integrity,
+            "settlement" => SettlementCostKind::Settlement,
+            _ => panic!("Invalid settlement cost kind"),
+        }
+    }
+}
+
+/// The fee paid for a block by a confirmed settlement-side transaction. Transactions settling
+/// multiple blocks are split evenly between them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettlementCost {
+    pub block_number: u32,
+    pub kind: SettlementCostKind,
+    pub transaction_hash: Felt,
+    /// Fee in FRI.
+    pub fee: Felt,
+    /// Unix timestamp in seconds at which the transaction was confirmed.
+    pub timestamp: u64,
+}
+
 pub trait PersistantStorage {
     fn initialize_block(&self, block_number: u32) -> impl Future<Output = Result<()>> + Send;
 
@@ -119,6 +189,13 @@ pub trait PersistantStorage {
         step: Step,
     ) -> impl Future<Output = Result<Vec<u8>>> + Send;
 
+    /// Gets the proof of the block for `step`, or `None` if the block has no such proof.
+    fn find_proof(
+        &self,
+        block_number: u32,
+        step: Step,
+    ) -> impl Future<Output = Result<Option<Vec<u8>>>> + Send;
+
     fn add_query_id(
         &self,
         block_number: u32,
@@ -142,6 +219,95 @@ pub trait PersistantStorage {
 
     fn get_first_db_block(&self) -> impl Future<Output = Result<u32>> + Send;
 
+    fn get_block_status_counts(
+        &self,
+    ) -> impl Future<Output = Result<Vec<(BlockStatus, u32)>>> + Send;
+
+    /// Lists all blocks in storage ordered by block number.
+    fn list_blocks(&self) -> impl Future<Output = Result<Vec<BlockRecord>>> + Send;
+
+    /// Removes the artifacts produced after `status` so that the block is processed again from
+    /// that stage.
+    ///
+    /// Only `Mined`, `SnosPieGenerated`, `SnosProofGenerated` and `BridgeProofGenerated` are valid
+    /// stages to reset to.
+    fn reset_block(
+        &self,
+        block_number: u32,
+        status: BlockStatus,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Queues the block for being picked up again by the block ingestor, by marking its latest
+    /// failure as unhandled, or adding a new one with `reason` if it never failed.
+    fn requeue_block(
+        &self,
+        block_number: u32,
+        reason: String,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Deletes the block along with all its artifacts and failure history.
+    fn forget_block(&self, block_number: u32) -> impl Future<Output = Result<()>> + Send;
+
+    fn get_integrity_job(
+        &self,
+        block_number: u32,
+    ) -> impl Future<Output = Result<Option<IntegrityJob>>> + Send;
+
+    /// Stores a new integrity job for the block, replacing any previous one.
+    fn add_integrity_job(
+        &self,
+        block_number: u32,
+        job: IntegrityJob,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn set_integrity_job_progress(
+        &self,
+        block_number: u32,
+        confirmed_chunks: u32,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Records the fee paid for a block. Costs are kept after the block is removed.
+    fn add_settlement_cost(&self, cost: SettlementCost) -> impl Future<Output = Result<()>> + Send;
+
+    /// Lists the costs recorded at or after the `since` Unix timestamp, in the order they were
+    /// recorded.
+    fn list_settlement_costs(
+        &self,
+        since: u64,
+    ) -> impl Future<Output = Result<Vec<SettlementCost>>> + Send;
+
+    /// Sums the fees of the given kind recorded for a block, in FRI.
+    fn get_block_fee(
+        &self,
+        block_number: u32,
+        kind: SettlementCostKind,
+    ) -> impl Future<Output = Result<Felt>> + Send;
+
+    /// Records the rollup hash of the block, as of when its PIE is generated.
+    fn set_block_hash(
+        &self,
+        block_number: u32,
+        block_hash: Felt,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Adds the SNOS PIE of a block whose PIE generation started with `block_hash` recorded, only
+    /// if the block is still waiting for it with the same recorded hash. Returns whether the PIE
+    /// was added, which it's not if the block has been reset in the meantime, e.g. because of a
+    /// reorg.
+    fn add_snos_pie_for_block_hash(
+        &self,
+        block_number: u32,
+        pie: Vec<u8>,
+        block_hash: Felt,
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Lists the recorded hashes of unsettled blocks in storage from `from_block`, ordered by
+    /// block number.
+    fn list_block_hashes(
+        &self,
+        from_block: u32,
+    ) -> impl Future<Output = Result<Vec<(u32, Felt)>>> + Send;
+
     fn add_failed_block(
         &self,
         block_number: u32,
@@ -150,6 +316,21 @@ pub trait PersistantStorage {
 
     fn get_failed_blocks(&self) -> impl Future<Output = Result<Vec<(u32, String)>>> + Send;
 
+    /// Counts the failures ever recorded for the block whose reason starts with `reason_prefix`.
+    fn count_failures(
+        &self,
+        block_number: u32,
+        reason_prefix: &str,
+    ) -> impl Future<Output = Result<u32>> + Send;
+
+    /// Marks the block as failed for good with `reason`. Unlike [`Self::add_failed_block`], the
+    /// block keeps its artifacts and is not retried by the block ingestor, until it's reset.
+    fn fail_block(
+        &self,
+        block_number: u32,
+        reason: String,
+    ) -> impl Future<Output = Result<()>> + Send;
+
     fn mark_failed_blocks_as_handled(
         &self,
         block_id: &[u32],
@@ -0,0 +1,104 @@
+use super::SqliteDb;
+use crate::data_availability::DataAvailabilityPointer;
+use crate::storage::{BlockWithDa, ChainHead, StorageBackend};
+use sqlx::query;
+use sqlx::Row;
+
+impl StorageBackend for SqliteDb {
+    async fn get_chain_head(&self) -> Result<ChainHead, anyhow::Error> {
+        let row = query(
+            "SELECT block_id, da_height, da_commitment FROM chain_heads \
+            ORDER BY block_id DESC LIMIT 1;",
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let row = match row {
+            Some(row) => row,
+            None => return Ok(ChainHead::Genesis),
+        };
+
+        let height: u64 = row.try_get("block_id")?;
+        let da_height: u64 = row.try_get("da_height")?;
+        let da_commitment: Vec<u8> = row.try_get("da_commitment")?;
+        let commitment: [u8; 32] = da_commitment
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("invalid DA commitment length for block {}", height))?;
+
+        Ok(ChainHead::Block(BlockWithDa {
+            height,
+            da_pointer: DataAvailabilityPointer {
+                height: da_height,
+                commitment,
+            },
+        }))
+    }
+
+    async fn set_chain_head(&mut self, block: BlockWithDa) -> Result<(), anyhow::Error> {
+        // `sqlx` doesn't support encoding `u64` for SQLite, so heights are stored as `i64`.
+        let height: i64 = block.height.try_into()?;
+        let da_height: i64 = block.da_pointer.height.try_into()?;
+
+        let mut tx = self.pool.begin().await?;
+
+        query(
+            "INSERT OR REPLACE INTO chain_heads (block_id, da_height, da_commitment) \
+            VALUES (?1, ?2, ?3);",
+        )
+        .bind(height)
+        .bind(da_height)
+        .bind(block.da_pointer.commitment.to_vec())
+        .execute(&mut *tx)
+        .await?;
+
+        // Only the latest head is ever read. Older entries are pruned to keep the table small.
+        query("DELETE FROM chain_heads WHERE block_id < ?1;")
+            .bind(height)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::sql_lite::IN_MEMORY_DB;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_chain_head_genesis() {
+        let db = SqliteDb::new(IN_MEMORY_DB).await.unwrap();
+
+        let chain_head = db.get_chain_head().await.unwrap();
+        assert_eq!(chain_head, ChainHead::Genesis);
+    }
+
+    #[tokio::test]
+    async fn test_set_and_get_chain_head() {
+        let mut db = SqliteDb::new(IN_MEMORY_DB).await.unwrap();
+
+        let first = BlockWithDa {
+            height: 1,
+            da_pointer: DataAvailabilityPointer {
+                height: 100,
+                commitment: [1; 32],
+            },
+        };
+        let second = BlockWithDa {
+            height: 2,
+            da_pointer: DataAvailabilityPointer {
+                height: 101,
+                commitment: [2; 32],
+            },
+        };
+
+        db.set_chain_head(first).await.unwrap();
+        assert_eq!(db.get_chain_head().await.unwrap(), ChainHead::Block(first));
+
+        db.set_chain_head(second).await.unwrap();
+        assert_eq!(db.get_chain_head().await.unwrap(), ChainHead::Block(second));
+    }
+}
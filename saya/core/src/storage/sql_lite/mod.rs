@@ -5,15 +5,28 @@ use std::time::Duration;
 use anyhow::Error;
 use log::trace;
 use sqlx::query;
-use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
 use sqlx::Pool;
 use sqlx::Sqlite;
 
+mod chain_head;
 mod storage;
 mod utils;
 
 const IN_MEMORY_DB: &str = ":memory:";
 
+/// Tables of databases created by the first releases, which any database can be read from.
+const BASELINE_TABLES: &[&str] = &["blocks", "pies", "proofs", "job_ids", "failed_blocks"];
+
+/// Tables added since the first releases, which are only created when opening a database for
+/// writing.
+const NEWER_TABLES: &[&str] = &[
+    "chain_heads",
+    "integrity_jobs",
+    "settlement_costs",
+    "block_hashes",
+];
+
 #[derive(Clone)]
 pub struct SqliteDb {
     pub(crate) pool: Pool<Sqlite>,
@@ -34,13 +47,24 @@ impl SqliteDb {
             trace!(database_path:% = path; "Database file found.");
         }
 
-        let pool = SqlitePoolOptions::new()
+        let pool_options = if path == IN_MEMORY_DB {
+            // Every connection to `:memory:` opens its own database, which is dropped along with
+            // the connection. A single connection is kept open for the lifetime of the pool.
+            SqlitePoolOptions::new()
+                .max_connections(1)
+                .min_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+        } else {
+            SqlitePoolOptions::new().max_connections(50)
+        };
+        let pool = pool_options
             .acquire_timeout(Duration::from_secs(10))
-            .max_connections(50)
             .connect(&format!("sqlite:{}", path))
             .await?;
 
-        let table_exists = Self::check_tables_exist(&pool).await?;
+        let table_exists = Self::check_tables_exist(&pool, BASELINE_TABLES).await?
+            && Self::check_tables_exist(&pool, NEWER_TABLES).await?;
 
         if !table_exists || !Self::check_columns(&pool).await? {
             trace!("Creating or updating tables...");
@@ -49,12 +73,42 @@ impl SqliteDb {
             Self::create_pies_table(&pool).await?;
             Self::create_job_id_table(&pool).await?;
             Self::create_failed_blocks_table(&pool).await?;
+            Self::create_chain_heads_table(&pool).await?;
+            Self::create_integrity_jobs_table(&pool).await?;
+            Self::create_settlement_costs_table(&pool).await?;
+            Self::create_block_hashes_table(&pool).await?;
         } else {
             trace!("Table 'blocks' with correct structure found.");
         }
         Ok(Self { pool })
     }
 
+    /// Creates a temporary database that only lives in memory.
+    pub async fn in_memory() -> Result<Self, Error> {
+        Self::new(IN_MEMORY_DB).await
+    }
+
+    /// Opens an existing database without write access, e.g. for inspecting it while another
+    /// process is running the pipeline on it.
+    ///
+    /// Databases written by previous releases may lack the tables added since, in which case
+    /// reading from them returns nothing.
+    pub async fn open_read_only(path: &str) -> Result<Self, Error> {
+        if !Path::new(path).try_exists()? {
+            anyhow::bail!("database file not found: {}", path);
+        }
+
+        let pool = SqlitePoolOptions::new()
+            .acquire_timeout(Duration::from_secs(10))
+            .connect_with(SqliteConnectOptions::new().filename(path).read_only(true))
+            .await?;
+
+        if !Self::check_tables_exist(&pool, BASELINE_TABLES).await? {
+            anyhow::bail!("database is not initialized: {}", path);
+        }
+        Ok(Self { pool })
+    }
+
     pub async fn create_block_table(pool: &Pool<Sqlite>) -> Result<(), Error> {
         query(
             r#"
@@ -144,4 +198,72 @@ impl SqliteDb {
         .await?;
         Ok(())
     }
+
+    pub async fn create_chain_heads_table(pool: &Pool<Sqlite>) -> Result<(), Error> {
+        query(
+            r#"
+            CREATE TABLE IF NOT EXISTS chain_heads (
+                block_id INTEGER PRIMARY KEY,
+                da_height INTEGER NOT NULL,
+                da_commitment BLOB NOT NULL
+            );
+            "#,
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn create_integrity_jobs_table(pool: &Pool<Sqlite>) -> Result<(), Error> {
+        // Felts are stored as hex strings.
+        query(
+            r#"
+            CREATE TABLE IF NOT EXISTS integrity_jobs (
+                block_id INTEGER PRIMARY KEY REFERENCES blocks(block_id) ON DELETE CASCADE,
+                job_id TEXT NOT NULL,
+                layout TEXT NOT NULL,
+                hasher TEXT NOT NULL,
+                stone_version TEXT NOT NULL,
+                memory_verification TEXT NOT NULL,
+                confirmed_chunks INTEGER NOT NULL DEFAULT 0
+            );
+            "#,
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn create_settlement_costs_table(pool: &Pool<Sqlite>) -> Result<(), Error> {
+        // Not referencing `blocks`, since costs are kept after blocks are settled and removed.
+        query(
+            r#"
+            CREATE TABLE IF NOT EXISTS settlement_costs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                block_id INTEGER NOT NULL,
+                kind TEXT NOT NULL CHECK (kind IN ('integrity', 'settlement')),
+                transaction_hash TEXT NOT NULL,
+                fee TEXT NOT NULL,
+                timestamp INTEGER NOT NULL
+            );
+            "#,
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn create_block_hashes_table(pool: &Pool<Sqlite>) -> Result<(), Error> {
+        query(
+            r#"
+            CREATE TABLE IF NOT EXISTS block_hashes (
+                block_id INTEGER PRIMARY KEY REFERENCES blocks(block_id) ON DELETE CASCADE,
+                block_hash TEXT NOT NULL
+            );
+            "#,
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
 }
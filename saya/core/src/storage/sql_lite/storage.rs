@@ -1,8 +1,12 @@
 use super::SqliteDb;
-use crate::storage::{BlockStatus, Query};
+use crate::storage::{
+    BlockRecord, BlockStatus, IntegrityJob, Query, SettlementCost, SettlementCostKind,
+};
 use crate::storage::{PersistantStorage, Step};
 use sqlx::query;
+use sqlx::sqlite::SqliteRow;
 use sqlx::Row;
+use starknet_types_core::felt::Felt;
 
 impl PersistantStorage for SqliteDb {
     async fn add_pie(
@@ -130,6 +134,31 @@ impl PersistantStorage for SqliteDb {
         Ok(proof)
     }
 
+    async fn find_proof(
+        &self,
+        block_number: u32,
+        step: Step,
+    ) -> Result<Option<Vec<u8>>, anyhow::Error> {
+        let column = match step {
+            Step::Snos => "snos_proof",
+            Step::Bridge => "bridge_proof",
+        };
+
+        let row = query(&format!(
+            "SELECT {} FROM proofs WHERE block_id = ?1",
+            column
+        ))
+        .bind(block_number)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let proof: Option<Vec<u8>> = match row {
+            Some(row) => row.try_get(0)?,
+            None => None,
+        };
+        Ok(proof.filter(|proof| !proof.is_empty()))
+    }
+
     async fn add_query_id(
         &self,
         block_number: u32,
@@ -246,6 +275,357 @@ impl PersistantStorage for SqliteDb {
         Ok(first_block)
     }
 
+    async fn get_block_status_counts(&self) -> anyhow::Result<Vec<(BlockStatus, u32)>> {
+        let rows = query("SELECT status, COUNT(*) FROM blocks GROUP BY status")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut counts = Vec::with_capacity(rows.len());
+        for row in rows {
+            let status: String = row.try_get(0)?;
+            let count: u32 = row.try_get(1)?;
+            counts.push((BlockStatus::from(status.as_str()), count));
+        }
+        Ok(counts)
+    }
+
+    async fn list_blocks(&self) -> anyhow::Result<Vec<BlockRecord>> {
+        let rows = query(
+            "SELECT blocks.block_id, blocks.status, MAX(job_ids.snos_proof_query_id), \
+            MAX(job_ids.trace_gen_query_id), MAX(job_ids.bridge_proof_query_id), \
+            CASE WHEN blocks.status = 'failed' THEN \
+            (SELECT failure_reason FROM failed_blocks WHERE failed_blocks.block_id = blocks.block_id \
+            ORDER BY failed_blocks.id DESC LIMIT 1) END \
+            FROM blocks LEFT JOIN job_ids ON job_ids.block_id = blocks.block_id \
+            GROUP BY blocks.block_id ORDER BY blocks.block_id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut blocks = Vec::with_capacity(rows.len());
+        for row in rows {
+            let status: String = row.try_get(1)?;
+            blocks.push(BlockRecord {
+                block_number: row.try_get(0)?,
+                status: BlockStatus::from(status.as_str()),
+                snos_proof_query_id: row.try_get(2)?,
+                trace_gen_query_id: row.try_get(3)?,
+                bridge_proof_query_id: row.try_get(4)?,
+                failure_reason: row.try_get(5)?,
+            });
+        }
+        Ok(blocks)
+    }
+
+    async fn reset_block(&self, block_number: u32, status: BlockStatus) -> anyhow::Result<()> {
+        // Artifacts to clear for each stage, in pipeline order.
+        let (pie_columns, proof_columns, query_columns): (&[&str], &[&str], &[&str]) = match status
+        {
+            BlockStatus::Mined => (
+                &["snos_pie", "bridge_pie"],
+                &["snos_proof", "bridge_proof"],
+                &[
+                    "snos_proof_query_id",
+                    "trace_gen_query_id",
+                    "bridge_proof_query_id",
+                ],
+            ),
+            BlockStatus::SnosPieGenerated => (
+                &["bridge_pie"],
+                &["snos_proof", "bridge_proof"],
+                &[
+                    "snos_proof_query_id",
+                    "trace_gen_query_id",
+                    "bridge_proof_query_id",
+                ],
+            ),
+            BlockStatus::SnosProofGenerated => (
+                &["bridge_pie"],
+                &["bridge_proof"],
+                &["trace_gen_query_id", "bridge_proof_query_id"],
+            ),
+            BlockStatus::BridgeProofGenerated => (&[], &[], &[]),
+            _ => anyhow::bail!("cannot reset block to status `{}`", status),
+        };
+
+        let mut tx = self.pool.begin().await?;
+
+        let row = query("SELECT COUNT(*) FROM blocks WHERE block_id = ?1")
+            .bind(block_number)
+            .fetch_one(&mut *tx)
+            .await?;
+        let count: u32 = row.try_get(0)?;
+        if count == 0 {
+            anyhow::bail!("block {} not found", block_number);
+        }
+
+        for (table, columns) in [
+            ("pies", pie_columns),
+            ("proofs", proof_columns),
+            ("job_ids", query_columns),
+        ] {
+            for column in columns {
+                query(&format!(
+                    "UPDATE {} SET {} = NULL WHERE block_id = ?1",
+                    table, column
+                ))
+                .bind(block_number)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        // A new layout bridge proof can't be verified as part of a previous integrity job.
+        if proof_columns.contains(&"bridge_proof") {
+            query("DELETE FROM integrity_jobs WHERE block_id = ?1")
+                .bind(block_number)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        query("UPDATE blocks SET status = ?1 WHERE block_id = ?2")
+            .bind(status.to_string())
+            .bind(block_number)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn requeue_block(&self, block_number: u32, reason: String) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = query(
+            "UPDATE failed_blocks SET handled = FALSE WHERE id = \
+            (SELECT MAX(id) FROM failed_blocks WHERE block_id = ?1)",
+        )
+        .bind(block_number)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            query("INSERT INTO failed_blocks (block_id, failure_reason) VALUES (?1, ?2)")
+                .bind(block_number)
+                .bind(reason)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn forget_block(&self, block_number: u32) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        // Artifacts are deleted through `ON DELETE CASCADE`.
+        query("DELETE FROM blocks WHERE block_id = ?1")
+            .bind(block_number)
+            .execute(&mut *tx)
+            .await?;
+        query("DELETE FROM failed_blocks WHERE block_id = ?1")
+            .bind(block_number)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_integrity_job(&self, block_number: u32) -> anyhow::Result<Option<IntegrityJob>> {
+        let row = query(
+            "SELECT job_id, layout, hasher, stone_version, memory_verification, confirmed_chunks \
+            FROM integrity_jobs WHERE block_id = ?1",
+        )
+        .bind(block_number)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let row = match row {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+
+        Ok(Some(IntegrityJob {
+            job_id: get_felt(&row, "job_id")?,
+            layout: get_felt(&row, "layout")?,
+            hasher: get_felt(&row, "hasher")?,
+            stone_version: get_felt(&row, "stone_version")?,
+            memory_verification: get_felt(&row, "memory_verification")?,
+            confirmed_chunks: row.try_get("confirmed_chunks")?,
+        }))
+    }
+
+    async fn add_integrity_job(&self, block_number: u32, job: IntegrityJob) -> anyhow::Result<()> {
+        query(
+            "INSERT OR REPLACE INTO integrity_jobs \
+            (block_id, job_id, layout, hasher, stone_version, memory_verification, confirmed_chunks) \
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )
+        .bind(block_number)
+        .bind(format!("{:#x}", job.job_id))
+        .bind(format!("{:#x}", job.layout))
+        .bind(format!("{:#x}", job.hasher))
+        .bind(format!("{:#x}", job.stone_version))
+        .bind(format!("{:#x}", job.memory_verification))
+        .bind(job.confirmed_chunks)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn set_integrity_job_progress(
+        &self,
+        block_number: u32,
+        confirmed_chunks: u32,
+    ) -> anyhow::Result<()> {
+        let result = query("UPDATE integrity_jobs SET confirmed_chunks = ?1 WHERE block_id = ?2")
+            .bind(confirmed_chunks)
+            .bind(block_number)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            anyhow::bail!("no integrity job for block {}", block_number);
+        }
+        Ok(())
+    }
+
+    async fn add_settlement_cost(&self, cost: SettlementCost) -> anyhow::Result<()> {
+        // `sqlx` doesn't support encoding `u64` for SQLite.
+        let timestamp: i64 = cost.timestamp.try_into()?;
+
+        query(
+            "INSERT INTO settlement_costs (block_id, kind, transaction_hash, fee, timestamp) \
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+        )
+        .bind(cost.block_number)
+        .bind(cost.kind.to_string())
+        .bind(format!("{:#x}", cost.transaction_hash))
+        .bind(format!("{:#x}", cost.fee))
+        .bind(timestamp)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn list_settlement_costs(&self, since: u64) -> anyhow::Result<Vec<SettlementCost>> {
+        // Missing from databases of previous releases opened in read-only mode.
+        if !Self::check_table_exists(&self.pool, "settlement_costs").await? {
+            return Ok(Vec::new());
+        }
+
+        let since: i64 = since.try_into()?;
+
+        let rows = query(
+            "SELECT block_id, kind, transaction_hash, fee, timestamp FROM settlement_costs \
+            WHERE timestamp >= ?1 ORDER BY id",
+        )
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let kind: String = row.try_get("kind")?;
+                let timestamp: i64 = row.try_get("timestamp")?;
+                Ok(SettlementCost {
+                    block_number: row.try_get("block_id")?,
+                    kind: kind.as_str().into(),
+                    transaction_hash: get_felt(row, "transaction_hash")?,
+                    fee: get_felt(row, "fee")?,
+                    timestamp: timestamp.try_into()?,
+                })
+            })
+            .collect()
+    }
+
+    async fn get_block_fee(
+        &self,
+        block_number: u32,
+        kind: SettlementCostKind,
+    ) -> anyhow::Result<Felt> {
+        let rows = query("SELECT fee FROM settlement_costs WHERE block_id = ?1 AND kind = ?2")
+            .bind(block_number)
+            .bind(kind.to_string())
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter()
+            .try_fold(Felt::ZERO, |total, row| Ok(total + get_felt(row, "fee")?))
+    }
+
+    async fn set_block_hash(&self, block_number: u32, block_hash: Felt) -> anyhow::Result<()> {
+        query("INSERT OR REPLACE INTO block_hashes (block_id, block_hash) VALUES (?1, ?2)")
+            .bind(block_number)
+            .bind(format!("{:#x}", block_hash))
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn add_snos_pie_for_block_hash(
+        &self,
+        block_number: u32,
+        pie: Vec<u8>,
+        block_hash: Felt,
+    ) -> anyhow::Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        // Checked in the same transaction as adding the PIE, for a concurrent reset not to be
+        // overwritten with the artifacts of the previous generation.
+        let result = query(
+            "UPDATE blocks SET status = 'snos_pie_generated' WHERE block_id = ?1 \
+            AND status = 'mined' AND EXISTS \
+            (SELECT 1 FROM block_hashes WHERE block_id = ?1 AND block_hash = ?2)",
+        )
+        .bind(block_number)
+        .bind(format!("{:#x}", block_hash))
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            tx.rollback().await?;
+            return Ok(false);
+        }
+
+        query(
+            "INSERT OR IGNORE INTO pies (block_id, snos_pie, bridge_pie) VALUES (?, NULL, NULL);",
+        )
+        .bind(block_number)
+        .execute(&mut *tx)
+        .await?;
+        query("UPDATE pies SET snos_pie = ? WHERE block_id = ?;")
+            .bind(pie)
+            .bind(block_number)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn list_block_hashes(&self, from_block: u32) -> anyhow::Result<Vec<(u32, Felt)>> {
+        // Missing from databases of previous releases opened in read-only mode.
+        if !Self::check_table_exists(&self.pool, "block_hashes").await? {
+            return Ok(Vec::new());
+        }
+
+        let rows = query(
+            "SELECT block_hashes.block_id, block_hashes.block_hash FROM block_hashes \
+            JOIN blocks ON blocks.block_id = block_hashes.block_id \
+            WHERE block_hashes.block_id >= ?1 AND blocks.status != ?2 \
+            ORDER BY block_hashes.block_id",
+        )
+        .bind(from_block)
+        .bind(BlockStatus::Settled.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| Ok((row.try_get("block_id")?, get_felt(row, "block_hash")?)))
+            .collect()
+    }
+
     async fn add_failed_block(
         &self,
         block_number: u32,
@@ -288,6 +668,38 @@ impl PersistantStorage for SqliteDb {
         Ok(failed_blocks)
     }
 
+    async fn count_failures(&self, block_number: u32, reason_prefix: &str) -> anyhow::Result<u32> {
+        let row = query(
+            "SELECT COUNT(*) FROM failed_blocks WHERE block_id = ?1 \
+            AND substr(failure_reason, 1, length(?2)) = ?2",
+        )
+        .bind(block_number)
+        .bind(reason_prefix)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.try_get(0)?)
+    }
+
+    async fn fail_block(&self, block_number: u32, reason: String) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        query("UPDATE blocks SET status = 'failed' WHERE block_id = ?1")
+            .bind(block_number)
+            .execute(&mut *tx)
+            .await?;
+        // Recorded as handled for the failure not to be retried by the block ingestor.
+        query(
+            "INSERT INTO failed_blocks (block_id, failure_reason, handled) VALUES (?1, ?2, TRUE)",
+        )
+        .bind(block_number)
+        .bind(reason)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
     async fn mark_failed_blocks_as_handled(&self, block_ids: &[u32]) -> anyhow::Result<()> {
         if block_ids.is_empty() {
             return Ok(()); // Nothing to update
@@ -307,9 +719,14 @@ impl PersistantStorage for SqliteDb {
     }
 }
 
+fn get_felt(row: &SqliteRow, column: &str) -> anyhow::Result<Felt> {
+    let value: String = row.try_get(column)?;
+    Felt::from_hex(&value).map_err(|_| anyhow::anyhow!("invalid felt in `{}`: {}", column, value))
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::storage::sql_lite::IN_MEMORY_DB;
+    use crate::storage::sql_lite::{IN_MEMORY_DB, NEWER_TABLES};
 
     use super::*;
 
@@ -414,6 +831,22 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_find_proof() {
+        let db = SqliteDb::new(IN_MEMORY_DB).await.unwrap();
+
+        assert_eq!(db.find_proof(1, Step::Snos).await.unwrap(), None);
+
+        db.initialize_block(1).await.unwrap();
+        db.add_proof(1, vec![1, 2, 3], Step::Snos).await.unwrap();
+
+        assert_eq!(
+            db.find_proof(1, Step::Snos).await.unwrap(),
+            Some(vec![1, 2, 3])
+        );
+        assert_eq!(db.find_proof(1, Step::Bridge).await.unwrap(), None);
+    }
+
     #[tokio::test]
     async fn test_add_and_get_query_id_for_multiple_blocks() {
         let db = SqliteDb::new(IN_MEMORY_DB).await.unwrap();
@@ -480,6 +913,227 @@ mod tests {
         assert_eq!(status, BlockStatus::BridgeProofGenerated);
     }
 
+    #[tokio::test]
+    async fn test_get_block_status_counts() {
+        let db = SqliteDb::new(IN_MEMORY_DB).await.unwrap();
+
+        db.initialize_block(1).await.unwrap();
+        db.initialize_block(2).await.unwrap();
+        db.initialize_block(3).await.unwrap();
+        db.set_status(3, "verified_proof".to_string())
+            .await
+            .unwrap();
+
+        let mut counts = db.get_block_status_counts().await.unwrap();
+        counts.sort_by_key(|(_, count)| *count);
+        assert_eq!(
+            counts,
+            vec![(BlockStatus::VerifiedProof, 1), (BlockStatus::Mined, 2)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reset_block_clears_later_artifacts() {
+        let db = SqliteDb::new(IN_MEMORY_DB).await.unwrap();
+
+        db.initialize_block(1).await.unwrap();
+        db.add_pie(1, vec![1, 2, 3], Step::Snos).await.unwrap();
+        db.add_query_id(1, "snos_123".to_string(), Query::SnosProof)
+            .await
+            .unwrap();
+        db.add_proof(1, vec![4, 5, 6], Step::Snos).await.unwrap();
+        db.add_query_id(1, "bridge_456".to_string(), Query::BridgeProof)
+            .await
+            .unwrap();
+        db.add_proof(1, vec![7, 8, 9], Step::Bridge).await.unwrap();
+
+        db.reset_block(1, BlockStatus::SnosProofGenerated)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.get_status(1).await.unwrap(),
+            BlockStatus::SnosProofGenerated
+        );
+        assert_eq!(db.get_proof(1, Step::Snos).await.unwrap(), vec![4, 5, 6]);
+        assert!(db.get_proof(1, Step::Bridge).await.is_err());
+        assert!(db.get_query_id(1, Query::BridgeProof).await.is_err());
+
+        let blocks = db.list_blocks().await.unwrap();
+        assert_eq!(
+            blocks,
+            vec![BlockRecord {
+                block_number: 1,
+                status: BlockStatus::SnosProofGenerated,
+                snos_proof_query_id: Some("snos_123".to_string()),
+                trace_gen_query_id: None,
+                bridge_proof_query_id: None,
+                failure_reason: None,
+            }]
+        );
+
+        assert!(db.reset_block(1, BlockStatus::Settled).await.is_err());
+        assert!(db.reset_block(2, BlockStatus::Mined).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_requeue_block() {
+        let db = SqliteDb::new(IN_MEMORY_DB).await.unwrap();
+
+        db.initialize_block(1).await.unwrap();
+        db.add_failed_block(1, "proof failed".to_string())
+            .await
+            .unwrap();
+        db.mark_failed_blocks_as_handled(&[1]).await.unwrap();
+        assert!(db.get_failed_blocks().await.unwrap().is_empty());
+
+        db.requeue_block(1, "requeued".to_string()).await.unwrap();
+        db.requeue_block(2, "requeued".to_string()).await.unwrap();
+
+        let failed_blocks = db.get_failed_blocks().await.unwrap();
+        assert_eq!(
+            failed_blocks,
+            vec![(1, "proof failed".to_string()), (2, "requeued".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forget_block() {
+        let db = SqliteDb::new(IN_MEMORY_DB).await.unwrap();
+
+        db.initialize_block(1).await.unwrap();
+        db.add_failed_block(1, "proof failed".to_string())
+            .await
+            .unwrap();
+        db.add_pie(1, vec![1, 2, 3], Step::Snos).await.unwrap();
+        db.add_query_id(1, "snos_123".to_string(), Query::SnosProof)
+            .await
+            .unwrap();
+        db.initialize_block(2).await.unwrap();
+
+        db.forget_block(1).await.unwrap();
+
+        assert!(db.get_status(1).await.is_err());
+        assert!(db.get_pie(1, Step::Snos).await.is_err());
+        assert!(db.get_query_id(1, Query::SnosProof).await.is_err());
+        assert!(db.get_failed_blocks().await.unwrap().is_empty());
+        assert_eq!(db.get_status(2).await.unwrap(), BlockStatus::Mined);
+    }
+
+    #[tokio::test]
+    async fn test_integrity_job_progress() {
+        let db = SqliteDb::new(IN_MEMORY_DB).await.unwrap();
+
+        db.initialize_block(1).await.unwrap();
+        assert_eq!(db.get_integrity_job(1).await.unwrap(), None);
+        assert!(db.set_integrity_job_progress(1, 1).await.is_err());
+
+        let job = IntegrityJob {
+            job_id: Felt::from(0x1234_u64),
+            layout: Felt::from(1_u64),
+            hasher: Felt::from(2_u64),
+            stone_version: Felt::from(3_u64),
+            memory_verification: Felt::from(4_u64),
+            confirmed_chunks: 0,
+        };
+        db.add_integrity_job(1, job.clone()).await.unwrap();
+        db.set_integrity_job_progress(1, 5).await.unwrap();
+        assert_eq!(
+            db.get_integrity_job(1).await.unwrap(),
+            Some(IntegrityJob {
+                confirmed_chunks: 5,
+                ..job.clone()
+            })
+        );
+
+        // The job is kept as long as the layout bridge proof is.
+        db.reset_block(1, BlockStatus::BridgeProofGenerated)
+            .await
+            .unwrap();
+        assert!(db.get_integrity_job(1).await.unwrap().is_some());
+        db.reset_block(1, BlockStatus::SnosProofGenerated)
+            .await
+            .unwrap();
+        assert_eq!(db.get_integrity_job(1).await.unwrap(), None);
+
+        db.add_integrity_job(1, job).await.unwrap();
+        db.remove_block(1).await.unwrap();
+        assert_eq!(db.get_integrity_job(1).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_settlement_costs_outlive_blocks() {
+        let db = SqliteDb::new(IN_MEMORY_DB).await.unwrap();
+
+        let integrity_cost = SettlementCost {
+            block_number: 1,
+            kind: SettlementCostKind::Integrity,
+            transaction_hash: Felt::from(0x11_u64),
+            fee: Felt::from(1_000_000_000_000_000_000_u128),
+            timestamp: 100,
+        };
+        let settlement_cost = SettlementCost {
+            block_number: 1,
+            kind: SettlementCostKind::Settlement,
+            transaction_hash: Felt::from(0x12_u64),
+            fee: Felt::from(500_u64),
+            timestamp: 200,
+        };
+
+        db.initialize_block(1).await.unwrap();
+        db.add_settlement_cost(integrity_cost.clone())
+            .await
+            .unwrap();
+        db.add_settlement_cost(settlement_cost.clone())
+            .await
+            .unwrap();
+        db.remove_block(1).await.unwrap();
+
+        assert_eq!(
+            db.list_settlement_costs(0).await.unwrap(),
+            vec![integrity_cost, settlement_cost.clone()]
+        );
+        assert_eq!(
+            db.list_settlement_costs(150).await.unwrap(),
+            vec![settlement_cost]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_block_fee() {
+        let db = SqliteDb::new(IN_MEMORY_DB).await.unwrap();
+
+        for (block_number, kind, fee) in [
+            (1, SettlementCostKind::Integrity, 100_u64),
+            (1, SettlementCostKind::Integrity, 20),
+            (1, SettlementCostKind::Settlement, 3),
+            (2, SettlementCostKind::Integrity, 4),
+        ] {
+            db.add_settlement_cost(SettlementCost {
+                block_number,
+                kind,
+                transaction_hash: Felt::from(fee),
+                fee: Felt::from(fee),
+                timestamp: 100,
+            })
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(
+            db.get_block_fee(1, SettlementCostKind::Integrity)
+                .await
+                .unwrap(),
+            Felt::from(120)
+        );
+        assert_eq!(
+            db.get_block_fee(3, SettlementCostKind::Integrity)
+                .await
+                .unwrap(),
+            Felt::ZERO
+        );
+    }
+
     #[tokio::test]
     async fn test_get_status_returns_error_for_missing_block() {
         let db = SqliteDb::new(IN_MEMORY_DB).await.unwrap();
@@ -529,4 +1183,174 @@ mod tests {
 
         assert_eq!(failed_blocks, vec![(1, "failed".to_string())]);
     }
+
+    #[tokio::test]
+    async fn test_add_snos_pie_for_block_hash() {
+        let db = SqliteDb::new(IN_MEMORY_DB).await.unwrap();
+
+        db.initialize_block(1).await.unwrap();
+        db.set_block_hash(1, Felt::from(0x1234)).await.unwrap();
+
+        // Reset by a reorg while generating the PIE.
+        db.add_failed_block(1, "reorg".to_string()).await.unwrap();
+        assert!(!db
+            .add_snos_pie_for_block_hash(1, vec![1, 2, 3], Felt::from(0x1234))
+            .await
+            .unwrap());
+        db.set_block_hash(1, Felt::from(0x5678)).await.unwrap();
+        assert!(!db
+            .add_snos_pie_for_block_hash(1, vec![1, 2, 3], Felt::from(0x1234))
+            .await
+            .unwrap());
+        assert!(db.get_pie(1, Step::Snos).await.is_err());
+        assert_eq!(db.get_status(1).await.unwrap(), BlockStatus::Mined);
+
+        assert!(db
+            .add_snos_pie_for_block_hash(1, vec![4, 5, 6], Felt::from(0x5678))
+            .await
+            .unwrap());
+        assert_eq!(db.get_pie(1, Step::Snos).await.unwrap(), vec![4, 5, 6]);
+        assert_eq!(
+            db.get_status(1).await.unwrap(),
+            BlockStatus::SnosPieGenerated
+        );
+
+        // Only the first of concurrent generations is kept.
+        assert!(!db
+            .add_snos_pie_for_block_hash(1, vec![7, 8, 9], Felt::from(0x5678))
+            .await
+            .unwrap());
+        assert_eq!(db.get_pie(1, Step::Snos).await.unwrap(), vec![4, 5, 6]);
+    }
+
+    #[tokio::test]
+    async fn test_count_failures() {
+        let db = SqliteDb::new(IN_MEMORY_DB).await.unwrap();
+
+        db.initialize_block(1).await.unwrap();
+        db.add_failed_block(1, "limit exceeded: timeout".to_string())
+            .await
+            .unwrap();
+        db.mark_failed_blocks_as_handled(&[1]).await.unwrap();
+        db.add_failed_block(1, "reorg".to_string()).await.unwrap();
+        db.add_failed_block(1, "limit exceeded: RSS".to_string())
+            .await
+            .unwrap();
+        db.add_failed_block(2, "limit exceeded: RSS".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(db.count_failures(1, "limit exceeded: ").await.unwrap(), 2);
+        assert_eq!(db.count_failures(1, "").await.unwrap(), 3);
+        assert_eq!(db.count_failures(3, "limit exceeded: ").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_fail_block() {
+        let db = SqliteDb::new(IN_MEMORY_DB).await.unwrap();
+
+        db.initialize_block(1).await.unwrap();
+        db.add_pie(1, vec![1, 2, 3], Step::Snos).await.unwrap();
+        db.fail_block(1, "invalid SNOS output".to_string())
+            .await
+            .unwrap();
+
+        // The block keeps its artifacts and is not retried.
+        assert_eq!(db.get_status(1).await.unwrap(), BlockStatus::Failed);
+        assert_eq!(db.get_pie(1, Step::Snos).await.unwrap(), vec![1, 2, 3]);
+        assert!(db.get_failed_blocks().await.unwrap().is_empty());
+        assert_eq!(
+            db.list_blocks().await.unwrap()[0].failure_reason,
+            Some("invalid SNOS output".to_string())
+        );
+
+        db.reset_block(1, BlockStatus::Mined).await.unwrap();
+        assert_eq!(db.list_blocks().await.unwrap()[0].failure_reason, None);
+    }
+
+    #[tokio::test]
+    async fn test_block_hashes_cleared_with_failed_block() {
+        let db = SqliteDb::new(IN_MEMORY_DB).await.unwrap();
+
+        db.initialize_block(1).await.unwrap();
+        db.initialize_block(2).await.unwrap();
+        db.set_block_hash(1, Felt::from(0x1234)).await.unwrap();
+        db.set_block_hash(2, Felt::from(0x5678)).await.unwrap();
+        db.set_block_hash(2, Felt::from(0x9abc)).await.unwrap();
+
+        assert_eq!(
+            db.list_block_hashes(0).await.unwrap(),
+            vec![(1, Felt::from(0x1234)), (2, Felt::from(0x9abc))]
+        );
+
+        db.add_failed_block(1, "reorg".to_string()).await.unwrap();
+
+        assert_eq!(
+            db.list_block_hashes(0).await.unwrap(),
+            vec![(2, Felt::from(0x9abc))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_block_hashes_of_recent_unsettled_blocks() {
+        let db = SqliteDb::new(IN_MEMORY_DB).await.unwrap();
+
+        for block_number in 1..=3 {
+            db.initialize_block(block_number).await.unwrap();
+            db.set_block_hash(block_number, Felt::from(block_number))
+                .await
+                .unwrap();
+        }
+        db.set_status(3, BlockStatus::Settled.to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.list_block_hashes(2).await.unwrap(),
+            vec![(2, Felt::from(2))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_open_read_only_without_newer_tables() {
+        let path = std::env::temp_dir().join(format!("saya_{}_read_only.db", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        // A database from a previous release, with only the baseline tables.
+        let db = SqliteDb::new(path).await.unwrap();
+        db.initialize_block(1).await.unwrap();
+        for table in NEWER_TABLES {
+            query(&format!("DROP TABLE {}", table))
+                .execute(&db.pool)
+                .await
+                .unwrap();
+        }
+        db.pool.close().await;
+
+        let db = SqliteDb::open_read_only(path).await.unwrap();
+        assert_eq!(db.list_blocks().await.unwrap().len(), 1);
+        assert!(db.list_settlement_costs(0).await.unwrap().is_empty());
+        assert!(db.list_block_hashes(0).await.unwrap().is_empty());
+
+        db.pool.close().await;
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_db_shared_by_concurrent_tasks() {
+        let db = SqliteDb::in_memory().await.unwrap();
+
+        // Each task would get its own empty database if the pool opened more connections.
+        let tasks = (1..=10)
+            .map(|block_number| {
+                let db = db.clone();
+                tokio::spawn(async move { db.initialize_block(block_number).await })
+            })
+            .collect::<Vec<_>>();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        assert_eq!(db.list_blocks().await.unwrap().len(), 10);
+    }
 }
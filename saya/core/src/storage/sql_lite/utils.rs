@@ -11,7 +11,19 @@ impl SqliteDb {
         let pies_table = Self::check_pies_table(pool).await?;
         let job_ids_table = Self::check_ids_table(pool).await?;
         let failed_blocks_table = Self::check_failed_blocks_table(pool).await?;
-        Ok(blocks_table && proofs_table && pies_table && job_ids_table && failed_blocks_table)
+        let chain_heads_table = Self::check_chain_heads_table(pool).await?;
+        let integrity_jobs_table = Self::check_integrity_jobs_table(pool).await?;
+        let settlement_costs_table = Self::check_settlement_costs_table(pool).await?;
+        let block_hashes_table = Self::check_block_hashes_table(pool).await?;
+        Ok(blocks_table
+            && proofs_table
+            && pies_table
+            && job_ids_table
+            && failed_blocks_table
+            && chain_heads_table
+            && integrity_jobs_table
+            && settlement_costs_table
+            && block_hashes_table)
     }
 
     /// Function to check if the blocks table has the correct columns
@@ -133,20 +145,134 @@ impl SqliteDb {
         Ok(has_id && has_block_id && has_failure_reason)
     }
 
+    /// Function to check if the chain_heads table has the correct columns
+    pub(crate) async fn check_chain_heads_table(pool: &Pool<Sqlite>) -> Result<bool, Error> {
+        let columns = sqlx::query("PRAGMA table_info(chain_heads);")
+            .fetch_all(pool)
+            .await?;
+        // Check if the table has the expected columns: block_id, da_height and da_commitment
+        let mut has_block_id = false;
+        let mut has_da_height = false;
+        let mut has_da_commitment = false;
+        for column in columns {
+            let name: String = column.get("name");
+            match name.as_str() {
+                "block_id" => has_block_id = true,
+                "da_height" => has_da_height = true,
+                "da_commitment" => has_da_commitment = true,
+                _ => {}
+            }
+        }
+        Ok(has_block_id && has_da_height && has_da_commitment)
+    }
+
+    /// Function to check if the integrity_jobs table has the correct columns
+    pub(crate) async fn check_integrity_jobs_table(pool: &Pool<Sqlite>) -> Result<bool, Error> {
+        let columns = sqlx::query("PRAGMA table_info(integrity_jobs);")
+            .fetch_all(pool)
+            .await?;
+        // Check if the table has the expected columns: block_id, job_id, verifier configuration
+        // and confirmed_chunks
+        let mut has_block_id = false;
+        let mut has_job_id = false;
+        let mut has_layout = false;
+        let mut has_hasher = false;
+        let mut has_stone_version = false;
+        let mut has_memory_verification = false;
+        let mut has_confirmed_chunks = false;
+        for column in columns {
+            let name: String = column.get("name");
+            match name.as_str() {
+                "block_id" => has_block_id = true,
+                "job_id" => has_job_id = true,
+                "layout" => has_layout = true,
+                "hasher" => has_hasher = true,
+                "stone_version" => has_stone_version = true,
+                "memory_verification" => has_memory_verification = true,
+                "confirmed_chunks" => has_confirmed_chunks = true,
+                _ => {}
+            }
+        }
+        Ok(has_block_id
+            && has_job_id
+            && has_layout
+            && has_hasher
+            && has_stone_version
+            && has_memory_verification
+            && has_confirmed_chunks)
+    }
+
+    /// Function to check if the settlement_costs table has the correct columns
+    pub(crate) async fn check_settlement_costs_table(pool: &Pool<Sqlite>) -> Result<bool, Error> {
+        let columns = sqlx::query("PRAGMA table_info(settlement_costs);")
+            .fetch_all(pool)
+            .await?;
+        // Check if the table has the expected columns: id, block_id, kind, transaction_hash, fee
+        // and timestamp
+        let mut has_id = false;
+        let mut has_block_id = false;
+        let mut has_kind = false;
+        let mut has_transaction_hash = false;
+        let mut has_fee = false;
+        let mut has_timestamp = false;
+        for column in columns {
+            let name: String = column.get("name");
+            match name.as_str() {
+                "id" => has_id = true,
+                "block_id" => has_block_id = true,
+                "kind" => has_kind = true,
+                "transaction_hash" => has_transaction_hash = true,
+                "fee" => has_fee = true,
+                "timestamp" => has_timestamp = true,
+                _ => {}
+            }
+        }
+        Ok(has_id && has_block_id && has_kind && has_transaction_hash && has_fee && has_timestamp)
+    }
+
+    /// Function to check if the block_hashes table has the correct columns
+    pub(crate) async fn check_block_hashes_table(pool: &Pool<Sqlite>) -> Result<bool, Error> {
+        let columns = sqlx::query("PRAGMA table_info(block_hashes);")
+            .fetch_all(pool)
+            .await?;
+        // Check if the table has the expected columns: block_id and block_hash
+        let mut has_block_id = false;
+        let mut has_block_hash = false;
+        for column in columns {
+            let name: String = column.get("name");
+            match name.as_str() {
+                "block_id" => has_block_id = true,
+                "block_hash" => has_block_hash = true,
+                _ => {}
+            }
+        }
+        Ok(has_block_id && has_block_hash)
+    }
+
     /// Function to check if the tables exist
-    pub(crate) async fn check_tables_exist(pool: &Pool<Sqlite>) -> Result<bool, Error> {
-        let expected_tables = vec!["blocks", "pies", "proofs", "job_ids", "failed_blocks"];
+    pub(crate) async fn check_tables_exist(
+        pool: &Pool<Sqlite>,
+        expected_tables: &[&str],
+    ) -> Result<bool, Error> {
         for table in expected_tables {
-            let exists =
-                sqlx::query("SELECT name FROM sqlite_master WHERE type='table' AND name=?")
-                    .bind(table)
-                    .fetch_optional(pool)
-                    .await?
-                    .is_some();
-            if !exists {
+            if !Self::check_table_exists(pool, table).await? {
                 return Ok(false);
             }
         }
         Ok(true)
     }
+
+    /// Function to check if a table exists
+    pub(crate) async fn check_table_exists(
+        pool: &Pool<Sqlite>,
+        table: &str,
+    ) -> Result<bool, Error> {
+        Ok(
+            sqlx::query("SELECT name FROM sqlite_master WHERE type='table' AND name=?")
+                .bind(table)
+                .fetch_optional(pool)
+                .await?
+                .is_some(),
+        )
+    }
 }
@@ -48,30 +48,84 @@ where
     )
 }
 
-pub async fn watch_tx<P>(
+/// Waits for the first of `transaction_hashes` to be included, e.g. when a transaction has been
+/// resubmitted with the same nonce.
+pub async fn watch_any_tx<P>(
     provider: P,
-    transaction_hash: Felt,
+    transaction_hashes: &[Felt],
     poll_interval: Duration,
 ) -> Result<TransactionReceiptWithBlockInfo>
 where
     P: Provider,
 {
     loop {
+        for transaction_hash in transaction_hashes {
+            match provider.get_transaction_receipt(transaction_hash).await {
+                Ok(receipt) => match receipt.receipt.execution_result() {
+                    ExecutionResult::Succeeded => {
+                        return Ok(receipt);
+                    }
+                    ExecutionResult::Reverted { reason } => {
+                        return Err(anyhow::anyhow!("transaction reverted: {}", reason));
+                    }
+                },
+                Err(ProviderError::StarknetError(StarknetError::TransactionHashNotFound)) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+/// Gets the receipt of the first of `transaction_hashes` found to be included, or `None` if none
+/// of them is included yet.
+pub async fn find_any_receipt<P>(
+    provider: P,
+    transaction_hashes: &[Felt],
+) -> Result<Option<TransactionReceiptWithBlockInfo>>
+where
+    P: Provider,
+{
+    for transaction_hash in transaction_hashes {
         match provider.get_transaction_receipt(transaction_hash).await {
-            Ok(receipt) => match receipt.receipt.execution_result() {
-                ExecutionResult::Succeeded => {
-                    return Ok(receipt);
-                }
-                ExecutionResult::Reverted { reason } => {
-                    return Err(anyhow::anyhow!("transaction reverted: {}", reason));
-                }
-            },
+            Ok(receipt) => return Ok(Some(receipt)),
             Err(ProviderError::StarknetError(StarknetError::TransactionHashNotFound)) => {}
             Err(err) => return Err(err.into()),
         }
+    }
+    Ok(None)
+}
 
-        tokio::time::sleep(poll_interval).await;
+/// Packs groups of calls, each fitting in a transaction on its own, into as few transactions as
+/// the calldata limit allows while keeping their order. Groups are never split. Returns the indices
+/// of the groups packed in each transaction along with its calls.
+pub fn pack_call_groups(groups: Vec<Vec<Call>>) -> Vec<(Vec<usize>, Vec<Call>)> {
+    let mut packed: Vec<(Vec<usize>, Vec<Call>)> = vec![];
+
+    // 1 slot is always used for calls length prefix
+    let mut chunk_size = 1;
+
+    for (ind, group) in groups.into_iter().enumerate() {
+        let group_size = group
+            .iter()
+            .map(|call| call.calldata.len() + ACCOUNT_CALL_OVERHEAD)
+            .sum::<usize>();
+
+        match packed.last_mut() {
+            Some((indices, calls)) if chunk_size + group_size <= STARKNET_TX_CALLDATA_LIMIT => {
+                indices.push(ind);
+                calls.extend(group);
+                chunk_size += group_size;
+            }
+            _ => {
+                packed.push((vec![ind], group));
+                chunk_size = 1 + group_size;
+            }
+        }
     }
+
+    packed
 }
 
 pub fn split_calls(calls: Vec<Call>) -> Vec<Vec<Call>> {
@@ -273,3 +327,45 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(calldata_len: usize) -> Call {
+        Call {
+            to: Felt::ONE,
+            selector: Felt::TWO,
+            calldata: vec![Felt::ZERO; calldata_len],
+        }
+    }
+
+    #[test]
+    fn test_pack_call_groups() {
+        let groups = vec![
+            vec![call(1_000), call(1_000)],
+            vec![call(2_000)],
+            vec![call(3_000)],
+            vec![call(500)],
+        ];
+
+        let packed = pack_call_groups(groups)
+            .into_iter()
+            .map(|(indices, calls)| (indices, calls.len()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(packed, vec![(vec![0, 1], 3), (vec![2, 3], 2)]);
+    }
+
+    #[test]
+    fn test_pack_call_groups_never_splits_groups() {
+        let groups = vec![vec![call(4_000)], vec![call(500), call(500)]];
+
+        let packed = pack_call_groups(groups)
+            .into_iter()
+            .map(|(indices, calls)| (indices, calls.len()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(packed, vec![(vec![0], 1), (vec![1], 2)]);
+    }
+}